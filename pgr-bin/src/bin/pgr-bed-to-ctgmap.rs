@@ -0,0 +1,41 @@
+const VERSION_STRING: &str = env!("VERSION_STRING");
+use clap::{self, CommandFactory, Parser};
+use pgr_bin::error::PgrError;
+use pgr_bin::paf::{paf_to_ctgmap_set, parse_paf};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Convert the alignments in a PAF file into a ctgmap.json file for pgr-generate-chr-aln-plot
+#[derive(Parser, Debug)]
+#[clap(name = "pgr-bed-to-ctgmap")]
+#[clap(author, version)]
+#[clap(about, long_about = None)]
+struct CmdOptions {
+    /// path to the input PAF file, the query contigs are mapped to the targets (the reference)
+    paf_path: String,
+    /// path to the output ctgmap.json file
+    output_path: String,
+}
+
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
+
+    let paf_file = BufReader::new(File::open(Path::new(&args.paf_path)).map_err(
+        PgrError::io_context(format!("can't open the PAF file {}", args.paf_path)),
+    )?);
+    let paf_records = parse_paf(paf_file)?;
+    let ctgmap_set = paf_to_ctgmap_set(&paf_records);
+
+    let mut out_ctgmap_json = BufWriter::new(File::create(Path::new(&args.output_path)).map_err(
+        PgrError::io_context(format!(
+            "can't create the ctgmap.json file {}",
+            args.output_path
+        )),
+    )?);
+    serde_json::to_writer(&mut out_ctgmap_json, &ctgmap_set)?;
+    writeln!(out_ctgmap_json)?;
+    out_ctgmap_json.flush()?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+const VERSION_STRING: &str = env!("VERSION_STRING");
+use clap::{self, CommandFactory, Parser};
+use pgr_bin::compare::{
+    compute_jaccard, get_target_alignment_stats, get_target_intervals, get_union_targets,
+    TargetAlignmentStats,
+};
+use pgr_bin::ctgmap::read_ctgmap_json;
+use pgr_bin::error::PgrError;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Compare two assemblies aligned to the same reference by the ctgmap.json files, per target chromosome
+#[derive(Parser, Debug)]
+#[clap(name = "pgr-compare-assemblies")]
+#[clap(author, version)]
+#[clap(about, long_about = None)]
+struct CmdOptions {
+    /// path to the ctgmap.json file of the first assembly
+    ctgmap_json_path_a: String,
+
+    /// path to the ctgmap.json file of the second assembly
+    ctgmap_json_path_b: String,
+
+    /// path to the output TSV file, the TSV lines are written to stdout if it is not given
+    #[clap(long)]
+    output_path: Option<String>,
+}
+
+fn write_stats<W: Write>(out: &mut W, stats: Option<&TargetAlignmentStats>) -> io::Result<()> {
+    let stats = stats.cloned().unwrap_or_default();
+    write!(
+        out,
+        "\t{}\t{:.6}\t{}\t{}",
+        stats.n50, stats.coverage_breadth, stats.number_contigs, stats.number_gaps
+    )
+}
+
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
+
+    let set_a = read_ctgmap_json(&args.ctgmap_json_path_a)?;
+    let set_b = read_ctgmap_json(&args.ctgmap_json_path_b)?;
+    let (stats_a, stats_b) = (
+        get_target_alignment_stats(&set_a),
+        get_target_alignment_stats(&set_b),
+    );
+    let (intervals_a, intervals_b) = (get_target_intervals(&set_a), get_target_intervals(&set_b));
+
+    let mut out: Box<dyn Write> = if let Some(output_path) = args.output_path.as_ref() {
+        Box::new(BufWriter::new(
+            File::create(Path::new(output_path)).map_err(PgrError::io_context(format!(
+                "can't create the TSV file {}",
+                output_path
+            )))?,
+        ))
+    } else {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    };
+    writeln!(
+        out,
+        "#target\ttarget_length\
+         \ta_n50\ta_coverage_breadth\ta_contigs\ta_gaps\
+         \tb_n50\tb_coverage_breadth\tb_contigs\tb_gaps\tjaccard"
+    )?;
+    for (t_name, t_len) in get_union_targets(&set_a, &set_b) {
+        write!(out, "{}\t{}", t_name, t_len)?;
+        write_stats(&mut out, stats_a.get(&t_name))?;
+        write_stats(&mut out, stats_b.get(&t_name))?;
+        let jaccard = compute_jaccard(
+            intervals_a.get(&t_name).map(|v| &v[..]).unwrap_or(&[]),
+            intervals_b.get(&t_name).map(|v| &v[..]).unwrap_or(&[]),
+        );
+        writeln!(out, "\t{:.6}", jaccard)?;
+    }
+    out.flush()?;
+    Ok(())
+}
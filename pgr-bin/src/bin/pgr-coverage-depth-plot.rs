@@ -0,0 +1,365 @@
+const VERSION_STRING: &str = env!("VERSION_STRING");
+use clap::{self, CommandFactory, Parser};
+use pgr_bin::ctgmap::{read_ctgmap_json, CtgMapSet};
+use pgr_bin::error::PgrError;
+use pgr_bin::plot::{get_depth_intervals, get_scaling_factor};
+use pgr_bin::render::{
+    assign_contigs_to_targets, bucket_records, get_chr_scaling_factor, get_target_span,
+    sort_targets, ChromosomeSort, RenderOptions,
+};
+use rustc_hash::FxHashMap;
+use svg::node::{element, Node};
+use svg::Document;
+
+/// Plot the alignment depth along each target of a ctgmap.json file as a SVG line chart,
+/// a companion of the per-chromosome panels of pgr-generate-chr-aln-plot at the same scale
+#[derive(Parser, Debug)]
+#[clap(name = "pgr-coverage-depth-plot")]
+#[clap(author, version)]
+#[clap(about, long_about = None)]
+struct CmdOptions {
+    /// path to a ctgmap.json file
+    ctgmap_json_path: String,
+
+    /// the prefix of the output files, the chart of each target is written to "<output_prefix>.<chr>.depth.svg"
+    output_prefix: String,
+
+    /// if given, we will use this to determine the plot scale, the charts are at the scale of the per-chromosome
+    /// panels of pgr-generate-chr-aln-plot with the same --total-target-bases, --panel-width, --target-padding
+    /// and --chr-scaling-multiplier
+    #[clap(long)]
+    total_target_bases: Option<f64>,
+
+    /// set the panel width
+    #[clap(long, default_value_t = 1400.0)]
+    panel_width: f64,
+
+    /// the gap (in bases) between the targets to get the scale without --total-target-bases
+    #[clap(long, default_value_t = 1.5e6)]
+    target_padding: f64,
+
+    /// the charts are drawn at this multiple of the scale fitting all targets to the panel width
+    #[clap(long, default_value_t = 12.0)]
+    chr_scaling_multiplier: f64,
+
+    /// the height of the chart
+    #[clap(long, default_value_t = 80.0)]
+    chart_height: f64,
+
+    /// ignore the records with t_dup for the depth
+    #[clap(long)]
+    exclude_dups: bool,
+}
+
+/// the y-axis is scaled to this percentile of the depth along a target so a few deep regions don't flatten the chart
+const DEPTH_PERCENTILE: f64 = 0.99;
+
+/// the smallest depth that `percentile` of the `t_len` bases of a target are covered at or below,
+/// the bases not in `depth_intervals` are at depth 0
+fn get_depth_percentile(depth_intervals: &[(u32, u32, u32)], t_len: u32, percentile: f64) -> u32 {
+    let covered = depth_intervals
+        .iter()
+        .map(|(bgn, end, _)| (end - bgn) as u64)
+        .sum::<u64>();
+    let mut depth_lengths = depth_intervals
+        .iter()
+        .map(|(bgn, end, depth)| (*depth, (end - bgn) as u64))
+        .collect::<Vec<_>>();
+    depth_lengths.push((0, (t_len as u64).saturating_sub(covered)));
+    depth_lengths.sort();
+    let total = depth_lengths.iter().map(|v| v.1).sum::<u64>();
+    let threshold = total as f64 * percentile;
+    let mut acc = 0_u64;
+    for (depth, len) in depth_lengths.iter() {
+        acc += len;
+        if acc as f64 >= threshold {
+            return *depth;
+        };
+    }
+    depth_lengths.last().map(|v| v.0).unwrap_or(0)
+}
+
+/// the step line of the depth along a target of `t_len` bases, from the baseline at `height`,
+/// the depth above `max_depth` is clipped to the top of the chart
+fn get_depth_path_str(
+    depth_intervals: &[(u32, u32, u32)],
+    t_len: u32,
+    scaling_factor: f64,
+    max_depth: u32,
+    height: f64,
+) -> String {
+    let y = |depth: u32| height - height * depth.min(max_depth) as f64 / max_depth.max(1) as f64;
+    let mut path_str = format!("M 0.0000 {:0.4}", height);
+    let mut c_pos = 0_u32;
+    depth_intervals.iter().for_each(|&(bgn, end, depth)| {
+        if bgn > c_pos {
+            path_str.push_str(&format!(" V {:0.4}", height));
+        };
+        path_str.push_str(&format!(
+            " H {:0.4} V {:0.4} H {:0.4}",
+            bgn as f64 * scaling_factor,
+            y(depth),
+            end as f64 * scaling_factor
+        ));
+        c_pos = end;
+    });
+    path_str.push_str(&format!(
+        " V {:0.4} H {:0.4}",
+        height,
+        t_len.max(c_pos) as f64 * scaling_factor
+    ));
+    path_str
+}
+
+/// the chart of the depth along a target with the y-axis labeled from 0 to `max_depth`
+fn get_depth_chart_document(
+    t_name: &str,
+    t_len: u32,
+    depth_intervals: &[(u32, u32, u32)],
+    scaling_factor: f64,
+    max_depth: u32,
+    panel_width: f64,
+    chart_height: f64,
+) -> Document {
+    let top = 20.0;
+    let width = t_len as f64 * scaling_factor;
+    let mut chart = element::Group::new().set("transform", format!("translate(0 {})", top));
+    chart.append(
+        element::Path::new()
+            .set("fill", "#4682B4")
+            .set("fill-opacity", 0.4)
+            .set("stroke", "#4682B4")
+            .set("stroke-width", 0.5)
+            .set(
+                "d",
+                get_depth_path_str(
+                    depth_intervals,
+                    t_len,
+                    scaling_factor,
+                    max_depth,
+                    chart_height,
+                ),
+            ),
+    );
+    chart.append(
+        element::Path::new()
+            .set("stroke", "#000")
+            .set("stroke-width", 0.5)
+            .set("fill", "none")
+            .set("d", format!("M 0 0 V {chart_height:0.4} H {width:0.4}")),
+    );
+    [(0, chart_height), (max_depth, 0.0)]
+        .into_iter()
+        .for_each(|(depth, y)| {
+            chart.append(
+                element::Text::new(format!("{}", depth))
+                    .set("x", -3.0)
+                    .set("y", y + 3.0)
+                    .set("font-size", "8px")
+                    .set("font-family", "monospace")
+                    .set("text-anchor", "end"),
+            );
+        });
+    let label = element::Text::new(format!(
+        "{} depth, the y-axis is up to {} (the {}th percentile)",
+        t_name,
+        max_depth,
+        (DEPTH_PERCENTILE * 100.0).round()
+    ))
+    .set("x", 0.0)
+    .set("y", 12.0)
+    .set("font-size", "10px")
+    .set("font-family", "monospace");
+
+    let height = chart_height + top + 10.0;
+    // the charts of the long targets go beyond the panel width as the per-chromosome panels
+    let document_width = (width + panel_width * 0.05).max(panel_width);
+    Document::new()
+        .set("viewBox", (-panel_width * 0.05, 0, document_width, height))
+        .set("width", document_width)
+        .set("height", height)
+        .add(label)
+        .add(chart)
+}
+
+/// the scaling factor fitting the targets plotted by pgr-generate-chr-aln-plot to the panel width as
+/// `render_ctgmap`, and the bases of these targets with the padding
+fn get_plot_scaling_factor(ctgmap_set: &CtgMapSet, opts: &RenderOptions) -> (f64, f64, bool) {
+    let query_length = ctgmap_set
+        .query_length
+        .iter()
+        .map(|v| (v.1.clone(), v.2))
+        .collect::<FxHashMap<_, _>>();
+    let ctg2tgt = assign_contigs_to_targets(&ctgmap_set.records);
+    let tgt_to_records = bucket_records(&ctgmap_set.records, &ctg2tgt, false).tgt_to_records;
+    let offset = ctgmap_set
+        .target_length
+        .iter()
+        .filter_map(|(_, t_name, t_len)| {
+            tgt_to_records.get(t_name).map(|records| {
+                get_target_span(*t_len, records, &query_length) + opts.target_padding
+            })
+        })
+        .sum::<f64>();
+    let (scaling_factor, clipped) =
+        get_scaling_factor(opts.panel_width, opts.total_target_bases, offset);
+    (scaling_factor, offset, clipped)
+}
+
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
+
+    let mut ctgmap_set = read_ctgmap_json(&args.ctgmap_json_path)?;
+    sort_targets(&mut ctgmap_set.target_length, ChromosomeSort::Natural);
+
+    let mut tgt_intervals = FxHashMap::<String, Vec<(u32, u32)>>::default();
+    ctgmap_set.records.iter().for_each(|r| {
+        if args.exclude_dups && r.t_dup {
+            return;
+        };
+        tgt_intervals
+            .entry(r.t_name.clone())
+            .or_default()
+            .push((r.ts, r.te));
+    });
+
+    let targets = ctgmap_set
+        .target_length
+        .iter()
+        .filter_map(|(_, t_name, t_len)| {
+            tgt_intervals
+                .get(t_name)
+                .map(|intervals| (t_name, *t_len, get_depth_intervals(intervals)))
+        })
+        .collect::<Vec<_>>();
+    let opts = RenderOptions {
+        panel_width: args.panel_width,
+        total_target_bases: args.total_target_bases,
+        target_padding: args.target_padding,
+        chr_scaling_multiplier: args.chr_scaling_multiplier,
+        ..RenderOptions::default()
+    };
+    let (scaling_factor, offset, clipped) = get_plot_scaling_factor(&ctgmap_set, &opts);
+    if clipped {
+        eprintln!(
+            "WARNING: --total-target-bases {} is smaller than the {} bases of the plotted targets (with the padding)",
+            args.total_target_bases.unwrap(),
+            offset
+        );
+    };
+
+    for (t_name, t_len, depth_intervals) in targets.iter() {
+        let max_depth = get_depth_percentile(depth_intervals, *t_len, DEPTH_PERCENTILE).max(1);
+        let document = get_depth_chart_document(
+            t_name,
+            *t_len,
+            depth_intervals,
+            get_chr_scaling_factor(scaling_factor, *t_len, &opts),
+            max_depth,
+            args.panel_width,
+            args.chart_height,
+        );
+        let chart_path = format!("{}.{}.depth.svg", args.output_prefix, t_name);
+        svg::save(&chart_path, &document).map_err(PgrError::io_context(format!(
+            "can't write the depth chart {}",
+            chart_path
+        )))?;
+    }
+    eprintln!("{} depth charts are written", targets.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pgr_bin::ctgmap::CtgMapRec;
+    use pgr_bin::render::render_ctgmap;
+
+    fn test_record(t_name: &str, ts: u32, te: u32, q_name: &str, qs: u32, qe: u32) -> CtgMapRec {
+        CtgMapRec {
+            t_name: t_name.to_string(),
+            ts,
+            te,
+            q_name: q_name.to_string(),
+            qs,
+            qe,
+            ctg_len: 1_000_000,
+            orientation: 0,
+            ctg_orientation: 0,
+            t_dup: false,
+            t_ovlp: false,
+            q_dup: false,
+            q_ovlp: false,
+            identity: None,
+        }
+    }
+
+    #[test]
+    fn test_chart_extent() {
+        // the query contig of chr2 is longer than the target, it widens the span of chr2 in the plot
+        let ctgmap_set = CtgMapSet {
+            records: vec![
+                test_record("chr1", 0, 50_000, "ctg1", 0, 50_000),
+                test_record("chr2", 0, 20_000, "ctg2", 0, 20_000),
+            ],
+            target_length: vec![
+                (0, "chr1".to_string(), 100_000),
+                (1, "chr2".to_string(), 30_000),
+            ],
+            query_length: vec![
+                (0, "ctg1".to_string(), 50_000),
+                (1, "ctg2".to_string(), 80_000),
+            ],
+        };
+        for total_target_bases in [None, Some(2e6)] {
+            let opts = RenderOptions {
+                total_target_bases,
+                overview: false,
+                ..RenderOptions::default()
+            };
+            let plot = render_ctgmap(&ctgmap_set, &opts).unwrap();
+            let target = &plot.layout.targets[0];
+            let panel_extent = target.t_len as f64 * target.chr_scaling_factor.unwrap();
+
+            let (scaling_factor, _, _) = get_plot_scaling_factor(&ctgmap_set, &opts);
+            let document = get_depth_chart_document(
+                "chr1",
+                100_000,
+                &[(0, 50_000, 1)],
+                get_chr_scaling_factor(scaling_factor, 100_000, &opts),
+                1,
+                opts.panel_width,
+                80.0,
+            );
+            assert!(document
+                .to_string()
+                .contains(&format!("M 0 0 V 80.0000 H {:0.4}", panel_extent)));
+        }
+    }
+
+    #[test]
+    fn test_depth_percentile() {
+        let depth_intervals = vec![(0, 50, 1), (50, 60, 2), (60, 61, 50)];
+        // 39 bases at depth 0
+        assert_eq!(get_depth_percentile(&depth_intervals, 100, 0.3), 0);
+        assert_eq!(get_depth_percentile(&depth_intervals, 100, 0.8), 1);
+        assert_eq!(get_depth_percentile(&depth_intervals, 100, 0.99), 2);
+        assert_eq!(get_depth_percentile(&depth_intervals, 100, 1.0), 50);
+        assert_eq!(get_depth_percentile(&[], 100, 0.99), 0);
+    }
+
+    #[test]
+    fn test_depth_path_str() {
+        let depth_intervals = vec![(10, 20, 1), (20, 30, 4), (40, 50, 2)];
+        assert_eq!(
+            get_depth_path_str(&depth_intervals, 100, 0.1, 2, 10.0),
+            "M 0.0000 10.0000 V 10.0000 H 1.0000 V 5.0000 H 2.0000 H 2.0000 V 0.0000 H 3.0000 \
+             V 10.0000 H 4.0000 V 0.0000 H 5.0000 V 10.0000 H 10.0000"
+        );
+        assert_eq!(
+            get_depth_path_str(&[], 100, 0.1, 1, 10.0),
+            "M 0.0000 10.0000 V 10.0000 H 10.0000"
+        );
+    }
+}
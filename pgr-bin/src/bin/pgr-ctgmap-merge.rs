@@ -0,0 +1,66 @@
+const VERSION_STRING: &str = env!("VERSION_STRING");
+use clap::{self, CommandFactory, Parser};
+use pgr_bin::ctgmap::{merge_ctgmap_sets, read_validated_ctgmap_json, validate_ctgmap_set};
+use pgr_bin::error::PgrError;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Merge multiple ctgmap.json files, e.g. from different chromosome subsets or assembly phases, into one ctgmap.json file
+#[derive(Parser, Debug)]
+#[clap(name = "pgr-ctgmap-merge")]
+#[clap(author, version)]
+#[clap(about, long_about = None)]
+struct CmdOptions {
+    /// paths to the input ctgmap.json files, for the records of the same (q_name, t_name, qs, qe) the one from the earlier file is kept
+    #[clap(required = true)]
+    ctgmap_json_paths: Vec<String>,
+    /// path to the output ctgmap.json file
+    #[clap(long, short)]
+    output_path: String,
+    /// for the records of the same (q_name, t_name, qs, qe), keep the one with the longer target span instead of the first one
+    #[clap(long)]
+    prefer_longer: bool,
+    /// sort the merged records by the target name and the target start
+    #[clap(long)]
+    sort: bool,
+}
+
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
+
+    let ctgmap_sets = args
+        .ctgmap_json_paths
+        .iter()
+        .map(read_validated_ctgmap_json)
+        .collect::<Result<Vec<_>, PgrError>>()?;
+    let (mut ctgmap_set, dropped) = merge_ctgmap_sets(ctgmap_sets, args.prefer_longer);
+    if dropped > 0 {
+        eprintln!("{} duplicated alignment records are dropped", dropped);
+    };
+    if args.sort {
+        ctgmap_set
+            .records
+            .sort_by(|a, b| (&a.t_name, a.ts, a.te).cmp(&(&b.t_name, b.ts, b.te)));
+    };
+    // the inputs may disagree on the length of a contig
+    let errors = validate_ctgmap_set(&ctgmap_set);
+    if !errors.is_empty() {
+        eprintln!(
+            "WARNING: {} problems in the merged records, check them with pgr-validate-ctgmap",
+            errors.len()
+        );
+    };
+
+    let mut out_ctgmap_json = BufWriter::new(File::create(Path::new(&args.output_path)).map_err(
+        PgrError::io_context(format!(
+            "can't create the ctgmap.json file {}",
+            args.output_path
+        )),
+    )?);
+    serde_json::to_writer(&mut out_ctgmap_json, &ctgmap_set)?;
+    writeln!(out_ctgmap_json)?;
+    out_ctgmap_json.flush()?;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+const VERSION_STRING: &str = env!("VERSION_STRING");
+use clap::{self, CommandFactory, Parser};
+use pgr_bin::ctgmap::read_validated_ctgmap_json;
+use pgr_bin::error::PgrError;
+use pgr_bin::render::assign_contigs_to_targets;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+use std::io::{self, BufWriter, Write};
+
+#[derive(Clone, Copy, clap::ValueEnum, Default, Debug)]
+enum OptOutputFormat {
+    #[default]
+    Tsv,
+    Json,
+}
+
+/// Summarize the alignment records in a ctgmap.json file for each target contig
+#[derive(Parser, Debug)]
+#[clap(name = "pgr-ctgmap-stats")]
+#[clap(author, version)]
+#[clap(about, long_about = None)]
+struct CmdOptions {
+    /// path to a ctgmap.json file
+    ctgmap_json_path: String,
+
+    /// the output format written to stdout
+    #[clap(long, default_value_t, value_enum)]
+    output_format: OptOutputFormat,
+}
+
+#[derive(Serialize, Default)]
+struct TargetStats {
+    target_name: String,
+    total_aligned_bases: u64,
+    num_query_contigs: usize,
+    num_records: usize,
+    dup_fraction: f64,
+    alt_hit_fraction: f64,
+    mean_alignment_length: f64,
+    max_alignment_length: u32,
+}
+
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
+
+    let mut ctgmap_set = read_validated_ctgmap_json(&args.ctgmap_json_path)?;
+    ctgmap_set.target_length.sort();
+
+    // assign each query contig to the target with the most aligned bases, the same as the plot tool
+    let ctg2tgt = assign_contigs_to_targets(&ctgmap_set.records);
+
+    let mut target_stats = ctgmap_set
+        .target_length
+        .iter()
+        .map(|(_, t_name, _)| {
+            (
+                t_name.clone(),
+                (
+                    TargetStats {
+                        target_name: t_name.clone(),
+                        ..Default::default()
+                    },
+                    FxHashSet::<String>::default(),
+                    0_usize,
+                    0_usize,
+                ),
+            )
+        })
+        .collect::<FxHashMap<_, _>>();
+
+    ctgmap_set.records.iter().for_each(|r| {
+        let (stats, q_names, dup_count, alt_count) =
+            target_stats.entry(r.t_name.clone()).or_insert_with(|| {
+                (
+                    TargetStats {
+                        target_name: r.t_name.clone(),
+                        ..Default::default()
+                    },
+                    FxHashSet::<String>::default(),
+                    0,
+                    0,
+                )
+            });
+        let aln_len = r.te.saturating_sub(r.ts);
+        stats.total_aligned_bases += aln_len as u64;
+        stats.num_records += 1;
+        stats.max_alignment_length = stats.max_alignment_length.max(aln_len);
+        q_names.insert(r.q_name.clone());
+        if r.t_dup || r.q_dup {
+            *dup_count += 1;
+        };
+        if let Some(tgt) = ctg2tgt.get(&r.q_name) {
+            if *tgt != r.t_name {
+                *alt_count += 1;
+            }
+        };
+    });
+
+    let mut target_names = ctgmap_set
+        .target_length
+        .iter()
+        .map(|(_, t_name, _)| t_name.clone())
+        .collect::<Vec<_>>();
+    // records may refer to targets missing in the target_length list
+    let target_name_set = target_names.iter().cloned().collect::<FxHashSet<_>>();
+    let mut extra_target_names = target_stats
+        .keys()
+        .filter(|&t_name| !target_name_set.contains(t_name))
+        .cloned()
+        .collect::<Vec<_>>();
+    extra_target_names.sort();
+    target_names.extend(extra_target_names);
+
+    let target_stats = target_names
+        .into_iter()
+        .map(|t_name| {
+            let (mut stats, q_names, dup_count, alt_count) = target_stats.remove(&t_name).unwrap();
+            stats.num_query_contigs = q_names.len();
+            if stats.num_records > 0 {
+                let num_records = stats.num_records as f64;
+                stats.dup_fraction = dup_count as f64 / num_records;
+                stats.alt_hit_fraction = alt_count as f64 / num_records;
+                stats.mean_alignment_length = stats.total_aligned_bases as f64 / num_records;
+            };
+            stats
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = BufWriter::new(io::stdout());
+    match args.output_format {
+        OptOutputFormat::Tsv => {
+            writeln!(
+                out,
+                "#target_name\ttotal_aligned_bases\tnum_query_contigs\tnum_records\tdup_fraction\talt_hit_fraction\tmean_alignment_length\tmax_alignment_length"
+            )?;
+            for stats in target_stats.iter() {
+                writeln!(
+                    out,
+                    "{}\t{}\t{}\t{}\t{:0.4}\t{:0.4}\t{:0.1}\t{}",
+                    stats.target_name,
+                    stats.total_aligned_bases,
+                    stats.num_query_contigs,
+                    stats.num_records,
+                    stats.dup_fraction,
+                    stats.alt_hit_fraction,
+                    stats.mean_alignment_length,
+                    stats.max_alignment_length
+                )?;
+            }
+        }
+        OptOutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, &target_stats)?;
+            writeln!(out)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,44 @@
+const VERSION_STRING: &str = env!("VERSION_STRING");
+use clap::{self, CommandFactory, Parser};
+use pgr_bin::ctgmap::read_validated_ctgmap_json;
+use pgr_bin::error::PgrError;
+use pgr_bin::paf::ctgmap_set_to_paf;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Convert the records of a ctgmap.json file into PAF lines, the reverse of pgr-bed-to-ctgmap
+#[derive(Parser, Debug)]
+#[clap(name = "pgr-ctgmap-to-paf")]
+#[clap(author, version)]
+#[clap(about, long_about = None)]
+struct CmdOptions {
+    /// path to a ctgmap.json file
+    ctgmap_json_path: String,
+    /// path to the output PAF file, the PAF lines are written to stdout if it is not given
+    output_path: Option<String>,
+}
+
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
+
+    let ctgmap_set = read_validated_ctgmap_json(&args.ctgmap_json_path)?;
+    let paf_records = ctgmap_set_to_paf(&ctgmap_set);
+
+    let mut out_paf: Box<dyn Write> = if let Some(output_path) = args.output_path.as_ref() {
+        Box::new(BufWriter::new(
+            File::create(Path::new(output_path)).map_err(PgrError::io_context(format!(
+                "can't create the PAF file {}",
+                output_path
+            )))?,
+        ))
+    } else {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    };
+    for r in paf_records.iter() {
+        writeln!(out_paf, "{}", r)?;
+    }
+    out_paf.flush()?;
+    Ok(())
+}
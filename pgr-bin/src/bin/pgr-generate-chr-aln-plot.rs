@@ -1,44 +1,31 @@
 const VERSION_STRING: &str = env!("VERSION_STRING");
 use clap::{self, CommandFactory, Parser};
+use pgr_bin::ctgmap::{
+    read_validated_ctgmap_json, validate_ctgmap_set, CtgMapRec, CtgMapSet, ValidationErrorKind,
+};
+use pgr_bin::error::PgrError;
+use pgr_bin::plot::{get_depth_intervals, parse_region};
+use pgr_bin::reader::open_possibly_compressed;
+use pgr_bin::render::{
+    assign_contigs_to_targets, bucket_records, calculate_hash, get_drawn_records,
+    get_output_extension, render_ctgmap, render_ctgmap_with_writer, sort_targets, write_plot_file,
+    ChromosomeSort, ColoredBedRegions, CytoBands, GeneFeature, LabelPosition, LengthTransform,
+    Opacity, QuerySort, RefAnnotationTrack, RenderOptions, Theme, TrackLayout, CMAP,
+    OKABE_ITO_CMAP, VIRIDIS_CMAP,
+};
 use rustc_hash::{FxHashMap, FxHashSet};
-use serde::Deserialize;
-use std::collections::hash_map::DefaultHasher;
+use std::borrow::Borrow;
 use std::fs::File;
-use std::hash::{Hash, Hasher};
-use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::{self, Path};
-use svg::node::{element, Node};
 use svg::Document;
 
-#[allow(dead_code)] // need the standard names for deserialization if they are not use
-#[derive(Deserialize, Clone, Debug)]
-struct CtgMapRec {
-    t_name: String,
-    ts: u32,
-    te: u32,
-    q_name: String,
-    qs: u32,
-    qe: u32,
-    ctg_len: u32,
-    orientation: u32,
-    ctg_orientation: u32,
-    t_dup: bool,
-    t_ovlp: bool,
-    q_dup: bool,
-    q_ovlp: bool,
-}
-
-#[derive(Deserialize)]
-struct CtgMapSet {
-    records: Vec<CtgMapRec>,
-    target_length: Vec<(u32, String, u32)>,
-    query_length: Vec<(u32, String, u32)>,
-}
-
-type CytoRecord = (u32, u32, String, String);
-#[derive(Deserialize)]
-struct CytoBands {
-    cytobands: FxHashMap<String, Vec<CytoRecord>>,
+#[derive(Clone, Copy, clap::ValueEnum, Default, Debug)]
+enum OptPalettePreset {
+    #[default]
+    Default,
+    OkabeIto,
+    Viridis,
 }
 
 /// generate align block plot from ctgmap.json file
@@ -49,15 +36,26 @@ struct CytoBands {
 
 struct CmdOptions {
     /// path to a ctgmap.json file
-    ctgmap_json_path: String,
+    #[clap(required_unless_present = "test_hash")]
+    ctgmap_json_path: Option<String>,
 
     /// the prefix of the output files
-    output_prefix: String,
+    #[clap(required_unless_present = "test_hash")]
+    output_prefix: Option<String>,
+
+    /// print the hash of the name that picks its color from the palette and exit, the hash is the same in every
+    /// process and on every platform
+    #[clap(long, exclusive = true, value_name = "NAME")]
+    test_hash: Option<String>,
 
     /// if given, we will use this to determine the plot scale, this is useful for generate many plot in the same scale
     #[clap(long)]
     total_target_bases: Option<f64>,
 
+    /// exit with an error instead of a warning when --total-target-bases is smaller than the plotted targets
+    #[clap(long)]
+    strict: bool,
+
     /// set the panel width
     #[clap(long, default_value_t = 1400.0)]
     panel_width: f64,
@@ -66,691 +64,1740 @@ struct CmdOptions {
     #[clap(long)]
     cytoband_json: Option<String>,
 
-    /// if given, we will only generate plot for the specified contig in the reference
+    /// draw the reference track with the cytobands of a UCSC cytoBand.txt file, an alternative to --cytoband-json
+    #[clap(long, conflicts_with = "cytoband_json")]
+    cytoband_txt: Option<String>,
+
+    /// if given, we will only generate plot for the specified contigs in the reference, as a comma-separated list or by repeating the option;
+    /// use "summary" to generate the overview panel only
+    #[clap(long, value_delimiter = ',')]
+    ctg: Vec<String>,
+
+    /// if given, it will highlight regions specified by the bed file in the reference(target) track;
+    /// the regions are colored by the name (4th) column, or by the score (5th) column if the name is ".", red by default;
+    /// repeat it for more bed files, each is drawn in its own lane stacked above the reference track;
+    /// the bed, GFF3 and cytoBand.txt files can be gzip or zstd compressed
+    #[clap(long)]
+    ref_annotation_bed: Vec<String>,
+
+    /// the labels of the --ref-annotation-bed files in the same order, shown in the tooltips of the regions;
+    /// the file name is used for a bed file without a label
+    #[clap(long)]
+    bed_label: Vec<String>,
+
+    /// if given, mark the telomeres in the bed file with red triangles above the reference tracks
+    #[clap(long)]
+    telomere_bed: Option<String>,
+
+    /// if given, draw the "gene" features (and the "mRNA" features without a parent) of the GFF3 file as arrows
+    /// of their strands above the reference tracks and the --ref-annotation-bed lanes, the seqid should be the target name
     #[clap(long)]
-    ctg: Option<String>,
+    gene_gff: Option<String>,
 
-    /// if given, it will highlight regions specified by the bed file in the reference(target) track
+    /// if given, it will mark regions specified by the bed file (with the query contig names) under the query tracks
     #[clap(long)]
-    ref_annotation_bed: Option<String>,
+    query_annotation_bed: Option<String>,
+
+    /// a file with a query contig name per line, the contigs on the query tracks are laid out in this order;
+    /// the contigs not listed follow in the order of --query-sort
+    #[clap(long)]
+    query_order: Option<String>,
+
+    /// lay out the query contigs by the position of their longest alignment on the target, by the contig length
+    /// (longest first) or by the name
+    #[clap(long, default_value_t, value_enum)]
+    query_sort: QuerySort,
+
+    /// lay out the query contigs by the median target position of all their alignment blocks instead of
+    /// --query-sort, so the contigs with scattered alignments cross fewer ribbons of the others
+    #[clap(long, conflicts_with = "query_sort")]
+    minimize_crossings: bool,
 
     /// generate SVG instead of HTML
     #[clap(long)]
     svg: bool,
-}
 
-static CMAP: [&str; 97] = [
-    "#870098", "#00aaa5", "#3bff00", "#ec0000", "#00a2c3", "#00f400", "#ff1500", "#0092dd",
-    "#00dc00", "#ff8100", "#007ddd", "#00c700", "#ffb100", "#0038dd", "#00af00", "#fcd200",
-    "#0000d5", "#009a00", "#f1e700", "#0000b1", "#00a55d", "#d4f700", "#4300a2", "#00aa93",
-    "#a1ff00", "#dc0000", "#00aaab", "#1dff00", "#f40000", "#009fcb", "#00ef00", "#ff2d00",
-    "#008ddd", "#00d700", "#ff9900", "#0078dd", "#00c200", "#ffb900", "#0025dd", "#00aa00",
-    "#f9d700", "#0000c9", "#009b13", "#efed00", "#0300aa", "#00a773", "#ccf900", "#63009e",
-    "#00aa98", "#84ff00", "#e10000", "#00a7b3", "#00ff00", "#f90000", "#009bd7", "#00ea00",
-    "#ff4500", "#0088dd", "#00d200", "#ffa100", "#005ddd", "#00bc00", "#ffc100", "#0013dd",
-    "#00a400", "#f7dd00", "#0000c1", "#009f33", "#e8f000", "#1800a7", "#00aa88", "#c4fc00",
-    "#78009b", "#00aaa0", "#67ff00", "#e60000", "#00a4bb", "#00fa00", "#fe0000", "#0098dd",
-    "#00e200", "#ff5d00", "#0082dd", "#00cc00", "#ffa900", "#004bdd", "#00b400", "#ffc900",
-    "#0000dd", "#009f00", "#f4e200", "#0000b9", "#00a248", "#dcf400", "#2d00a4", "#00aa8d",
-    "#bcff00",
-];
-
-fn calculate_hash<T: Hash>(t: &T) -> u64 {
-    let mut s = DefaultHasher::new();
-    t.hash(&mut s);
-    s.finish()
-}
+    /// write the HTML output gzip-compressed to "<output_prefix>.html.gz"
+    #[clap(long, conflicts_with = "svg")]
+    gzip_html: bool,
 
-fn main() -> Result<(), std::io::Error> {
-    CmdOptions::command().version(VERSION_STRING).get_matches();
-    let args = CmdOptions::parse();
+    /// do not show the progress bar of the per-chromosome panels on stderr
+    #[clap(long)]
+    no_progress: bool,
+
+    /// number of threads used to generate the per-chromosome panels in parallel, default to "0" using all CPUs available
+    /// or the number set by RAYON_NUM_THREADS
+    #[clap(long, default_value_t = 0)]
+    number_of_thread: usize,
 
-    let mut ctgmap_json_file = BufReader::new(
-        File::open(Path::new(&args.ctgmap_json_path)).expect("can't open the input file"),
-    );
+    /// write each per-chromosome panel to "<output_prefix>.<chr>.svg" and the overview panel to
+    /// "<output_prefix>.overview.svg" instead of stacking them in one file
+    #[clap(long)]
+    split_output: bool,
 
-    let mut buffer = Vec::new();
-    ctgmap_json_file.read_to_end(&mut buffer)?;
-    let mut ctgmap_set: CtgMapSet = serde_json::from_str(&String::from_utf8_lossy(&buffer[..]))
-        .expect("can't parse the ctgmap.json file");
+    /// write each per-chromosome panel to "<output_prefix>_<chr>.svg" (or ".html") without the overview panel,
+    /// the output files are listed in "<output_prefix>_manifest.tsv"
+    #[clap(long, conflicts_with = "split_output")]
+    split_chromosomes: bool,
 
-    let cytobands = if let Some(cytoband_path) = args.cytoband_json.clone() {
-        let mut cytoband_file = BufReader::new(
-            File::open(Path::new(&cytoband_path)).expect("can't open the cytoband json file"),
-        );
-        let mut buffer = Vec::new();
-        cytoband_file.read_to_end(&mut buffer)?;
-        let cytobands: CytoBands = serde_json::from_str(&String::from_utf8_lossy(&buffer[..]))
-            .expect("can't parse the cytoband json file");
-        Some(cytobands)
-    } else {
-        None
-    };
+    /// the color palette for the query contigs, use "okabe-ito" or "viridis" for colorblind-safe figures
+    #[clap(long, default_value_t, value_enum)]
+    palette_preset: OptPalettePreset,
 
-    let ref_highlight = if let Some(ref_annotation_bed) = args.ref_annotation_bed.clone() {
-        let bed_file_path = path::Path::new(&ref_annotation_bed);
-        let bed_file = BufReader::new(File::open(bed_file_path).expect("can't open the bed file"));
-        let mut ref_highlight = FxHashMap::<String, Vec<(u32, u32)>>::default();
-        let bed_file_parse_err_msg = "bed file parsing error";
-        bed_file.lines().for_each(|line| {
-            let line = line.unwrap().trim().to_string();
-            if line.is_empty() {
-                return;
-            }
-            if &line[0..1] == "#" {
-                return;
-            }
-            let bed_fields = line.split('\t').collect::<Vec<&str>>();
-            let ctg: String = bed_fields[0].to_string();
-            let bgn: u32 = bed_fields[1].parse().expect(bed_file_parse_err_msg);
-            let end: u32 = bed_fields[2].parse().expect(bed_file_parse_err_msg);
-            let e = ref_highlight.entry(ctg).or_insert_with(Vec::new);
-            e.push((bgn, end));
-        });
-        Some(ref_highlight)
-    } else {
-        None
-    };
+    /// if given, only keep the longest N alignment records for each target contig in the per-chromosome plot
+    #[clap(long)]
+    max_records_per_contig: Option<usize>,
 
-    ctgmap_set.query_length.sort();
-    ctgmap_set.target_length.sort();
-    let mut ctg_target_hit_len = FxHashMap::<String, FxHashMap<String, u32>>::default();
+    /// add a legend of the query contig colors to the right of the panels
+    #[clap(long)]
+    legend: bool,
+
+    /// list at most this many query contigs, the ones with the most aligned bases, in the legend
+    #[clap(long, default_value_t = 30)]
+    legend_max_contigs: usize,
+
+    /// sort the targets naturally (chr1, chr2, ..., chr10, ..., chrM, chrX, chrY), by the name, by the length
+    /// (longest first) or keep the order of the ctgmap.json file
+    #[clap(long, default_value_t, value_enum)]
+    sort_chromosomes: ChromosomeSort,
+
+    /// the same as --sort-chromosomes natural, the default now, kept for the old command lines
+    #[clap(long, hide = true, conflicts_with = "sort_chromosomes")]
+    natural_sort: bool,
+
+    /// the color theme of the output
+    #[clap(long, default_value_t, value_enum)]
+    theme: Theme,
+
+    /// if given, drop the alignment records shorter than this length before assigning the contigs to the targets; the length is measured by the span in the target (te - ts), not in the query
+    #[clap(long)]
+    min_aln_len: Option<u32>,
+
+    /// if given, merge the consecutive collinear records of a query contig on a target with the same orientation
+    /// into one ribbon when both the target and the query gaps between them are at most this many bases;
+    /// the records are merged before --min-aln-len, and a merged record is a dup only if all of its records are
+    #[clap(long)]
+    merge_gap: Option<u32>,
+
+    /// drop the query contigs with less than this fraction (0 to 1) of their bases covered by the alignment records,
+    /// applied after --min-aln-len
+    #[clap(long, default_value_t = 0.0)]
+    min_contig_coverage: f64,
+
+    /// if given, drop the query contigs shorter than this length (by the query_length of the ctgmap.json file),
+    /// they get no lane and no ribbon in the overview and the per-chromosome panels
+    #[clap(long)]
+    min_query_len: Option<u32>,
+
+    /// the gap (in bases) between the targets in the overview panel
+    #[clap(long, default_value_t = 1.5e6)]
+    target_padding: f64,
+
+    /// transform the lengths of the targets and the query contigs in the overview panel with "sqrt" or "log"
+    /// so the short contigs remain visible next to the long chromosomes; the lengths are scaled so the longest
+    /// target keeps its length, the ribbons still connect the same positions and the overview has no scale bar
+    #[clap(long, default_value_t, value_enum)]
+    length_transform: LengthTransform,
+
+    /// do not draw the base-pair scale bars
+    #[clap(long)]
+    no_scale_bar: bool,
+
+    /// the interval (in bases) of the ruler ticks along the reference track of each chromosome, labeled in Mbp;
+    /// by default, 1, 5, 10 or 50 Mbp is chosen by the chromosome length and the panel width
+    #[clap(long)]
+    tick_interval: Option<f64>,
+
+    /// do not draw the ruler ticks along the reference tracks of the per-chromosome panels
+    #[clap(long, conflicts_with = "tick_interval")]
+    no_ruler: bool,
+
+    /// if given as "CHR:START-END", only plot the alignments within the region of the reference, the region fills the panel
+    /// and the query contigs are trimmed to the parts aligned to the region
+    #[clap(long, conflicts_with = "ctg")]
+    region: Option<String>,
+
+    /// if given, write the target assigned to each query contig to this TSV file
+    #[clap(long)]
+    summary_tsv: Option<String>,
+
+    /// if given, also write the alignment depth (the number of the overlapping alignment records) along the targets
+    /// to this bedGraph file
+    #[clap(long)]
+    bedgraph: Option<String>,
+
+    /// ignore the records with t_dup for the depth of --bedgraph
+    #[clap(long, requires = "bedgraph")]
+    bedgraph_exclude_dups: bool,
+
+    /// if given, write the records and the sequence lengths left after --ctg, --min-aln-len, --min-contig-coverage and --min-query-len
+    /// to this ctgmap.json file in the plot order, it can be the input of the other tools or of another plot
+    #[clap(long)]
+    output_filtered_json: Option<String>,
+
+    /// if given, write the records drawn as the ribbons on the targets in --ctg to this BEDPE file, clipped to
+    /// --region and capped by --max-records-per-contig as they are drawn, with the query strand from the orientation
+    /// and the t_dup and the q_dup flags (as 0 or 1) in two extra columns
+    #[clap(long)]
+    bedpe_out: Option<String>,
+
+    /// if given, add the CSS rules of this file as a <style> element to the SVG documents, the reference tracks,
+    /// the query tracks and the ribbons have the "ref_track", the "query_track" and the "ribbon" classes,
+    /// the CSS rules take precedence over their default styles
+    #[clap(long)]
+    external_css: Option<String>,
+
+    /// if given, write the records skipped for q_dup as "q_name<tab>t_name<tab>qs<tab>qe" lines to this file,
+    /// the q_dup records to the assigned targets are drawn with --show-dups and not written
+    #[clap(long)]
+    dropped_out: Option<String>,
+
+    /// set the height of the document instead of fitting it to the panels, the panels beyond it are clipped
+    #[clap(long)]
+    box_height: Option<f64>,
+
+    /// mark the regions of the targets without the alignment records with a gray hatching behind the reference
+    /// track of the per-chromosome panels
+    #[clap(long)]
+    gap_markers: bool,
+
+    /// the minimum length of the regions marked by --gap-markers
+    #[clap(long, default_value_t = 10000, requires = "gap_markers")]
+    gap_min_length: u32,
+
+    /// draw the faint horizontal guide lines at the reference and the query tracks of the per-chromosome panels
+    #[clap(long)]
+    grid: bool,
+
+    /// use the native browser tooltips instead of the styled ones in the HTML output
+    #[clap(long)]
+    no_tooltips: bool,
+
+    /// if given, write the layout (the target offsets, the scaling factors and the ribbon coordinates in the SVG space) to this JSON file
+    #[clap(long)]
+    layout_json: Option<String>,
+
+    /// the per-chromosome panels are drawn at this multiple of the overview scale
+    #[clap(long, default_value_t = 12.0)]
+    chr_scaling_multiplier: f64,
+
+    /// scale each per-chromosome panel to fill 90% of the panel width, the scales are not comparable across the chromosomes
+    #[clap(long, conflicts_with = "region")]
+    auto_scale_chr: bool,
+
+    /// do not draw the alignments of the query contigs to the targets other than the assigned ones in the per-chromosome panels
+    #[clap(long)]
+    no_alt: bool,
+
+    /// draw the duplicated blocks (t_dup or q_dup) with a dashed outline and a lighter fill, the q_dup blocks to the assigned targets are drawn too
+    #[clap(long)]
+    show_dups: bool,
+
+    /// fill the blocks duplicated only on the target (t_dup) in blue and only on the query (q_dup) in orange,
+    /// the blocks duplicated on both sides are drawn as thin gray lines instead of being hidden
+    #[clap(long)]
+    highlight_duplications: bool,
+
+    /// an additional ctgmap.json file to the same targets as "path:label", its query contigs are drawn as an extra track
+    /// under each per-chromosome panel with a separate section of the palette; repeat it for more assemblies
+    #[clap(long, conflicts_with = "region")]
+    additional_ctgmap: Vec<String>,
+
+    /// the vertical spacing between the tracks of the additional assemblies
+    #[clap(long, default_value_t = 110.0)]
+    track_spacing: f64,
+
+    /// the vertical distance from the reference and the query tracks to their alternative hits tracks
+    /// in the per-chromosome panels
+    #[clap(long, default_value_t = 8.0)]
+    track_height: f64,
+
+    /// the vertical span of the alignment ribbons between the reference and the query tracks
+    /// in the per-chromosome panels, increase it (and --track-spacing with --additional-ctgmap) for detailed views
+    #[clap(long, default_value_t = 74.0)]
+    inter_track_gap: f64,
+
+    /// the opacity (0 to 1) of the alignment ribbons
+    #[clap(long, default_value_t = 0.7)]
+    ribbon_opacity: f64,
+
+    /// the opacity (0 to 1) of the reference and query tracks
+    #[clap(long, default_value_t = 0.7)]
+    track_opacity: f64,
+
+    /// the font family of the text in the figure
+    #[clap(long, default_value = "monospace")]
+    font_family: String,
+
+    /// the font size of the chromosome names of the per-chromosome panels, the chromosome names in the overview panel
+    /// and the contig names in the legend are drawn at 0.3 and 0.5 of this size
+    #[clap(long, default_value_t = 20.0)]
+    label_font_size: f64,
+
+    /// where to draw the chromosome names of the per-chromosome panels, at a fixed x position and vertically
+    /// centered on the reference track
+    #[clap(long, default_value_t, value_enum)]
+    track_labels: LabelPosition,
+
+    /// if given, the title at the top of the figure, "{name}" is replaced by the name of the ctgmap.json file
+    /// (without ".ctgmap.json"), e.g. the sample name when generating many plots in a batch
+    #[clap(long)]
+    title: Option<String>,
+
+    /// if given, the subtitle under the title, "{name}" is replaced as in --title
+    #[clap(long)]
+    subtitle: Option<String>,
+
+    /// fill the inverted alignments (orientation != ctg_orientation) with a hatching pattern and outline them in orange
+    #[clap(long)]
+    highlight_inversions: bool,
 
+    /// mirror all query contigs on the query tracks, the contigs aligned in the forward orientation are drawn
+    /// reversed and vice versa, with the ribbons twisted accordingly
+    #[clap(long)]
+    flip_query: bool,
+
+    /// only draw the ribbons of the inverted alignments (orientation != ctg_orientation, as --highlight-inversions),
+    /// the reference and the query tracks are drawn as usual
+    #[clap(long)]
+    inversions_only: bool,
+
+    /// fill the ribbons of the reverse strand alignments (orientation == 1) with a desaturated contig color,
+    /// so the strand is readable without the tooltips
+    #[clap(long)]
+    shade_reverse_strand: bool,
+
+    /// draw the ribbons of this query contig with a thick outline at the full opacity and dim the others
+    #[clap(long)]
+    highlight_query: Option<String>,
+
+    /// split each target into this many bins and draw the mean alignment depth of the bins as a heat strip
+    /// under the reference track, the bins without any alignment are left empty
+    #[clap(long)]
+    coverage_bins: Option<usize>,
+}
+
+impl OptPalettePreset {
+    fn colors(&self) -> &'static [&'static str] {
+        match self {
+            OptPalettePreset::Default => &CMAP[..],
+            OptPalettePreset::OkabeIto => &OKABE_ITO_CMAP[..],
+            OptPalettePreset::Viridis => &VIRIDIS_CMAP[..],
+        }
+    }
+}
+
+/// the fraction of the bases of each query contig covered by the records, the overlapping blocks are counted once
+fn get_contig_coverage(ctgmap_set: &CtgMapSet) -> FxHashMap<String, f64> {
+    let mut ctg_intervals = FxHashMap::<String, Vec<(u32, u32)>>::default();
+    ctgmap_set.records.iter().for_each(|r| {
+        ctg_intervals
+            .entry(r.q_name.clone())
+            .or_default()
+            .push((r.qs.min(r.qe), r.qs.max(r.qe)));
+    });
     let query_length = ctgmap_set
         .query_length
         .iter()
-        .map(|v| (v.1.clone(), v.2))
+        .map(|v| (v.1.as_str(), v.2))
+        .collect::<FxHashMap<_, _>>();
+    let ctg_len = ctgmap_set
+        .records
+        .iter()
+        .map(|r| (r.q_name.as_str(), r.ctg_len))
         .collect::<FxHashMap<_, _>>();
+    ctg_intervals
+        .into_iter()
+        .map(|(q_name, mut intervals)| {
+            intervals.sort();
+            let mut covered = 0_u64;
+            let mut c_end = 0_u32;
+            intervals.into_iter().for_each(|(s, e)| {
+                let s = s.max(c_end);
+                if e > s {
+                    covered += (e - s) as u64;
+                    c_end = e;
+                };
+            });
+            let q_len = query_length
+                .get(q_name.as_str())
+                .or_else(|| ctg_len.get(q_name.as_str()))
+                .copied()
+                .unwrap_or(0);
+            let coverage = if q_len > 0 {
+                covered as f64 / q_len as f64
+            } else {
+                0.0
+            };
+            (q_name, coverage)
+        })
+        .collect()
+}
 
-    ctgmap_set.records.iter().for_each(|r| {
-        if r.q_dup {
-            return;
-        };
-        let e = ctg_target_hit_len.entry(r.q_name.clone()).or_default();
-        let e2 = e.entry(r.t_name.clone()).or_default();
-        *e2 += (r.qe as i32 - r.qs as i32).unsigned_abs();
-    });
+/// whether `record` continues `last` (the records sorted by ts) in the same orientation without gaps longer than
+/// `merge_gap` in the target and the query, the query coordinates go down along the target in the reverse orientation
+fn is_collinear(last: &CtgMapRec, record: &CtgMapRec, merge_gap: u32) -> bool {
+    if last.t_name != record.t_name
+        || last.q_name != record.q_name
+        || last.orientation != record.orientation
+    {
+        return false;
+    };
+    let within_gap = |bgn: u32, end: u32| bgn <= end && end - bgn <= merge_gap;
+    let q_collinear = if record.orientation == 0 {
+        within_gap(last.qe, record.qs)
+    } else {
+        within_gap(record.qe, last.qs)
+    };
+    within_gap(last.te, record.ts) && q_collinear
+}
 
-    let mut ctg2tgt = FxHashMap::<String, String>::default();
-    let mut tgt2ctg = FxHashMap::<String, String>::default();
-
-    ctg_target_hit_len.into_iter().for_each(|(ctg, tgt_len)| {
-        let mut tgt_len = tgt_len.into_iter().collect::<Vec<_>>();
-        if !tgt_len.is_empty() {
-            tgt_len.sort_by(|a, b| b.1.cmp(&a.1));
-            let tgt = tgt_len[0].0.clone();
-            //println!("DBG: {} {:?}", ctg, tgt_len);
-            ctg2tgt.insert(ctg.clone(), tgt.clone());
-            tgt2ctg.insert(tgt.clone(), ctg.clone());
+/// merge the consecutive collinear records (see `is_collinear`) into one record spanning them, the merged record
+/// takes the place of its first record, return the records and the number of the records merged into the others
+fn merge_collinear_records(records: Vec<CtgMapRec>, merge_gap: u32) -> (Vec<CtgMapRec>, usize) {
+    let number_records = records.len();
+    let mut order = (0..number_records).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (&records[a], &records[b]);
+        (&a.t_name, &a.q_name, a.orientation, a.ts).cmp(&(
+            &b.t_name,
+            &b.q_name,
+            b.orientation,
+            b.ts,
+        ))
+    });
+    // (the index of the first record, the merged record)
+    let mut merged = Vec::<(usize, CtgMapRec)>::new();
+    order.into_iter().for_each(|idx| {
+        let record = &records[idx];
+        match merged.last_mut() {
+            Some((_, last)) if is_collinear(last, record, merge_gap) => {
+                last.te = record.te;
+                if record.orientation == 0 {
+                    last.qe = record.qe;
+                } else {
+                    last.qs = record.qs;
+                };
+                last.t_dup &= record.t_dup;
+                last.q_dup &= record.q_dup;
+                last.t_ovlp |= record.t_ovlp;
+                last.q_ovlp |= record.q_ovlp;
+            }
+            _ => merged.push((idx, record.clone())),
         };
     });
+    merged.sort_by_key(|v| v.0);
+    let number_merged = number_records - merged.len();
+    (merged.into_iter().map(|v| v.1).collect(), number_merged)
+}
 
-    let mut tgt_to_records = FxHashMap::<String, Vec<CtgMapRec>>::default();
-    let mut qry_to_alt_tgt_records = FxHashMap::<String, Vec<CtgMapRec>>::default();
-    let mut tgt_to_alt_qry_records = FxHashMap::<String, Vec<CtgMapRec>>::default();
-    ctgmap_set.records.iter().for_each(|r| {
-        if r.q_dup {
-            return;
-        };
-        if *ctg2tgt.get(&r.q_name).unwrap() != r.t_name {
-            let e = qry_to_alt_tgt_records.entry(r.q_name.clone()).or_default();
-            e.push((*r).clone());
-            let e = tgt_to_alt_qry_records.entry(r.t_name.clone()).or_default();
-            e.push((*r).clone());
-            return;
+/// drop the records of the query contigs with the coverage less than `min_contig_coverage`,
+/// return the number of the contigs dropped
+fn filter_by_contig_coverage(ctgmap_set: &mut CtgMapSet, min_contig_coverage: f64) -> usize {
+    let contig_coverage = get_contig_coverage(ctgmap_set);
+    let dropped_ctgs = contig_coverage
+        .into_iter()
+        .filter(|(_, coverage)| *coverage < min_contig_coverage)
+        .map(|(q_name, _)| q_name)
+        .collect::<FxHashSet<_>>();
+    ctgmap_set
+        .records
+        .retain(|r| !dropped_ctgs.contains(&r.q_name));
+    dropped_ctgs.len()
+}
+
+/// drop the records of the query contigs shorter than `min_query_len`, the length is from `query_length`
+/// or `ctg_len` of the records if the contig is not in it, return the number of the contigs dropped
+fn filter_by_query_length(ctgmap_set: &mut CtgMapSet, min_query_len: u32) -> usize {
+    let query_length = ctgmap_set
+        .query_length
+        .iter()
+        .map(|(_, q_name, q_len)| (q_name.clone(), *q_len))
+        .collect::<FxHashMap<_, _>>();
+    let mut dropped_ctgs = FxHashSet::<String>::default();
+    ctgmap_set.records.retain(|r| {
+        let q_len = *query_length.get(&r.q_name).unwrap_or(&r.ctg_len);
+        if q_len < min_query_len {
+            dropped_ctgs.insert(r.q_name.clone());
+            false
+        } else {
+            true
         }
-        let e = tgt_to_records.entry(r.t_name.clone()).or_default();
-        e.push((*r).clone());
     });
+    dropped_ctgs.len()
+}
+
+/// parse "path:label" of --additional-ctgmap, the file name is used as the label if it is not given
+fn parse_additional_ctgmap(arg: &str) -> (String, String) {
+    match arg.rsplit_once(':') {
+        Some((path, label)) if !path.is_empty() && !label.is_empty() => {
+            (path.to_string(), label.to_string())
+        }
+        _ => {
+            let label = Path::new(arg)
+                .file_name()
+                .map(|v| v.to_string_lossy().to_string())
+                .unwrap_or_else(|| arg.to_string());
+            (arg.to_string(), label)
+        }
+    }
+}
+
+/// the records the plot can not draw: a target interval with the start after the end, or a query interval
+/// beyond the length of the contig
+fn check_ctgmap_set(ctgmap_set: &CtgMapSet, ctgmap_json_path: &str) -> Result<(), PgrError> {
+    match validate_ctgmap_set(ctgmap_set).into_iter().find(|e| {
+        matches!(
+            e.kind,
+            ValidationErrorKind::TargetStartAfterEnd | ValidationErrorKind::QueryEndExceedsLength
+        )
+    }) {
+        Some(e) => Err(PgrError::InvalidCtgmap(format!(
+            "record {} of {}: {}",
+            e.record_index, ctgmap_json_path, e.description
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// apply --merge-gap, --min-aln-len, --min-contig-coverage and --min-query-len to the set,
+/// return the numbers of the merged records and of the query contigs dropped by the coverage and by the length
+fn filter_ctgmap_set(ctgmap_set: &mut CtgMapSet, args: &CmdOptions) -> (usize, usize, usize) {
+    let mut number_merged = 0;
+    if let Some(merge_gap) = args.merge_gap {
+        let records = std::mem::take(&mut ctgmap_set.records);
+        let (records, n) = merge_collinear_records(records, merge_gap);
+        ctgmap_set.records = records;
+        number_merged = n;
+    };
+    if let Some(min_aln_len) = args.min_aln_len {
+        ctgmap_set
+            .records
+            .retain(|r| r.te.saturating_sub(r.ts) >= min_aln_len);
+    };
+    let mut number_low_coverage = 0;
+    if args.min_contig_coverage > 0.0 {
+        number_low_coverage = filter_by_contig_coverage(ctgmap_set, args.min_contig_coverage);
+    };
+    let number_short = args
+        .min_query_len
+        .map(|min_query_len| filter_by_query_length(ctgmap_set, min_query_len))
+        .unwrap_or(0);
+    (number_merged, number_low_coverage, number_short)
+}
+
+/// read the annotation files and the additional assemblies of the command line into the options of the plot
+fn get_render_options(
+    args: &CmdOptions,
+    ctgmap_json_path: &str,
+    region: Option<(String, u32, u32)>,
+) -> Result<RenderOptions, PgrError> {
+    let summary_only = args.ctg.iter().any(|ctg| ctg == "summary");
+    if args.label_font_size <= 0.0 {
+        return Err(PgrError::InvalidArgument(
+            "--label-font-size should be positive".to_string(),
+        ));
+    };
+    if args.track_height <= 0.0 || args.inter_track_gap <= 0.0 {
+        return Err(PgrError::InvalidArgument(
+            "--track-height and --inter-track-gap should be positive".to_string(),
+        ));
+    };
+    if args
+        .tick_interval
+        .is_some_and(|tick_interval| tick_interval <= 0.0)
+    {
+        return Err(PgrError::InvalidArgument(
+            "--tick-interval should be positive".to_string(),
+        ));
+    };
+    if args.coverage_bins == Some(0) {
+        return Err(PgrError::InvalidArgument(
+            "--coverage-bins should be positive".to_string(),
+        ));
+    };
+    if !(0.0..=1.0).contains(&args.ribbon_opacity) || !(0.0..=1.0).contains(&args.track_opacity) {
+        return Err(PgrError::InvalidArgument(
+            "--ribbon-opacity and --track-opacity should be between 0 and 1".to_string(),
+        ));
+    };
 
-    let target_padding = 1.5e6;
-    let mut offset = 0_f64;
-    let target_aln_blocks = ctgmap_set
-        .target_length
+    let cytobands = if let Some(cytoband_path) = args.cytoband_json.clone() {
+        let cytoband_file = BufReader::new(File::open(Path::new(&cytoband_path)).map_err(
+            PgrError::io_context(format!(
+                "can't open the cytoband json file {}",
+                cytoband_path
+            )),
+        )?);
+        let cytobands: CytoBands = serde_json::from_reader(cytoband_file)?;
+        Some(cytobands)
+    } else if let Some(cytoband_path) = args.cytoband_txt.as_ref() {
+        let cytoband_file = open_possibly_compressed(Path::new(cytoband_path))?;
+        let cytobands = CytoBands::from_ucsc_txt(cytoband_file)?;
+        Some(cytobands)
+    } else {
+        None
+    };
+
+    if args.bed_label.len() > args.ref_annotation_bed.len() {
+        return Err(PgrError::InvalidArgument(
+            "--bed-label is given more times than --ref-annotation-bed".to_string(),
+        ));
+    };
+    let ref_annotation_tracks = args
+        .ref_annotation_bed
         .iter()
-        .flat_map(|(id, t_name, t_len)| {
-            if let Some(target_ctg) = args.ctg.as_ref() {
-                if target_ctg != "summary" && target_ctg != t_name {
-                    return None;
-                }
-            };
-            let mut q_len_sum = 0.0;
-            let mut q_set = FxHashSet::<String>::default();
-            tgt_to_records
-                .get(t_name)
-                .unwrap_or(&vec![])
-                .iter()
-                .for_each(|record| {
-                    let q_len = query_length.get(&record.q_name).unwrap();
-                    if !q_set.contains(&record.q_name) {
-                        q_set.insert(record.q_name.clone());
-                        q_len_sum += *q_len as f64;
-                    };
-                });
-
-            if let Some(records) = tgt_to_records.get(t_name) {
-                let out = (*id, t_name.clone(), *t_len, offset, records);
-                offset += (*t_len as f64).max(q_len_sum) + target_padding;
-                Some(out)
-            } else {
-                None
-            }
+        .enumerate()
+        .map(|(idx, path)| {
+            let label = args.bed_label.get(idx).cloned().unwrap_or_else(|| {
+                Path::new(path)
+                    .file_name()
+                    .map(|v| v.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.clone())
+            });
+            Ok(RefAnnotationTrack {
+                label,
+                regions: read_bed_regions_with_colors(path, args.palette_preset.colors())?,
+            })
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>, PgrError>>()?;
+    let telomeres = args
+        .telomere_bed
+        .as_ref()
+        .map(|path| read_bed_regions(path))
+        .transpose()?;
+    let genes = args
+        .gene_gff
+        .as_ref()
+        .map(|path| read_gene_gff(open_possibly_compressed(Path::new(path))?))
+        .transpose()?;
+    let query_order = args
+        .query_order
+        .as_ref()
+        .map(|path| read_query_order(path))
+        .transpose()?
+        .unwrap_or_default();
+    let query_annotations = args
+        .query_annotation_bed
+        .as_ref()
+        .map(|path| read_bed_regions(path))
+        .transpose()?;
+    let external_css =
+        match args.external_css.as_ref() {
+            Some(css_path) => Some(std::fs::read_to_string(css_path).map_err(
+                PgrError::io_context(format!("can't read the CSS file {}", css_path)),
+            )?),
+            None => None,
+        };
 
-    let svg_box_height = if args.ctg.is_none() { 3500 } else { 180 };
+    let additional_assemblies = args
+        .additional_ctgmap
+        .iter()
+        .map(|arg| {
+            let (path, label) = parse_additional_ctgmap(arg);
+            let mut additional_ctgmap_set = read_validated_ctgmap_json(&path)?;
+            check_ctgmap_set(&additional_ctgmap_set, &path)?;
+            filter_ctgmap_set(&mut additional_ctgmap_set, args);
+            Ok((additional_ctgmap_set, label))
+        })
+        .collect::<Result<Vec<_>, PgrError>>()?;
+
+    // "{name}" in the title and the subtitle is the name of the ctgmap.json file
+    let title_name = get_title_name(ctgmap_json_path);
+    let title = args
+        .title
+        .as_ref()
+        .map(|t| t.replace("{name}", &title_name));
+    let subtitle = args
+        .subtitle
+        .as_ref()
+        .map(|t| t.replace("{name}", &title_name));
+
+    Ok(RenderOptions {
+        panel_width: args.panel_width,
+        total_target_bases: args.total_target_bases,
+        strict: args.strict,
+        target_padding: args.target_padding,
+        chr_scaling_multiplier: args.chr_scaling_multiplier,
+        auto_scale_chr: args.auto_scale_chr,
+        ctg: if summary_only {
+            vec![]
+        } else {
+            args.ctg.clone()
+        },
+        summary_only,
+        region,
+        sort_chromosomes: args.sort_chromosomes,
+        length_transform: args.length_transform,
+        theme: args.theme,
+        palette: args.palette_preset.colors(),
+        font_family: args.font_family.clone(),
+        label_font_size: args.label_font_size,
+        track_labels: args.track_labels,
+        title,
+        subtitle,
+        external_css,
+        box_height: args.box_height,
+        legend: args.legend,
+        legend_max_contigs: args.legend_max_contigs,
+        opacity: Opacity {
+            ribbon: args.ribbon_opacity,
+            track: args.track_opacity,
+        },
+        track_layout: TrackLayout::new(args.track_height, args.inter_track_gap),
+        query_order,
+        query_sort: args.query_sort,
+        cytobands,
+        ref_annotation_tracks,
+        telomeres,
+        genes,
+        query_annotations,
+        additional_assemblies,
+        track_spacing: args.track_spacing,
+        max_records_per_contig: args.max_records_per_contig,
+        scale_bar: !args.no_scale_bar,
+        tick_interval: args.tick_interval,
+        ruler: !args.no_ruler,
+        grid: args.grid,
+        gap_min_length: args.gap_markers.then_some(args.gap_min_length),
+        coverage_bins: args.coverage_bins,
+        alt_hits: !args.no_alt,
+        show_dups: args.show_dups,
+        highlight_inversions: args.highlight_inversions,
+        highlight_duplications: args.highlight_duplications,
+        flip_query: args.flip_query,
+        inversions_only: args.inversions_only,
+        shade_reverse_strand: args.shade_reverse_strand,
+        highlight_query: args.highlight_query.clone(),
+        overview: !args.split_chromosomes,
+        split_chr_panels: args.split_output || args.split_chromosomes,
+        show_progress: !args.no_progress,
+    })
+}
 
-    // start to construct the SVG element
-    let mut document = Document::new()
-        .set(
-            "viewBox",
-            (
-                -args.panel_width * 0.05,
-                -50,
-                args.panel_width * 0.95 * 2.0,
-                svg_box_height,
-            ),
-        )
-        .set("width", args.panel_width * 2.0)
-        .set("height", svg_box_height)
-        .set("preserveAspectRatio", "none")
-        .set("id", "WholeGenomeViwer")        
-        .set("overflow", "visible");
-
-    let scaling_factor = if let Some(total_target_bases) = args.total_target_bases {
-        args.panel_width * 0.8 / total_target_bases
-    } else {
-        args.panel_width * 0.8 / offset
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let mut args = CmdOptions::parse();
+    if let Some(name) = args.test_hash.as_ref() {
+        println!("{}", calculate_hash(name));
+        return Ok(());
+    };
+    // both are required without --test-hash
+    let ctgmap_json_path = args.ctgmap_json_path.clone().unwrap();
+    let output_prefix = args.output_prefix.clone().unwrap();
+    let summary_only = args.ctg.iter().any(|ctg| ctg == "summary");
+    if args.minimize_crossings {
+        args.query_sort = QuerySort::MedianPosition;
     };
 
-    let mut plot_overview = || {
-        target_aln_blocks
-            .iter()
-            .for_each(|target_aln_block_records| {
-                let t_offset = target_aln_block_records.3;
-                let t_name = target_aln_block_records.1.clone();
-                let mut group = element::Group::new().set("id", format!("overview_{}", t_name));
-                let b = t_offset * scaling_factor;
-                let e = (t_offset + target_aln_block_records.2 as f64) * scaling_factor;
-                let w = 4.0 + ((target_aln_block_records.0 + 1) % 2) as f64 * 1.5;
-                let path_str = format!("M {b:0.4} 6 L {e:0.4} 6");
-                let path = element::Path::new()
-                    .set("stroke", "#000")
-                    .set("stroke-width", format!("{w}"))
-                    .set("opacity", 0.7)
-                    .set("stroke-opacity", 0.7)
-                    .set("d", path_str);
-                group.append(path);
-
-                let text = element::Text::new(target_aln_block_records.1.clone())
-                    .set("x", b)
-                    .set("y", 0)
-                    .set("font-size", "6px")
-                    .set("font-family", "monospace");
-                group.append(text);
-
-                if let Some(ref_highlight) = &ref_highlight {
-                    if let Some(regions) = ref_highlight.get(&t_name) {
-                        regions.iter().for_each(|(bgn, end)| {
-                            let b = (t_offset + *bgn as f64) * scaling_factor;
-                            let e = (t_offset + *end as f64) * scaling_factor;
-                            let path_str = format!("M {b:0.4} 3 L {e:0.4} 3");
-                            let path = element::Path::new()
-                                .set("stroke", "#F00")
-                                .set("stroke-width", 6)
-                                .set("opacity", 0.7)
-                                .set("stroke-opacity", 0.7)
-                                .set("d", path_str);
-                            group.append(path);
-                        });
-                    }
-                };
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(args.number_of_thread)
+        .build_global()
+        .map_err(|e| {
+            PgrError::InvalidArgument(format!(
+                "can't build the thread pool of --number-of-thread {}: {}",
+                args.number_of_thread, e
+            ))
+        })?;
+    if summary_only && args.ctg.len() > 1 {
+        return Err(PgrError::InvalidArgument(
+            "--ctg summary can not be combined with other contigs".to_string(),
+        ));
+    };
+    if !(0.0..=1.0).contains(&args.min_contig_coverage) {
+        return Err(PgrError::InvalidArgument(
+            "--min-contig-coverage should be between 0 and 1".to_string(),
+        ));
+    };
 
-                let mut best_query_block = FxHashMap::<String, CtgMapRec>::default();
-                target_aln_block_records.4.iter().for_each(|record| {
-                    let e = best_query_block
-                        .entry(record.q_name.clone())
-                        .or_insert(record.clone());
-                    if (e.qs as i32 - e.qe as i32).abs()
-                        < (record.qs as i32 - record.qe as i32).abs()
-                    {
-                        *e = record.clone();
-                    }
-                });
-
-                let mut best_query_block = best_query_block.values().collect::<Vec<_>>();
-                best_query_block.sort_by_key(|&v| v.ts);
-                let mut q_offset = 0.0;
-                let mut q_offset_map = FxHashMap::<String, f64>::default();
-                best_query_block.into_iter().for_each(|record| {
-                    let q_len = query_length.get(&record.q_name).unwrap();
-                    if !q_offset_map.contains_key(&record.q_name) {
-                        q_offset_map.insert(record.q_name.clone(), q_offset);
-
-                        let b = (t_offset + q_offset) * scaling_factor;
-                        let e = (t_offset + q_offset + *q_len as f64) * scaling_factor;
-                        let y = 95.0;
-                        let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
-                        let color = CMAP[(calculate_hash(&record.q_name) % 97) as usize];
-                        let path = element::Path::new()
-                            .set("stroke", color)
-                            .set("stroke-width", "5")
-                            .set("opacity", 0.7)
-                            .set("stroke-opacity", 0.7)
-                            .set("d", path_str);
-                        group.append(path);
-
-                        q_offset += *q_len as f64;
-                    };
-                });
-
-                target_aln_block_records.4.iter().for_each(|record| {
-                    if record.t_dup && record.q_dup {
-                        return;
-                    };
-
-                    let q_len = query_length.get(&record.q_name).unwrap();
-
-                    let ts = record.ts as f64 + t_offset;
-                    let te = record.te as f64 + t_offset;
-
-                    let (qs, qe) = if record.ctg_orientation == 1 {
-                        (q_len - record.qe, q_len - record.qs)
-                    } else {
-                        (record.qs, record.qe)
-                    };
-
-                    // let qs = record.qs;
-                    // let qe = record.qe;
-                    let (qs, qe) = if record.orientation != record.ctg_orientation {
-                        (qe, qs)
-                    } else {
-                        (qs, qe)
-                    };
-                    let offset = q_offset_map.get(&record.q_name).unwrap();
-                    let qs = qs as f64 + t_offset + offset;
-                    let qe = qe as f64 + t_offset + offset;
-                    let ts = ts * scaling_factor;
-                    let te = te * scaling_factor;
-                    let qs = qs * scaling_factor;
-                    let qe = qe * scaling_factor;
-                    // println!("{:?}", record);
-                    // println!("{} {} {} {}", ts, te, qs, qe);
-
-                    let color = CMAP[(calculate_hash(&record.q_name) % 97) as usize];
-
-                    let path_str =
-                        format!("M {ts:0.4} 10 L {te:0.4} 10 L {qe:0.4} 90 L {qs:0.4} 90 Z");
-                    let path = element::Path::new()
-                        .set("fill", color)
-                        .set("stroke", "#000")
-                        .set("stroke-width", 0.25)
-                        .set("opacity", 0.7)
-                        .set("stroke-opacity", 0.4)
-                        .set("d", path_str);
-                    group.append(path);
-                });
-                document.append(group);
-            });
+    let mut ctgmap_set = read_validated_ctgmap_json(&ctgmap_json_path)?;
+    check_ctgmap_set(&ctgmap_set, &ctgmap_json_path)?;
+
+    let region = args
+        .region
+        .as_ref()
+        .map(|region| {
+            let (r_name, r_bgn, r_end) = parse_region(region)
+                .map_err(|e| PgrError::InvalidArgument(format!("--region: {}", e)))?;
+            if !ctgmap_set
+                .target_length
+                .iter()
+                .any(|(_, t_name, _)| *t_name == r_name)
+            {
+                return Err(PgrError::InvalidArgument(format!(
+                    "--region: {} is not found in the target_length of the ctgmap.json file",
+                    r_name
+                )));
+            };
+            Ok((r_name, r_bgn, r_end))
+        })
+        .transpose()?;
+    if let Some((r_name, _, _)) = region.as_ref() {
+        // a region plot is a single target plot
+        args.ctg = vec![r_name.clone()];
     };
 
-    if let Some(target_ctg) = args.ctg.as_ref() {
-        if target_ctg.eq("summary") {
-            plot_overview();
-        };
-    } else {
-        plot_overview();
+    let (number_merged, number_low_coverage, number_short) =
+        filter_ctgmap_set(&mut ctgmap_set, &args);
+    if number_merged > 0 {
+        eprintln!(
+            "{} alignment records are merged into the neighboring records by --merge-gap {}",
+            number_merged,
+            args.merge_gap.unwrap()
+        );
+    };
+    if number_low_coverage > 0 {
+        eprintln!(
+            "{} query contigs are dropped by --min-contig-coverage {}",
+            number_low_coverage, args.min_contig_coverage
+        );
+    };
+    if number_short > 0 {
+        eprintln!(
+            "{} query contigs are dropped by --min-query-len {}",
+            number_short,
+            args.min_query_len.unwrap()
+        );
     };
 
-    // per chromosome plot
+    let opts = get_render_options(&args, &ctgmap_json_path, region)?;
 
-    let mut y_offset = if args.ctg.is_none() { 200.0 } else { 0.0 };
-    let scaling_factor = if args.ctg.is_some() {
-        scaling_factor
-    } else {
-        scaling_factor * 12.0
+    ctgmap_set.query_length.sort();
+    sort_targets(&mut ctgmap_set.target_length, args.sort_chromosomes);
+    let ctg2tgt = assign_contigs_to_targets(&ctgmap_set.records);
+
+    if let Some(summary_tsv) = args.summary_tsv.as_ref() {
+        let mut out = BufWriter::new(File::create(path::Path::new(summary_tsv)).map_err(
+            PgrError::io_context(format!("can't create the summary TSV file {}", summary_tsv)),
+        )?);
+        write_summary_tsv(&mut out, &ctgmap_set.records, &ctg2tgt)?;
     };
 
-    target_aln_blocks
-        .iter()
-        .for_each(|target_aln_block_record| {
-            let t_name = target_aln_block_record.1.clone();
-            if let Some(target_ctg) = args.ctg.as_ref() {
-                if t_name != target_ctg.clone() {
-                    return;
-                };
-            };
-            let group = match get_chr_svg_group(
-                target_aln_block_record,
-                scaling_factor,
-                &cytobands,
-                &ref_highlight,
-                &tgt_to_alt_qry_records,
-                &ctg2tgt,
-                &query_length,
-                &qry_to_alt_tgt_records,
-            ) {
-                Some(value) => value,
-                None => return,
-            };
-            let mut sub_svg = Document::new()
-            .set(
-                "viewBox",
-                (
-                    0,
-                    -25,
-                    args.panel_width,
-                    130,
-                ),
-            )
-            .set("width", args.panel_width)
-            .set("height", 130)
-            .set("preserveAspectRatio", "none")
-            .set("y", y_offset)
-            .set("id", t_name.clone())
-            .set("class", "chr_view")
-            .set("overflow", "visible");
-    
-            sub_svg.append(group);
-            let text = element::Text::new(target_aln_block_record.1.clone())
-            .set("x", 0.0)
-            .set("y", y_offset+20.0)
-            .set("font-size", "20px")
-            .set("font-family", "monospace");
-            document.append(text);
-            document.append(sub_svg);
-            y_offset += 130.0;
-        });
+    if let Some(bedgraph) = args.bedgraph.as_ref() {
+        let mut out = BufWriter::new(File::create(path::Path::new(bedgraph)).map_err(
+            PgrError::io_context(format!("can't create the bedGraph file {}", bedgraph)),
+        )?);
+        write_bedgraph(
+            &mut out,
+            &ctgmap_set.records,
+            &ctgmap_set.target_length,
+            args.bedgraph_exclude_dups,
+        )?;
+    };
 
-    let mut out_file = if args.svg {
-        BufWriter::new(
-            File::create(path::Path::new(&args.output_prefix).with_extension("svg"))
-                .expect("can't create the HTML output file"),
-        )
+    if let Some(output_filtered_json) = args.output_filtered_json.as_ref() {
+        let out = BufWriter::new(File::create(path::Path::new(output_filtered_json)).map_err(
+            PgrError::io_context(format!(
+                "can't create the filtered ctgmap json file {}",
+                output_filtered_json
+            )),
+        )?);
+        serde_json::to_writer_pretty(out, &get_filtered_ctgmap_set(&ctgmap_set, &opts.ctg))?;
+    };
+
+    if let Some(bedpe_out) = args.bedpe_out.as_ref() {
+        let mut out = BufWriter::new(File::create(path::Path::new(bedpe_out)).map_err(
+            PgrError::io_context(format!("can't create the BEDPE file {}", bedpe_out)),
+        )?);
+        write_bedpe(&mut out, &get_drawn_records(&ctgmap_set, &opts))?;
+    };
+
+    if let Some(dropped_out) = args.dropped_out.as_ref() {
+        let q_dup_dropped = if args.show_dups || args.highlight_duplications {
+            vec![]
+        } else {
+            bucket_records(&ctgmap_set.records, &ctg2tgt, false).q_dup_dropped
+        };
+        let mut out = BufWriter::new(File::create(path::Path::new(dropped_out)).map_err(
+            PgrError::io_context(format!(
+                "can't create the dropped record file {}",
+                dropped_out
+            )),
+        )?);
+        write_dropped_records(&mut out, &q_dup_dropped)?;
+        eprintln!("{} q_dup records are skipped", q_dup_dropped.len());
+    };
+
+    // the per-chromosome files are written as their panels are drawn instead of keeping all the documents
+    let ext = get_output_extension(args.svg, args.gzip_html);
+    let write_chr_document = |t_name: &str, chr_document: Document| {
+        if args.split_output {
+            let chr_svg_path = format!("{}.{}.svg", output_prefix, t_name);
+            svg::save(&chr_svg_path, &chr_document).map_err(PgrError::io_context(format!(
+                "can't write the SVG output file {}",
+                chr_svg_path
+            )))
+        } else {
+            write_plot_file(
+                &format!("{}_{}.{}", output_prefix, t_name, ext),
+                &chr_document,
+                args.svg,
+                args.gzip_html,
+                args.theme,
+                args.no_tooltips,
+            )
+        }
+    };
+    let plot = if opts.split_chr_panels {
+        render_ctgmap_with_writer(&ctgmap_set, &opts, &write_chr_document)?
     } else {
-        BufWriter::new(
-            File::create(path::Path::new(&args.output_prefix).with_extension("html"))
-                .expect("can't create the SVG output file"),
-        )
+        render_ctgmap(&ctgmap_set, &opts)?
     };
-    let mut svg_elment = BufWriter::new(Vec::new());
-    svg::write(&mut svg_elment, &document).unwrap();
-    if !args.svg {
-        let jscript = r#"
-        <script>
-        document.addEventListener('readystatechange', event => {
-            if (event.target.readyState === "complete") {
-                var views = document.getElementsByClassName("chr_view");
-                for (let i = 0; i < views.length; i++) {
-                    views[i].addEventListener('mousedown', function(event) {
-                        event.preventDefault();
-                        const viewBoxValues = views[i].getAttribute('viewBox').split(' ').map(val => parseFloat(val));
-                        let viewBox = { x: viewBoxValues[0], y: viewBoxValues[1], width: viewBoxValues[2], height: viewBoxValues[3] };
-                        console.log(event);
-                        if (event.button != 0) {
-                            return;
-                        }
-                        if (event.altKey) {
-                            scalingFactor = 1.25;
-                        } else {
-                            scalingFactor = 0.8; 
-                        };
-                        viewBox.width *= scalingFactor;
-                        views[i].setAttribute('viewBox', `${viewBox.x} ${viewBox.y} ${viewBox.width} ${viewBox.height}`);
-                    });
-                };
-            }
-        });
-        </script>
-        "#;
-        writeln!(out_file, r#"<html><body>"#).expect("can't write the output html file");
-        writeln!(out_file, "{}", jscript).expect("can't write the output html file");
-        writeln!(out_file, r#"<div style="overflow:scroll;">"#).expect("can't write the output html file");
+    plot.messages.iter().for_each(|msg| eprintln!("{}", msg));
+
+    if let Some(layout_json) = args.layout_json.as_ref() {
+        let out_layout = BufWriter::new(File::create(path::Path::new(layout_json)).map_err(
+            PgrError::io_context(format!("can't create the layout json file {}", layout_json)),
+        )?);
+        serde_json::to_writer_pretty(out_layout, &plot.layout)?;
     };
 
-    writeln!(
-        out_file,
-        "{}",
-        String::from_utf8_lossy(&svg_elment.into_inner().unwrap())
-    )
-    .expect("can't write the output HTML or SVG file");
-
-    if !args.svg {
-        writeln!(out_file, "</div></body></html>").expect("can't write the output html file");
+    if args.split_output {
+        if opts.ctg.is_empty() {
+            let overview_svg_path = format!("{}.overview.svg", output_prefix);
+            svg::save(&overview_svg_path, &plot.document).map_err(PgrError::io_context(
+                format!("can't write the SVG output file {}", overview_svg_path),
+            ))?;
+        };
+        return Ok(());
+    };
+    if args.split_chromosomes {
+        let manifest_path = format!("{}_manifest.tsv", output_prefix);
+        let mut out_manifest =
+            BufWriter::new(File::create(path::Path::new(&manifest_path)).map_err(
+                PgrError::io_context(format!("can't create the manifest file {}", manifest_path)),
+            )?);
+        writeln!(out_manifest, "#t_name\tpath")?;
+        // the targets with the per-chromosome panels in the plot order
+        for target in plot.layout.targets.iter() {
+            if target.chr_panel_y.is_some() {
+                let chr_path = format!("{}_{}.{}", output_prefix, target.t_name, ext);
+                writeln!(out_manifest, "{}\t{}", target.t_name, chr_path)?;
+            };
+        }
+        return Ok(());
     };
 
+    let output_path = path::Path::new(&output_prefix).with_extension(ext);
+    write_plot_file(
+        &output_path.to_string_lossy(),
+        &plot.document,
+        args.svg,
+        args.gzip_html,
+        args.theme,
+        args.no_tooltips,
+    )?;
+
     Ok(())
 }
 
+/// the file name of the ctgmap.json file without the ".ctgmap.json" or ".json" extension
+fn get_title_name(ctgmap_json_path: &str) -> String {
+    let file_name = Path::new(ctgmap_json_path)
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    [".ctgmap.json", ".json"]
+        .iter()
+        .find_map(|ext| file_name.strip_suffix(ext))
+        .unwrap_or(&file_name)
+        .to_string()
+}
+
+/// read the query contig names of --query-order, a name is mapped to its first line number
+fn read_query_order(query_order_path: &str) -> Result<FxHashMap<String, usize>, PgrError> {
+    let query_order_file = BufReader::new(File::open(path::Path::new(query_order_path)).map_err(
+        PgrError::io_context(format!(
+            "can't open the query order file {}",
+            query_order_path
+        )),
+    )?);
+    let mut query_order = FxHashMap::<String, usize>::default();
+    for line in query_order_file.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let q_name = line.split_whitespace().next().unwrap().to_string();
+        let rank = query_order.len();
+        query_order.entry(q_name).or_insert(rank);
+    }
+    Ok(query_order)
+}
 
-fn get_chr_svg_group(
-    target_aln_block_record: &(u32, String, u32, f64, &Vec<CtgMapRec>),
-    scaling_factor: f64,
-    cytobands: &Option<CytoBands>,
-    ref_highlight: &Option<FxHashMap<String, Vec<(u32, u32)>>>,
-    tgt_to_alt_qry_records: &FxHashMap::<String, Vec<CtgMapRec>>,
-    ctg2tgt: &FxHashMap::<String, String>,
-    query_length: &FxHashMap::<String, u32>,
-    qry_to_alt_tgt_records: &FxHashMap::<String, Vec<CtgMapRec>>
-) -> Option<element::Group> {
-    let t_name = target_aln_block_record.1.clone();
-    let mut group = element::Group::new();
-    let t_offset = 0.0;
-    let t_len = target_aln_block_record.2;
-    let y = 6.0;
-    let mut draw_plain_ref_track = || {
-        let b = t_offset * scaling_factor;
-        let e = (t_offset + t_len as f64) * scaling_factor;
-        // let w = 4.0 + ((target_aln_block_records.0 + 1) % 2) as f64 * 1.5;
-        let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
-        let path = element::Path::new()
-            .set("stroke", "#000")
-            .set("stroke-width", 8)
-            .set("opacity", 0.7)
-            .set("stroke-opacity", 0.7)
-            .set("d", path_str);
-        group.append(path);
+/// the highlight color of a bed region, picked from `palette` by the name column,
+/// or by the score column if the name is missing or "."
+fn get_bed_region_color(bed_fields: &[&str], palette: &'static [&'static str]) -> &'static str {
+    let key = match (bed_fields.get(3), bed_fields.get(4)) {
+        (Some(name), _) if !name.is_empty() && *name != "." => *name,
+        (_, Some(score)) if !score.is_empty() && *score != "." => *score,
+        _ => return "#F00",
     };
-    if let Some(cytobands) = cytobands.as_ref() {
-        if let Some(cyto_records) = cytobands.cytobands.get(&t_name) {
-            cyto_records.iter().for_each(|(cs, ce, c_name, band)| {
-                let b = (t_offset + *cs as f64) * scaling_factor;
-                let e = (t_offset + *ce as f64) * scaling_factor;
-                let mut color = if band.starts_with("gpos") {
-                    "#000"
-                } else {
-                    "#AAA"
-                };
-                if band == "acen" {
-                    color = "#FF0";
-                };
-                let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
-                let mut path = element::Path::new()
-                    .set("stroke", color)
-                    .set("stroke-width", 8)
-                    .set("opacity", 0.7)
-                    .set("stroke-opacity", 0.7)
-                    .set("d", path_str);
-                path.append(element::Title::new(c_name.clone()));
-                group.append(path);
-            })
-        } else {
-            draw_plain_ref_track()
+    palette[(calculate_hash(key) % palette.len() as u64) as usize]
+}
+
+/// read the regions of a bed file by the sequence names with the colors from `get_bed_region_color`
+fn read_bed_regions_with_colors(
+    bed_path: &str,
+    palette: &'static [&'static str],
+) -> Result<ColoredBedRegions, PgrError> {
+    let bed_file = open_possibly_compressed(Path::new(bed_path))?;
+    let mut regions = ColoredBedRegions::default();
+    for line in bed_file.lines() {
+        let line = line?.trim().to_string();
+        if line.is_empty() {
+            continue;
+        }
+        if &line[0..1] == "#" {
+            continue;
+        }
+        let bed_fields = line.split('\t').collect::<Vec<&str>>();
+        let parse_error = |reason: &str| PgrError::BedParse {
+            line: line.clone(),
+            reason: reason.to_string(),
         };
-    } else {
-        draw_plain_ref_track()
+        if bed_fields.len() < 3 {
+            return Err(parse_error("fewer than 3 columns"));
+        };
+        let ctg: String = bed_fields[0].to_string();
+        let bgn: u32 = bed_fields[1]
+            .parse()
+            .map_err(|_| parse_error("invalid start"))?;
+        let end: u32 = bed_fields[2]
+            .parse()
+            .map_err(|_| parse_error("invalid end"))?;
+        let e = regions.entry(ctg).or_insert_with(Vec::new);
+        e.push((bgn, end, get_bed_region_color(&bed_fields, palette)));
     }
-    if let Some(ref_highlight) = ref_highlight.as_ref() {
-        if let Some(regions) = ref_highlight.get(&t_name) {
-            let y2 = y - 8.0;
-            regions.iter().for_each(|(bgn, end)| {
-                let b = (t_offset + *bgn as f64) * scaling_factor;
-                let e = (t_offset + *end as f64) * scaling_factor;
-                let path_str = format!("M {b:0.4} {y2:0.4} L {e:0.4} {y2:0.4}");
-                let mut path = element::Path::new()
-                    .set("stroke", "#F00")
-                    .set("stroke-width", 6)
-                    .set("opacity", 0.7)
-                    .set("stroke-opacity", 0.7)
-                    .set("d", path_str);
-                path.append(element::Title::new(format!("{}-{}", bgn, end)));
-                group.append(path);
+    Ok(regions)
+}
+
+/// read the "gene" features and the "mRNA" features without a parent gene of a GFF3 file by the seqid,
+/// the name of a feature is from its "Name" attribute, or its "ID" attribute if there is no name
+fn read_gene_gff<R: BufRead>(reader: R) -> Result<FxHashMap<String, Vec<GeneFeature>>, PgrError> {
+    let mut genes = FxHashMap::<String, Vec<GeneFeature>>::default();
+    for line in reader.lines() {
+        let line = line?;
+        // the sequences follow the "##FASTA" directive
+        if line.starts_with("##FASTA") {
+            break;
+        };
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        };
+        let parse_error = |reason: &str| PgrError::GffParse {
+            line: line.clone(),
+            reason: reason.to_string(),
+        };
+        let fields = line.trim_end().split('\t').collect::<Vec<&str>>();
+        if fields.len() < 9 {
+            return Err(parse_error("fewer than 9 columns"));
+        };
+        let attributes = fields[8]
+            .split(';')
+            .flat_map(|kv| kv.split_once('='))
+            .collect::<FxHashMap<_, _>>();
+        match fields[2] {
+            "gene" => {}
+            "mRNA" if !attributes.contains_key("Parent") => {}
+            _ => continue,
+        };
+        let start = fields[3]
+            .parse::<u32>()
+            .map_err(|_| parse_error("invalid start"))?;
+        let end = fields[4]
+            .parse::<u32>()
+            .map_err(|_| parse_error("invalid end"))?;
+        let strand = match fields[6] {
+            "+" => '+',
+            "-" => '-',
+            _ => '.',
+        };
+        let name = attributes
+            .get("Name")
+            .or_else(|| attributes.get("ID"))
+            .unwrap_or(&".")
+            .to_string();
+        genes
+            .entry(fields[0].to_string())
+            .or_default()
+            .push(GeneFeature {
+                bgn: start.saturating_sub(1),
+                end,
+                strand,
+                name,
             });
-        }
-    };
+    }
+    Ok(genes)
+}
 
-    if let Some(tgt_to_alt_qry_records) = tgt_to_alt_qry_records.get(&target_aln_block_record.1) {
-        let t_offset = 0.0;
-        tgt_to_alt_qry_records.iter().for_each(|record| {
-            let b = (t_offset + record.ts as f64) * scaling_factor;
-            let e = (t_offset + record.te as f64) * scaling_factor;
-            let y = 14.0;
-            let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
-            let mut path = element::Path::new()
-                .set("stroke", "#000")
-                .set("stroke-width", 8)
-                .set("opacity", 0.7)
-                .set("stroke-opacity", 0.7)
-                .set("d", path_str);
-            let na = "N/A".to_string();
-            let q_tgt = ctg2tgt.get(&record.q_name).unwrap_or(&na);
-            path.append(element::Title::new(format!(
-                "{} to {} with {}:{}-{}",
-                record.t_name, q_tgt, record.q_name, record.qs, record.qe
-            )));
-            group.append(path);
+/// read the regions of a bed file by the sequence names, the colors are not used
+fn read_bed_regions(bed_path: &str) -> Result<FxHashMap<String, Vec<(u32, u32)>>, PgrError> {
+    let regions = read_bed_regions_with_colors(bed_path, &CMAP)?
+        .into_iter()
+        .map(|(ctg, regions)| {
+            let regions = regions
+                .into_iter()
+                .map(|(bgn, end, _)| (bgn, end))
+                .collect();
+            (ctg, regions)
         })
-    };
-    let mut best_query_block = FxHashMap::<String, CtgMapRec>::default();
-    target_aln_block_record.4.iter().for_each(|record| {
-        let e = best_query_block
-            .entry(record.q_name.clone())
-            .or_insert(record.clone());
-        if (e.qs as i32 - e.qe as i32).abs() < (record.qs as i32 - record.qe as i32).abs() {
-            *e = record.clone();
-        }
-    });
-    let mut best_query_block = best_query_block.values().collect::<Vec<_>>();
-    best_query_block.sort_by_key(|&v| v.ts);
-    let mut q_offset = 0.0;
-    let mut q_offset_map = FxHashMap::<String, f64>::default();
-    best_query_block.into_iter().for_each(|record| {
-        let q_len = query_length.get(&record.q_name).unwrap();
-        if !q_offset_map.contains_key(&record.q_name) {
-            let ctg_aln_orientation = record.ctg_orientation;
-            q_offset_map.insert(record.q_name.clone(), q_offset);
-
-            let b = (t_offset + q_offset) * scaling_factor;
-            let e = (t_offset + q_offset + *q_len as f64) * scaling_factor;
-            let y = 95.0;
-            let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
-            let color = CMAP[(calculate_hash(&record.q_name) % 97) as usize];
-            let mut path = element::Path::new()
-                .set("stroke", color)
-                .set("stroke-width", 8)
-                .set("opacity", 0.7)
-                .set("stroke-opacity", 0.7)
-                .set("d", path_str);
-            path.append(element::Title::new(record.q_name.clone()));
-            group.append(path);
-
-            if let Some(qry_to_alt_tgt_records) = qry_to_alt_tgt_records.get(&record.q_name) {
-                qry_to_alt_tgt_records.iter().for_each(|record| {
-                    let qe = if ctg_aln_orientation == 0 {
-                        record.qs
-                    } else {
-                        q_len - record.qs
-                    };
-                    let qs = if ctg_aln_orientation == 0 {
-                        record.qe
-                    } else {
-                        q_len - record.qe
-                    };
-                    let b = (t_offset + q_offset + qs as f64) * scaling_factor;
-                    let e = (t_offset + q_offset + qe as f64) * scaling_factor;
-                    let y = 105.0;
-                    let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
-                    let color = CMAP[(calculate_hash(&record.q_name) % 97) as usize];
-                    let mut path = element::Path::new()
-                        .set("stroke", color)
-                        .set("stroke-width", 8)
-                        .set("opacity", 0.7)
-                        .set("stroke-opacity", 0.7)
-                        .set("d", path_str);
-                    path.append(element::Title::new(format!(
-                        "{}@{}:{}-{}",
-                        record.q_name, record.t_name, record.ts, record.te
-                    )));
-                    group.append(path);
-                });
-            };
+        .collect();
+    Ok(regions)
+}
 
-            q_offset += *q_len as f64;
+/// a copy of the records on the targets in `ctg` (all targets if it is empty) with the lengths of the targets
+/// and of the query contigs of the records, in the order of `ctgmap_set`
+fn get_filtered_ctgmap_set(ctgmap_set: &CtgMapSet, ctg: &[String]) -> CtgMapSet {
+    let is_plotted = |t_name: &String| ctg.is_empty() || ctg.contains(t_name);
+    let records = ctgmap_set
+        .records
+        .iter()
+        .filter(|r| is_plotted(&r.t_name))
+        .cloned()
+        .collect::<Vec<_>>();
+    let q_names = records
+        .iter()
+        .map(|r| r.q_name.as_str())
+        .collect::<FxHashSet<_>>();
+    CtgMapSet {
+        target_length: ctgmap_set
+            .target_length
+            .iter()
+            .filter(|(_, t_name, _)| is_plotted(t_name))
+            .cloned()
+            .collect(),
+        query_length: ctgmap_set
+            .query_length
+            .iter()
+            .filter(|(_, q_name, _)| q_names.contains(q_name.as_str()))
+            .cloned()
+            .collect(),
+        records,
+    }
+}
+
+/// write the alignment depth along the targets in the `target_length` order as "chr\tstart\tend\tdepth" lines
+fn write_bedgraph<W: Write>(
+    out: &mut W,
+    records: &[CtgMapRec],
+    target_length: &[(u32, String, u32)],
+    exclude_dups: bool,
+) -> Result<(), std::io::Error> {
+    let mut tgt_intervals = FxHashMap::<String, Vec<(u32, u32)>>::default();
+    records.iter().for_each(|r| {
+        if exclude_dups && r.t_dup {
+            return;
         };
+        tgt_intervals
+            .entry(r.t_name.clone())
+            .or_default()
+            .push((r.ts, r.te));
     });
-    target_aln_block_record.4.iter().for_each(|record| {
-        if record.t_dup && record.q_dup {
+    for (_, t_name, _) in target_length {
+        if let Some(intervals) = tgt_intervals.get(t_name) {
+            for (bgn, end, depth) in get_depth_intervals(intervals) {
+                writeln!(out, "{}\t{}\t{}\t{}", t_name, bgn, end, depth)?;
+            }
+        };
+    }
+    Ok(())
+}
+
+/// write the records drawn as the ribbons, see `get_drawn_records`, as the
+/// "t_name\tts\tte\tq_name\tqs\tqe\tq_name\t.\t+\tstrand\tt_dup\tq_dup" BEDPE lines
+fn write_bedpe<W: Write, R: Borrow<CtgMapRec>>(
+    out: &mut W,
+    drawn_records: &[(String, Vec<R>)],
+) -> Result<(), std::io::Error> {
+    for r in drawn_records.iter().flat_map(|(_, records)| records.iter()) {
+        let r: &CtgMapRec = r.borrow();
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t.\t+\t{}\t{}\t{}",
+            r.t_name,
+            r.ts,
+            r.te,
+            r.q_name,
+            r.qs,
+            r.qe,
+            r.q_name,
+            if r.orientation == 0 { '+' } else { '-' },
+            r.t_dup as u8,
+            r.q_dup as u8
+        )?;
+    }
+    Ok(())
+}
+
+/// write the records as "q_name\tt_name\tqs\tqe" lines
+fn write_dropped_records<W: Write, R: Borrow<CtgMapRec>>(
+    out: &mut W,
+    records: &[R],
+) -> Result<(), std::io::Error> {
+    for r in records.iter() {
+        let r: &CtgMapRec = r.borrow();
+        writeln!(out, "{}\t{}\t{}\t{}", r.q_name, r.t_name, r.qs, r.qe)?;
+    }
+    Ok(())
+}
+
+/// write the best target of each query contig, the aligned bases and the number of alignment blocks to it,
+/// and whether the contig also hits other targets, the records marked as `q_dup` are not counted
+fn write_summary_tsv<W: Write>(
+    out: &mut W,
+    records: &[CtgMapRec],
+    ctg2tgt: &FxHashMap<String, String>,
+) -> Result<(), std::io::Error> {
+    // (aligned bases, number of blocks, has alternative target hits)
+    let mut summary = FxHashMap::<String, (u32, usize, bool)>::default();
+    records.iter().for_each(|r| {
+        if r.q_dup {
             return;
         };
+        if let Some(tgt) = ctg2tgt.get(&r.q_name) {
+            let e = summary.entry(r.q_name.clone()).or_default();
+            if *tgt == r.t_name {
+                e.0 += (r.qe as i32 - r.qs as i32).unsigned_abs();
+                e.1 += 1;
+            } else {
+                e.2 = true;
+            }
+        };
+    });
+    let mut q_names = summary.keys().cloned().collect::<Vec<_>>();
+    q_names.sort();
+    writeln!(
+        out,
+        "#q_name\tbest_target\taligned_bases\tnum_blocks\thas_alt_target_hits"
+    )?;
+    for q_name in q_names {
+        let (aligned_bases, num_blocks, has_alt) = summary.get(&q_name).unwrap();
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}",
+            q_name,
+            ctg2tgt.get(&q_name).unwrap(),
+            aligned_bases,
+            num_blocks,
+            if *has_alt { 1 } else { 0 }
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pgr_bin::render::{draw_ruler, get_ruler_interval};
+    use std::io;
+    use svg::node::element;
+
+    fn test_record(t_name: &str, ts: u32, te: u32, q_name: &str, qs: u32, qe: u32) -> CtgMapRec {
+        CtgMapRec {
+            t_name: t_name.to_string(),
+            ts,
+            te,
+            q_name: q_name.to_string(),
+            qs,
+            qe,
+            ctg_len: 1_000_000,
+            orientation: 0,
+            ctg_orientation: 0,
+            t_dup: false,
+            t_ovlp: false,
+            q_dup: false,
+            q_ovlp: false,
+            identity: None,
+        }
+    }
+
+    #[test]
+    fn test_summary_tsv() {
+        let records = vec![
+            test_record("chr1", 0, 1000, "ctg1", 0, 1000),
+            test_record("chr1", 2000, 2500, "ctg1", 1000, 1500),
+            test_record("chr2", 0, 300, "ctg1", 1500, 1800),
+            test_record("chr2", 0, 800, "ctg2", 0, 800),
+            CtgMapRec {
+                q_dup: true,
+                ..test_record("chr3", 0, 800, "ctg2", 0, 800)
+            },
+        ];
+        let ctg2tgt = [("ctg1", "chr1"), ("ctg2", "chr2")]
+            .into_iter()
+            .map(|(q, t)| (q.to_string(), t.to_string()))
+            .collect::<FxHashMap<_, _>>();
+        let mut out = Vec::<u8>::new();
+        write_summary_tsv(&mut out, &records, &ctg2tgt).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "#q_name\tbest_target\taligned_bases\tnum_blocks\thas_alt_target_hits\n\
+             ctg1\tchr1\t1500\t2\t1\n\
+             ctg2\tchr2\t800\t1\t0\n"
+        );
+    }
 
-        let q_len = query_length.get(&record.q_name).unwrap();
+    #[test]
+    fn test_multiple_ctg() {
+        let args = CmdOptions::try_parse_from([
+            "pgr-generate-chr-aln-plot",
+            "t.ctgmap.json",
+            "out",
+            "--ctg",
+            "chr1,chr7",
+            "--ctg",
+            "chr14",
+        ])
+        .unwrap();
+        assert_eq!(args.ctg, vec!["chr1", "chr7", "chr14"]);
+    }
 
-        let ts = record.ts as f64 + t_offset;
-        let te = record.te as f64 + t_offset;
+    #[test]
+    fn test_ruler() {
+        // a 250 Mbp chromosome in a 1400 pixel wide panel
+        let scaling_factor = 1400.0 * 0.8 / 3.1e9 * 12.0;
+        assert_eq!(get_ruler_interval(250_000_000, scaling_factor), 10_000_000);
+        // the same chromosome filling the panel
+        assert_eq!(
+            get_ruler_interval(250_000_000, 1400.0 * 0.9 / 250_000_000.0),
+            10_000_000
+        );
+        assert_eq!(get_ruler_interval(50_000_000, 1e-4), 1_000_000);
+        assert_eq!(get_ruler_interval(250_000_000, 1e-7), 50_000_000);
+
+        let mut group = element::Group::new();
+        draw_ruler(
+            &mut group,
+            25_000_000,
+            1e-5,
+            5_000_000,
+            Theme::Light,
+            "monospace",
+        );
+        let group = group.to_string();
+        assert_eq!(group.matches("<text").count(), 5);
+        assert!(group.contains("\n25\n</text>"));
+
+        let args = CmdOptions::try_parse_from([
+            "pgr-generate-chr-aln-plot",
+            "in.json",
+            "out",
+            "--no-ruler",
+            "--tick-interval",
+            "1e6",
+        ]);
+        assert!(args.is_err());
+    }
 
-        let (qs, qe) = if record.ctg_orientation == 1 {
-            (q_len - record.qe, q_len - record.qs)
-        } else {
-            (record.qs, record.qe)
+    #[test]
+    fn test_contig_coverage() {
+        let mut ctgmap_set = CtgMapSet {
+            records: vec![
+                test_record("chr1", 0, 400, "ctg1", 0, 400),
+                test_record("chr1", 1000, 1400, "ctg1", 200, 600),
+                test_record("chr2", 0, 20, "ctg2", 0, 20),
+            ],
+            target_length: vec![(0, "chr1".to_string(), 2000), (1, "chr2".to_string(), 100)],
+            query_length: vec![(0, "ctg1".to_string(), 1000), (1, "ctg2".to_string(), 1000)],
         };
+        let contig_coverage = get_contig_coverage(&ctgmap_set);
+        // the overlapping 200-400 is counted once
+        assert_eq!(contig_coverage["ctg1"], 0.6);
+        assert_eq!(contig_coverage["ctg2"], 0.02);
+
+        assert_eq!(filter_by_contig_coverage(&mut ctgmap_set, 0.05), 1);
+        assert_eq!(ctgmap_set.records.len(), 2);
+        assert!(ctgmap_set.records.iter().all(|r| r.q_name == "ctg1"));
+    }
 
-        // let qs = record.qs;
-        // let qe = record.qe;
-        let (qs, qe) = if record.orientation != record.ctg_orientation {
-            (qe, qs)
-        } else {
-            (qs, qe)
+    #[test]
+    fn test_query_length_filter() {
+        let mut ctgmap_set = CtgMapSet {
+            records: vec![
+                test_record("chr1", 0, 400, "ctg1", 0, 400),
+                test_record("chr1", 1000, 1400, "ctg2", 200, 600),
+                test_record("chr2", 0, 20, "ctg2", 0, 20),
+                // not in the query_length, the ctg_len of test_record is used
+                test_record("chr2", 100, 200, "ctg3", 0, 100),
+            ],
+            target_length: vec![(0, "chr1".to_string(), 2000), (1, "chr2".to_string(), 100)],
+            query_length: vec![(0, "ctg1".to_string(), 1000), (1, "ctg2".to_string(), 800)],
         };
-        let offset = q_offset_map.get(&record.q_name).unwrap();
-        let qs = qs as f64 + t_offset + offset;
-        let qe = qe as f64 + t_offset + offset;
-        let ts = ts * scaling_factor;
-        let te = te * scaling_factor;
-        let qs = qs * scaling_factor;
-        let qe = qe * scaling_factor;
-        // println!("{:?}", record);
-        // println!("{} {} {} {}", ts, te, qs, qe);
-
-        let color = CMAP[(calculate_hash(&record.q_name) % 97) as usize];
-        let y = 14.0;
-        let y2 = 88.0;
-        let path_str = format!(
-            "M {ts:0.4} {y:0.4} L {te:0.4} {y:0.4} L {qe:0.4} {y2:0.4} L {qs:0.4} {y2:0.4} Z"
+        assert_eq!(filter_by_query_length(&mut ctgmap_set, 900), 1);
+        assert_eq!(ctgmap_set.records.len(), 2);
+        assert!(ctgmap_set.records.iter().all(|r| r.q_name != "ctg2"));
+        assert_eq!(filter_by_query_length(&mut ctgmap_set, 1001), 1);
+        assert_eq!(ctgmap_set.records.len(), 1);
+        assert_eq!(ctgmap_set.records[0].q_name, "ctg3");
+        assert_eq!(filter_by_query_length(&mut ctgmap_set, 0), 0);
+    }
+
+    #[test]
+    fn test_bed_region_color() {
+        assert_eq!(get_bed_region_color(&["chr1", "0", "100"], &CMAP), "#F00");
+        assert_eq!(
+            get_bed_region_color(&["chr1", "0", "100", "."], &CMAP),
+            "#F00"
         );
-        let mut path = element::Path::new()
-            .set("fill", color)
-            .set("stroke", "#000")
-            .set("stroke-width", "0.25")
-            .set("opacity", "0.7")
-            .set("stroke-opacity", "0.4")
-            .set("d", path_str);
-        let orientation = if record.orientation == 0 { '+' } else { '-' };
-        let t_dup_mark = if record.t_dup { 1 } else { 0 };
-        let q_dup_mark = if record.q_dup { 1 } else { 0 };
-        path.append(element::Title::new(format!(
-            "{}:{}-{} @ {}:{}-{} {}:{}:{}",
-            record.t_name,
-            record.ts,
-            record.te,
-            record.q_name,
-            record.qs,
-            record.qe,
-            orientation,
-            t_dup_mark,
-            q_dup_mark
-        )));
-
-        group.append(path);
-    });
-    Some(group)
+        let gene_color = get_bed_region_color(&["chr1", "0", "100", "gene"], &CMAP);
+        assert!(CMAP.contains(&gene_color));
+        assert_eq!(
+            get_bed_region_color(&["chr2", "500", "900", "gene", "0"], &CMAP),
+            gene_color
+        );
+        assert_eq!(
+            get_bed_region_color(&["chr1", "0", "100", ".", "500"], &CMAP),
+            get_bed_region_color(&["chr1", "0", "100", "500"], &CMAP)
+        );
+        // the colors follow --palette-preset
+        let okabe_ito_color = get_bed_region_color(
+            &["chr1", "0", "100", "gene"],
+            OptPalettePreset::OkabeIto.colors(),
+        );
+        assert!(OKABE_ITO_CMAP.contains(&okabe_ito_color));
+        assert_ne!(okabe_ito_color, gene_color);
+        assert_eq!(
+            get_bed_region_color(&["chr1", "0", "100"], OptPalettePreset::Viridis.colors()),
+            "#F00"
+        );
+    }
+
+    #[test]
+    fn test_read_bed_regions_errors() {
+        let dir = std::env::temp_dir().join(format!(
+            "pgr_generate_chr_aln_plot_bed_test_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("regions.bed");
+        let path = path.to_string_lossy().to_string();
+        assert!(matches!(read_bed_regions(&path), Err(PgrError::Io(_))));
+
+        std::fs::write(&path, "# comment\nchr1\t0\t100\tgene\n\nchr2\t10\t20\n").unwrap();
+        let regions = read_bed_regions(&path).unwrap();
+        assert_eq!(regions["chr1"], vec![(0, 100)]);
+        assert_eq!(regions["chr2"], vec![(10, 20)]);
+
+        std::fs::write(&path, "chr1\t0\t100\nchr1\tabc\t200\n").unwrap();
+        match read_bed_regions(&path) {
+            Err(PgrError::BedParse { line, reason }) => {
+                assert_eq!(line, "chr1\tabc\t200");
+                assert_eq!(reason, "invalid start");
+            }
+            _ => panic!("expect a BedParse error"),
+        };
+        std::fs::write(&path, "chr1\t0\n").unwrap();
+        assert!(matches!(
+            read_bed_regions(&path),
+            Err(PgrError::BedParse { .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_ctgmap_set() {
+        let mut ctgmap_set = CtgMapSet {
+            records: vec![test_record("chr1", 0, 400, "ctg1", 0, 400)],
+            target_length: vec![(0, "chr1".to_string(), 1000)],
+            query_length: vec![(0, "ctg1".to_string(), 1000)],
+        };
+        assert!(check_ctgmap_set(&ctgmap_set, "test.json").is_ok());
+        // the ctg_len mismatch does not stop the plot
+        ctgmap_set.records[0].ctg_len = 500;
+        assert!(check_ctgmap_set(&ctgmap_set, "test.json").is_ok());
+        ctgmap_set.records[0].qe = 1200;
+        match check_ctgmap_set(&ctgmap_set, "test.json") {
+            Err(PgrError::InvalidCtgmap(description)) => {
+                assert!(description.starts_with("record 0 of test.json: ctg1:0-1200"))
+            }
+            _ => panic!("expect an InvalidCtgmap error"),
+        };
+    }
+
+    #[test]
+    fn test_merge_collinear_records() {
+        let reverse = |ts, te, qs, qe| CtgMapRec {
+            orientation: 1,
+            ..test_record("chr1", ts, te, "ctg1", qs, qe)
+        };
+        let mut dup_record = test_record("chr1", 1150, 1300, "ctg1", 1150, 1300);
+        dup_record.t_dup = true;
+        let records = vec![
+            test_record("chr1", 0, 1000, "ctg1", 0, 1000),
+            reverse(5000, 5100, 900, 1000),
+            test_record("chr2", 1050, 1100, "ctg1", 1050, 1100),
+            test_record("chr1", 1050, 1100, "ctg1", 1050, 1100),
+            reverse(5150, 5200, 800, 880),
+            dup_record.clone(),
+            // the query gap is longer than the merge gap
+            test_record("chr1", 1300, 1400, "ctg1", 2000, 2100),
+            // the query goes backward
+            reverse(5250, 5300, 950, 990),
+        ];
+        let (merged, number_merged) = merge_collinear_records(records.clone(), 100);
+        assert_eq!(number_merged, 3);
+        let spans = merged
+            .iter()
+            .map(|r| {
+                (
+                    r.t_name.as_str(),
+                    r.ts,
+                    r.te,
+                    r.qs,
+                    r.qe,
+                    r.orientation,
+                    r.t_dup,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            vec![
+                ("chr1", 0, 1300, 0, 1300, 0, false),
+                ("chr1", 5000, 5200, 800, 1000, 1, false),
+                ("chr2", 1050, 1100, 1050, 1100, 0, false),
+                ("chr1", 1300, 1400, 2000, 2100, 0, false),
+                ("chr1", 5250, 5300, 950, 990, 1, false),
+            ]
+        );
+
+        let (merged, _) = merge_collinear_records(vec![dup_record.clone(), dup_record], 100);
+        assert!(merged[0].t_dup);
+        let (merged, number_merged) = merge_collinear_records(records, 0);
+        assert_eq!((merged.len(), number_merged), (8, 0));
+    }
+
+    #[test]
+    fn test_filtered_ctgmap_set() {
+        let ctgmap_set = CtgMapSet {
+            records: vec![
+                test_record("chr1", 0, 100, "ctg1", 0, 100),
+                test_record("chr2", 0, 100, "ctg2", 0, 100),
+                test_record("chr2", 200, 300, "ctg3", 0, 100),
+            ],
+            target_length: vec![(0, "chr1".to_string(), 1000), (1, "chr2".to_string(), 1000)],
+            query_length: vec![
+                (0, "ctg1".to_string(), 100),
+                (1, "ctg2".to_string(), 100),
+                (2, "ctg3".to_string(), 100),
+                (3, "ctg4".to_string(), 100),
+            ],
+        };
+        let filtered = get_filtered_ctgmap_set(&ctgmap_set, &["chr2".to_string()]);
+        assert_eq!(filtered.records.len(), 2);
+        assert_eq!(filtered.target_length, vec![(1, "chr2".to_string(), 1000)]);
+        assert_eq!(
+            filtered.query_length,
+            vec![(1, "ctg2".to_string(), 100), (2, "ctg3".to_string(), 100)]
+        );
+
+        // the output can be read back as a ctgmap.json file
+        let json = serde_json::to_string(&get_filtered_ctgmap_set(&ctgmap_set, &[])).unwrap();
+        let ctgmap_set = serde_json::from_str::<CtgMapSet>(&json).unwrap();
+        assert_eq!(ctgmap_set.records.len(), 3);
+        assert_eq!(ctgmap_set.target_length.len(), 2);
+        assert_eq!(ctgmap_set.query_length.len(), 3);
+    }
+
+    #[test]
+    fn test_bedgraph() {
+        let mut dup_record = test_record("chr1", 20, 80, "ctg2", 0, 60);
+        dup_record.t_dup = true;
+        let records = vec![
+            test_record("chr2", 0, 100, "ctg3", 0, 100),
+            test_record("chr1", 0, 100, "ctg1", 0, 100),
+            dup_record,
+        ];
+        let target_length = vec![(0, "chr1".to_string(), 100), (1, "chr2".to_string(), 100)];
+        let mut out = Vec::<u8>::new();
+        write_bedgraph(&mut out, &records, &target_length, false).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chr1\t0\t20\t1\nchr1\t20\t80\t2\nchr1\t80\t100\t1\nchr2\t0\t100\t1\n"
+        );
+        let mut out = Vec::<u8>::new();
+        write_bedgraph(&mut out, &records, &target_length, true).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chr1\t0\t100\t1\nchr2\t0\t100\t1\n"
+        );
+    }
+
+    #[test]
+    fn test_bedpe() {
+        let mut dup_record = test_record("chr1", 200, 300, "ctg2", 0, 100);
+        dup_record.t_dup = true;
+        dup_record.q_dup = true;
+        let mut q_dup_record = test_record("chr1", 600, 700, "ctg2", 300, 400);
+        q_dup_record.q_dup = true;
+        let mut inverted_record = test_record("chr1", 400, 500, "ctg2", 100, 200);
+        inverted_record.orientation = 1;
+        let ctgmap_set = CtgMapSet {
+            records: vec![
+                test_record("chr1", 0, 100, "ctg1", 0, 100),
+                dup_record,
+                inverted_record,
+                q_dup_record,
+                test_record("chr2", 0, 100, "ctg3", 0, 100),
+            ],
+            target_length: vec![(0, "chr1".to_string(), 1000), (1, "chr2".to_string(), 1000)],
+            query_length: vec![
+                (0, "ctg1".to_string(), 100),
+                (1, "ctg2".to_string(), 400),
+                (2, "ctg3".to_string(), 100),
+            ],
+        };
+        let get_bedpe = |opts: RenderOptions| {
+            let mut out = Vec::<u8>::new();
+            write_bedpe(&mut out, &get_drawn_records(&ctgmap_set, &opts)).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        // the q_dup record is not drawn without --show-dups
+        assert_eq!(
+            get_bedpe(RenderOptions {
+                ctg: vec!["chr1".to_string()],
+                ..RenderOptions::default()
+            }),
+            "chr1\t0\t100\tctg1\t0\t100\tctg1\t.\t+\t+\t0\t0\n\
+             chr1\t400\t500\tctg2\t100\t200\tctg2\t.\t+\t-\t0\t0\n"
+        );
+        let bedpe = get_bedpe(RenderOptions {
+            show_dups: true,
+            ..RenderOptions::default()
+        });
+        assert_eq!(bedpe.lines().count(), 5);
+        assert!(bedpe.contains("chr1\t200\t300\tctg2\t0\t100\tctg2\t.\t+\t+\t1\t1\n"));
+        assert!(bedpe.contains("chr1\t600\t700\tctg2\t300\t400\tctg2\t.\t+\t+\t0\t1\n"));
+        assert_eq!(
+            get_bedpe(RenderOptions {
+                inversions_only: true,
+                ..RenderOptions::default()
+            })
+            .lines()
+            .count(),
+            1
+        );
+        // the records are clipped to the region, the ones outside are not drawn
+        assert_eq!(
+            get_bedpe(RenderOptions {
+                ctg: vec!["chr1".to_string()],
+                region: Some(("chr1".to_string(), 50, 450)),
+                ..RenderOptions::default()
+            }),
+            "chr1\t50\t100\tctg1\t50\t100\tctg1\t.\t+\t+\t0\t0\n\
+             chr1\t400\t450\tctg2\t150\t200\tctg2\t.\t+\t-\t0\t0\n"
+        );
+        // only the longest records of each target are drawn with the cap
+        assert_eq!(
+            get_bedpe(RenderOptions {
+                show_dups: true,
+                max_records_per_contig: Some(1),
+                ..RenderOptions::default()
+            })
+            .lines()
+            .filter(|line| line.starts_with("chr1\t"))
+            .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_dropped_records() {
+        let records = vec![
+            test_record("chr1", 0, 100, "ctg1", 50, 150),
+            test_record("chr2", 200, 300, "ctg1", 300, 400),
+        ];
+        let mut out = Vec::<u8>::new();
+        write_dropped_records(&mut out, &records).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "ctg1\tchr1\t50\t150\nctg1\tchr2\t300\t400\n"
+        );
+    }
+
+    #[test]
+    fn test_natural_sort_conflict() {
+        let args = CmdOptions::try_parse_from([
+            "pgr-generate-chr-aln-plot",
+            "in.json",
+            "out",
+            "--natural-sort",
+            "--sort-chromosomes",
+            "as-input",
+        ]);
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn test_additional_ctgmap() {
+        assert_eq!(
+            parse_additional_ctgmap("/data/hap1.ctgmap.json:hap1"),
+            ("/data/hap1.ctgmap.json".to_string(), "hap1".to_string())
+        );
+        assert_eq!(
+            parse_additional_ctgmap("/data/hap2.ctgmap.json"),
+            (
+                "/data/hap2.ctgmap.json".to_string(),
+                "hap2.ctgmap.json".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_opacity() {
+        let args =
+            CmdOptions::try_parse_from(["pgr-generate-chr-aln-plot", "in.json", "out"]).unwrap();
+        assert_eq!((args.ribbon_opacity, args.track_opacity), (0.7, 0.7));
+    }
+
+    #[test]
+    fn test_title_name() {
+        assert_eq!(get_title_name("/data/HG002.ctgmap.json"), "HG002");
+        assert_eq!(get_title_name("HG002.mat.json"), "HG002.mat");
+        assert_eq!(get_title_name("HG002"), "HG002");
+    }
+
+    #[test]
+    fn test_gene_gff() {
+        let gff = "\
+##gff-version 3
+chr1\t.\tgene\t101\t300\t.\t+\t.\tID=gene1;Name=ABC1
+chr1\t.\tmRNA\t101\t300\t.\t+\t.\tID=mrna1;Parent=gene1
+chr1\t.\texon\t101\t200\t.\t+\t.\tParent=mrna1
+chr1\t.\tmRNA\t501\t520\t.\t-\t.\tID=mrna2
+chr2\t.\tgene\t1\t50\t.\t.\t.\tID=gene3
+##FASTA
+>chr1
+ACGT
+";
+        let genes = read_gene_gff(io::Cursor::new(gff)).unwrap();
+        assert_eq!(
+            genes.get("chr1").unwrap(),
+            &vec![
+                GeneFeature {
+                    bgn: 100,
+                    end: 300,
+                    strand: '+',
+                    name: "ABC1".to_string()
+                },
+                GeneFeature {
+                    bgn: 500,
+                    end: 520,
+                    strand: '-',
+                    name: "mrna2".to_string()
+                },
+            ]
+        );
+        assert_eq!(genes.get("chr2").unwrap()[0].strand, '.');
+        assert!(matches!(
+            read_gene_gff(io::Cursor::new("chr1\t.\tgene\t101\n")),
+            Err(PgrError::GffParse { .. })
+        ));
+        assert!(matches!(
+            read_gene_gff(io::Cursor::new("chr1\t.\tgene\tx\t300\t.\t+\t.\tID=g\n")),
+            Err(PgrError::GffParse { reason, .. }) if reason == "invalid start"
+        ));
+    }
 }
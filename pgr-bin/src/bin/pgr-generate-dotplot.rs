@@ -0,0 +1,254 @@
+const VERSION_STRING: &str = env!("VERSION_STRING");
+use clap::{self, CommandFactory, Parser};
+use pgr_bin::ctgmap::{read_ctgmap_json, CtgMapRec};
+use pgr_bin::error::PgrError;
+use pgr_bin::plot::{clip_record, get_scaling_factor, parse_region};
+use pgr_bin::render::{get_plot_document, write_plot_file, Theme};
+use svg::node::{element, Node};
+
+/// Generate the dot plot (SVG) of the alignment records of a query contig against a target chromosome or region
+#[derive(Parser, Debug)]
+#[clap(name = "pgr-generate-dotplot")]
+#[clap(author, version)]
+#[clap(about, long_about = None)]
+struct CmdOptions {
+    /// path to the ctgmap.json file
+    ctgmap_json_path: String,
+    /// the path of the output SVG file, or the HTML file with --html
+    output_path: String,
+    /// the name of the query contig
+    #[clap(long)]
+    query: String,
+    /// the name of the target chromosome, the whole chromosome is plotted
+    #[clap(long, required_unless_present = "region", conflicts_with = "region")]
+    target: Option<String>,
+    /// only plot the region of the target in the form of CHR:START-END, the records are clipped to the region
+    #[clap(long)]
+    region: Option<String>,
+    /// the width and the height of the SVG document
+    #[clap(long, default_value_t = 800.0)]
+    size: f64,
+    /// write the HTML page with the tooltips of the records instead of the SVG document
+    #[clap(long)]
+    html: bool,
+    /// the color theme of the output, the forward alignments are in the color of the labels
+    #[clap(long, default_value_t, value_enum)]
+    theme: Theme,
+}
+
+static REVERSE_COLOR: &str = "#FF8C00";
+
+/// the fraction of the document for the margins around the plot area
+const MARGIN: f64 = 0.1;
+
+/// the line of a record in the plot area of `size`, the target start is at the left and the query start is at the bottom,
+/// so the forward alignments go up to the right and the reverse ones go down to the right
+fn get_dotplot_line(
+    record: &CtgMapRec,
+    t_bgn: u32,
+    q_bgn: u32,
+    x_scaling_factor: f64,
+    y_scaling_factor: f64,
+    size: f64,
+) -> (f64, f64, f64, f64) {
+    let x = |t: u32| (t.saturating_sub(t_bgn) as f64) * x_scaling_factor;
+    let y = |q: u32| size - (q.saturating_sub(q_bgn) as f64) * y_scaling_factor;
+    let (qs, qe) = (record.qs.min(record.qe), record.qs.max(record.qe));
+    if record.orientation == 0 {
+        (x(record.ts), y(qs), x(record.te), y(qe))
+    } else {
+        (x(record.ts), y(qe), x(record.te), y(qs))
+    }
+}
+
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
+
+    let ctgmap_set = read_ctgmap_json(&args.ctgmap_json_path)?;
+    let region = args
+        .region
+        .as_ref()
+        .map(|region| {
+            parse_region(region).map_err(|e| PgrError::InvalidArgument(format!("--region: {}", e)))
+        })
+        .transpose()?;
+    let t_name = region
+        .as_ref()
+        .map(|(r_name, _, _)| r_name.clone())
+        .or(args.target.clone())
+        .unwrap();
+
+    let records = ctgmap_set
+        .records
+        .iter()
+        .filter(|r| r.t_name == t_name && r.q_name == args.query)
+        .filter_map(|r| match region {
+            Some((_, r_bgn, r_end)) => clip_record(r, r_bgn, r_end, true),
+            None => Some(r.clone()),
+        })
+        .collect::<Vec<_>>();
+    if records.is_empty() {
+        eprintln!(
+            "no alignment record of {} on {} is found",
+            args.query, t_name
+        );
+    };
+
+    // the whole target and query are plotted without a region, the query window is the span of the clipped records otherwise
+    let (t_bgn, t_end, q_bgn, q_end) = if let Some((_, r_bgn, r_end)) = region {
+        let q_bgn = records.iter().map(|r| r.qs.min(r.qe)).min().unwrap_or(0);
+        let q_end = records.iter().map(|r| r.qs.max(r.qe)).max().unwrap_or(0);
+        (r_bgn, r_end, q_bgn, q_end)
+    } else {
+        let t_len = ctgmap_set
+            .target_length
+            .iter()
+            .find(|(_, name, _)| *name == t_name)
+            .map(|(_, _, t_len)| *t_len)
+            .unwrap_or_else(|| records.iter().map(|r| r.te).max().unwrap_or(0));
+        let q_len = ctgmap_set
+            .query_length
+            .iter()
+            .find(|(_, name, _)| *name == args.query)
+            .map(|(_, _, q_len)| *q_len)
+            .unwrap_or_else(|| records.first().map(|r| r.ctg_len).unwrap_or(0));
+        (0, t_len, 0, q_len)
+    };
+
+    // the scaling factors map the spans to 0.8 of the document size, the plot area within the margins
+    let plot_size = args.size * (1.0 - 2.0 * MARGIN);
+    let (x_scaling_factor, _) = get_scaling_factor(args.size, None, (t_end - t_bgn).max(1) as f64);
+    let (y_scaling_factor, _) = get_scaling_factor(args.size, None, (q_end - q_bgn).max(1) as f64);
+
+    let mut plot = element::Group::new().set("id", "dotplot").set(
+        "transform",
+        format!("translate({} {})", args.size * MARGIN, args.size * MARGIN),
+    );
+    let frame = element::Rectangle::new()
+        .set("x", 0.0)
+        .set("y", 0.0)
+        .set("width", plot_size)
+        .set("height", plot_size)
+        .set("fill", "none")
+        .set("stroke", args.theme.fg_color())
+        .set("stroke-width", 1);
+    plot.append(frame);
+    records.iter().for_each(|r| {
+        let (x1, y1, x2, y2) = get_dotplot_line(
+            r,
+            t_bgn,
+            q_bgn,
+            x_scaling_factor,
+            y_scaling_factor,
+            plot_size,
+        );
+        let mut line = element::Line::new()
+            .set("x1", x1)
+            .set("y1", y1)
+            .set("x2", x2)
+            .set("y2", y2)
+            .set(
+                "stroke",
+                if r.orientation == 0 {
+                    args.theme.fg_color()
+                } else {
+                    REVERSE_COLOR
+                },
+            )
+            .set("stroke-width", 2);
+        line.append(element::Title::new(format!(
+            "{}:{}-{} {}:{}-{}",
+            r.t_name, r.ts, r.te, r.q_name, r.qs, r.qe
+        )));
+        plot.append(line);
+    });
+
+    let axis_label = |label: String, x: f64, y: f64, anchor: &str| {
+        element::Text::new(label)
+            .set("x", x)
+            .set("y", y)
+            .set("font-size", "12px")
+            .set("font-family", "sans-serif")
+            .set("text-anchor", anchor)
+            .set("fill", args.theme.fg_color())
+    };
+    plot.append(axis_label(
+        format!("{}", t_bgn),
+        0.0,
+        plot_size + 16.0,
+        "start",
+    ));
+    plot.append(axis_label(
+        format!("{}", t_end),
+        plot_size,
+        plot_size + 16.0,
+        "end",
+    ));
+    plot.append(axis_label(
+        t_name.clone(),
+        plot_size * 0.5,
+        plot_size + 32.0,
+        "middle",
+    ));
+    plot.append(axis_label(format!("{}", q_bgn), -4.0, plot_size, "end"));
+    plot.append(axis_label(format!("{}", q_end), -4.0, 12.0, "end"));
+    plot.append(axis_label(args.query.clone(), 0.0, 0.0, "middle").set(
+        "transform",
+        format!("translate(-32 {}) rotate(-90)", plot_size * 0.5),
+    ));
+
+    let document = get_plot_document(
+        (0.0, 0.0, args.size, args.size),
+        args.size,
+        args.size,
+        args.theme,
+    )
+    .add(plot);
+    write_plot_file(
+        &args.output_path,
+        &document,
+        !args.html,
+        false,
+        args.theme,
+        false,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dotplot_line() {
+        let record = CtgMapRec {
+            t_name: "chr1".to_string(),
+            ts: 1000,
+            te: 2000,
+            q_name: "ctg1".to_string(),
+            qs: 100,
+            qe: 600,
+            ctg_len: 1000,
+            orientation: 0,
+            ctg_orientation: 0,
+            t_dup: false,
+            t_ovlp: false,
+            q_dup: false,
+            q_ovlp: false,
+            identity: None,
+        };
+        assert_eq!(
+            get_dotplot_line(&record, 1000, 100, 0.1, 0.2, 100.0),
+            (0.0, 100.0, 100.0, 0.0)
+        );
+        // the reverse alignment goes from the query end at the target start
+        let record = CtgMapRec {
+            orientation: 1,
+            ..record
+        };
+        assert_eq!(
+            get_dotplot_line(&record, 0, 0, 0.01, 0.1, 100.0),
+            (10.0, 40.0, 20.0, 90.0)
+        );
+    }
+}
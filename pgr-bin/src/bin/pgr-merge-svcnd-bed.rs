@@ -1,9 +1,15 @@
 const VERSION_STRING: &str = env!("VERSION_STRING");
 use clap::{self, CommandFactory, Parser};
+use pgr_bin::chain::{read_chain_file, LiftOver};
+use pgr_bin::error::PgrError;
+use pgr_bin::reader::open_possibly_compressed;
+use pgr_bin::svcnd::{self, merge_overlapping_intervals};
 // use rayon::prelude::*;
 use rustc_hash::FxHashMap;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
 
 /// Merge svcnd from multiple *.svcnd.bed files into one and compute the merged regions
@@ -13,106 +19,337 @@ use std::path::Path;
 #[clap(author, version)]
 #[clap(about, long_about = None)]
 struct CmdOptions {
-    /// path to the file contain the input bed files, each line should be "label<tab>input file path"
+    /// path to the file contain the input bed files (plain, gzip or zstd compressed), each line should be "label<tab>input file path";
+    /// with --glob, a glob pattern (quoted, e.g. "*.svcnd.bed") of the input bed files;
+    /// with --stdin, it is ignored (use "-") and the manifest is read from stdin
     input_files: String,
     /// the path of the output files
     output_path: String,
     /// number of threads used in parallel (more memory usage), default to "0" using all CPUs available or the number set by RAYON_NUM_THREADS
     #[clap(long, default_value_t = 0)]
     number_of_thread: usize,
+    /// if given, write the summary statistics of the merged regions to this JSON file
+    #[clap(long)]
+    output_json_stats: Option<String>,
+    /// interpret the input_files argument as a glob pattern, the file stem of each file is used as the label
+    #[clap(long, conflicts_with = "stdin")]
+    glob: bool,
+    /// read the "label<tab>input file path" manifest from stdin
+    #[clap(long)]
+    stdin: bool,
+    /// if given, lift the merged regions over to another assembly with this UCSC chain file
+    /// and write them to "<output_path>.liftover.bed", regions that can not be lifted are marked as "FAIL"
+    #[clap(long)]
+    liftover_chain: Option<String>,
+    /// within each merged region, keep only the longest interval of each label,
+    /// useful when the input bed files contain overlapping records from split alignments
+    #[clap(long)]
+    deduplicate_by_overlap: bool,
+    /// weight the intervals by their lengths, a 5th "weighted_score" column as "label=fraction,..." is added to
+    /// the merged region lines for the fraction of the total interval length from each label
+    #[clap(long)]
+    weight_by_length: bool,
+    /// also merge the intervals separated by a gap of at most this many bases, e.g. the SV candidates of the
+    /// same event split by alignment artifacts
+    #[clap(long, default_value_t = 0)]
+    merge_gap: u32,
+    /// only merge the input files of these labels, as a comma-separated list or by repeating the option,
+    /// the N of "merged:N:M" counts the selected labels only
+    #[clap(long, value_delimiter = ',')]
+    label_filter: Vec<String>,
+    /// skip the input files of these labels, as a comma-separated list or by repeating the option
+    #[clap(long, value_delimiter = ',')]
+    label_exclude: Vec<String>,
 }
 
-type Interval = ((u32, u32), (String, String));
-fn main() {
-    CmdOptions::command().version(VERSION_STRING).get_matches();
-    let args = CmdOptions::parse();
+type Interval = svcnd::Interval<(String, String)>;
 
-    rayon::ThreadPoolBuilder::new()
-        .num_threads(args.number_of_thread)
-        .build_global()
-        .unwrap();
+/// parse the "label<tab>input file path" lines, the empty lines are skipped
+fn parse_input_manifest<R: BufRead>(reader: R) -> Result<Vec<(String, String)>, PgrError> {
+    let mut input_files = Vec::<(String, String)>::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        };
+        let rec = line.trim().split('\t').collect::<Vec<&str>>();
+        if rec.len() < 2 {
+            return Err(PgrError::InvalidArgument(format!(
+                "the input manifest line \"{}\" is not \"label<tab>input file path\"",
+                line
+            )));
+        };
+        input_files.push((rec[0].to_string(), rec[1].to_string()));
+    }
+    Ok(input_files)
+}
 
-    let input_files = BufReader::new(File::open(Path::new(&args.input_files)).unwrap());
+/// expand the glob pattern, the file stem of each path is used as its label
+fn glob_input_files(pattern: &str) -> Result<Vec<(String, String)>, PgrError> {
+    let paths = glob::glob(pattern).map_err(|e| {
+        PgrError::InvalidArgument(format!("invalid glob pattern {}: {}", pattern, e))
+    })?;
+    Ok(paths
+        .flat_map(|path| {
+            let path = path.ok()?;
+            let label = path.file_stem()?.to_string_lossy().to_string();
+            Some((label, path.to_string_lossy().to_string()))
+        })
+        .collect::<Vec<_>>())
+}
 
-    let input_files = input_files
-        .lines()
-        .flat_map(|line| {
-            if let Ok(line) = line {
-                let rec = line.trim().split('\t').collect::<Vec<&str>>();
-                assert!(rec.len() >= 2);
-                Some((rec[0].to_string(), rec[1].to_string()))
+/// keep the input files with the labels in `label_filter` (all if it is empty) and not in `label_exclude`,
+/// a label in either list but not in the input files is an error
+fn select_input_files(
+    input_files: Vec<(String, String)>,
+    label_filter: &[String],
+    label_exclude: &[String],
+) -> Result<Vec<(String, String)>, PgrError> {
+    if let Some(label) = label_filter
+        .iter()
+        .chain(label_exclude.iter())
+        .find(|label| !input_files.iter().any(|(l, _)| l == *label))
+    {
+        return Err(PgrError::InvalidArgument(format!(
+            "the label {} is not in the input files",
+            label
+        )));
+    };
+    Ok(input_files
+        .into_iter()
+        .filter(|(label, _)| label_filter.is_empty() || label_filter.contains(label))
+        .filter(|(label, _)| !label_exclude.contains(label))
+        .collect())
+}
+
+/// read the "chr<tab>bgn<tab>end<tab>annotation" records of a svcnd bed file into `interval_collection`,
+/// the comment lines and the empty lines are skipped, a record with its end before its start is an error
+fn read_svcnd_bed<R: BufRead>(
+    reader: R,
+    label: &str,
+    interval_collection: &mut FxHashMap<String, Vec<Interval>>,
+) -> Result<(), PgrError> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        };
+        let parse_error = |reason: &str| PgrError::BedParse {
+            line: line.clone(),
+            reason: reason.to_string(),
+        };
+        let fields = line.split('\t').collect::<Vec<&str>>();
+        if fields.len() < 4 {
+            return Err(parse_error("fewer than 4 columns"));
+        };
+        let bgn = fields[1]
+            .parse::<u32>()
+            .map_err(|_| parse_error("invalid start"))?;
+        let end = fields[2]
+            .parse::<u32>()
+            .map_err(|_| parse_error("invalid end"))?;
+        if end < bgn {
+            return Err(parse_error("end before start"));
+        };
+        let e = interval_collection
+            .entry(fields[0].to_string())
+            .or_default();
+        e.push(((bgn, end), (label.to_string(), fields[3].to_string())));
+    }
+    Ok(())
+}
+
+/// the second pass over the output of `merge_overlapping_intervals`, keep the longest interval of each label
+/// in a group (the first one in the sorted order if there is a tie), the boundaries of the merged regions are
+/// not changed
+fn deduplicate_group_intervals(
+    interval_groups: Vec<(u32, u32, Vec<Interval>)>,
+) -> Vec<(u32, u32, Vec<Interval>)> {
+    interval_groups
+        .into_iter()
+        .map(|(bgn, end, intervals)| {
+            let mut longest = FxHashMap::<String, usize>::default();
+            intervals
+                .iter()
+                .enumerate()
+                .for_each(|(idx, ((s, e), payload))| {
+                    let best = longest.entry(payload.0.clone()).or_insert(idx);
+                    let ((best_s, best_e), _) = intervals[*best];
+                    if e.saturating_sub(*s) > best_e.saturating_sub(best_s) {
+                        *best = idx;
+                    }
+                });
+            let intervals = intervals
+                .into_iter()
+                .enumerate()
+                .filter(|(idx, (_, payload))| longest.get(&payload.0) == Some(idx))
+                .map(|(_, interval)| interval)
+                .collect::<Vec<_>>();
+            (bgn, end, intervals)
+        })
+        .collect::<Vec<_>>()
+}
+
+/// the fraction of the total length of the intervals in a group from each label, sorted by the labels
+fn get_weighted_scores(intervals: &[Interval]) -> Vec<(String, f64)> {
+    let mut label_length = FxHashMap::<String, u64>::default();
+    let mut total_length = 0_u64;
+    intervals.iter().for_each(|((bgn, end), payload)| {
+        let length = end.saturating_sub(*bgn) as u64;
+        *label_length.entry(payload.0.clone()).or_default() += length;
+        total_length += length;
+    });
+    let mut weighted_scores = label_length
+        .into_iter()
+        .map(|(label, length)| {
+            let score = if total_length > 0 {
+                length as f64 / total_length as f64
             } else {
-                None
-            }
+                0.0
+            };
+            (label, score)
         })
         .collect::<Vec<_>>();
+    weighted_scores.sort_by(|a, b| a.0.cmp(&b.0));
+    weighted_scores
+}
+
+#[derive(Serialize, Default, Debug)]
+struct MergeStats {
+    total_merged_regions: usize,
+    per_chromosome_counts: BTreeMap<String, usize>,
+    label_coverage: BTreeMap<String, u64>,
+    unique_to_single_label_count: usize,
+}
+
+impl MergeStats {
+    /// add a merged group, the intervals in the group are sorted by their start positions
+    fn add_group(&mut self, chr: &str, intervals: &[Interval]) {
+        self.total_merged_regions += 1;
+        *self
+            .per_chromosome_counts
+            .entry(chr.to_string())
+            .or_default() += 1;
 
-    let mut interval_collection =
-        FxHashMap::<String, Vec<((u32, u32), (String, String))>>::default();
-    input_files.iter().for_each(|(label, path)| {
-        let bed_reader = BufReader::new(File::open(Path::new(path)).unwrap());
-        bed_reader.lines().for_each(|line| {
-            if let Ok(line) = line {
-                if line.starts_with('#') {
-                    return;
-                };
-                let err_msg = format!("fail to parse on {}", line);
-                let fields = line.split('\t').collect::<Vec<&str>>();
-                let chr = fields[0].to_string();
-                let bgn = fields[1].parse::<u32>().expect(&err_msg);
-                let end = fields[2].parse::<u32>().expect(&err_msg);
-                let annotation = fields[3].to_string();
-                let e = interval_collection.entry(chr).or_insert_with(Vec::new);
-                e.push(((bgn, end), (label.clone(), annotation)));
+        // the bases covered by each label, overlapping intervals of the same label are counted once
+        let mut label_span = FxHashMap::<String, (u32, u32, u64)>::default();
+        intervals.iter().for_each(|((bgn, end), payload)| {
+            let e = label_span
+                .entry(payload.0.clone())
+                .or_insert((*bgn, *bgn, 0));
+            if *bgn > e.1 {
+                e.2 += (e.1 - e.0) as u64;
+                (e.0, e.1) = (*bgn, *end);
+            } else if *end > e.1 {
+                e.1 = *end;
             }
         });
-    });
+        if label_span.len() == 1 {
+            self.unique_to_single_label_count += 1;
+        };
+        label_span
+            .into_iter()
+            .for_each(|(label, (bgn, end, covered))| {
+                *self.label_coverage.entry(label).or_default() += covered + (end - bgn) as u64;
+            });
+    }
+}
 
-    let group_intervals = |intervals: &mut Vec<Interval>| -> Vec<(u32, u32, Vec<Interval>)> {
-        let mut interval_groups = Vec::<(u32, u32, Vec<Interval>)>::new();
-        if intervals.is_empty() {
-            return interval_groups;
-        }
+/// write the lifted merged region as "chr<tab>bgn<tab>end<tab>merged:...<tab>source_chr:bgn-end:strand",
+/// or the source coordinates with a "FAIL" in the last column if it can not be lifted
+fn write_liftover_record<W: Write>(
+    out: &mut W,
+    liftover: &LiftOver,
+    chr: &str,
+    bgn: u32,
+    end: u32,
+    number_labels: usize,
+    number_intervals: u32,
+) -> Result<(), io::Error> {
+    match liftover.lift(chr, bgn, end) {
+        Some((q_name, q_bgn, q_end, q_strand)) => writeln!(
+            out,
+            "{}\t{}\t{}\tmerged:{}:{}\t{}:{}-{}:{}",
+            q_name, q_bgn, q_end, number_labels, number_intervals, chr, bgn, end, q_strand
+        ),
+        None => writeln!(
+            out,
+            "{}\t{}\t{}\tmerged:{}:{}\tFAIL",
+            chr, bgn, end, number_labels, number_intervals
+        ),
+    }
+}
 
-        intervals.sort();
-        let (mut current_bgn, mut current_end) = intervals.first().unwrap().0;
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
 
-        let mut current_groups = Vec::<Interval>::new();
-        intervals.iter().for_each(|(interval, payload)| {
-            if current_end < interval.0 {
-                interval_groups.push((current_bgn, current_end, current_groups.clone()));
-                current_groups.clear();
-                current_groups.push((*interval, payload.clone()));
-                (current_bgn, current_end) = *interval;
-            } else {
-                current_groups.push((*interval, payload.clone()));
-                if current_end < interval.1 {
-                    current_end = interval.1;
-                } 
-            }
-        });
-        if !current_groups.is_empty() {
-            interval_groups.push((current_bgn, current_end, current_groups.clone()));
-        }
-        interval_groups
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(args.number_of_thread)
+        .build_global()
+        .map_err(|e| {
+            PgrError::InvalidArgument(format!(
+                "can't build the thread pool of --number-of-thread {}: {}",
+                args.number_of_thread, e
+            ))
+        })?;
+
+    let input_files = if args.glob {
+        glob_input_files(&args.input_files)?
+    } else if args.stdin {
+        parse_input_manifest(io::stdin().lock())?
+    } else {
+        parse_input_manifest(BufReader::new(
+            File::open(Path::new(&args.input_files)).map_err(PgrError::io_context(format!(
+                "can't open the input manifest {}",
+                args.input_files
+            )))?,
+        ))?
+    };
+    let input_files = select_input_files(input_files, &args.label_filter, &args.label_exclude)?;
+
+    let mut interval_collection = FxHashMap::<String, Vec<Interval>>::default();
+    for (label, path) in input_files.iter() {
+        let bed_reader = open_possibly_compressed(Path::new(path))?;
+        read_svcnd_bed(bed_reader, label, &mut interval_collection)?;
+    }
+
+    let liftover = match args.liftover_chain.as_ref() {
+        Some(path) => Some(LiftOver::new(read_chain_file(path).map_err(
+            PgrError::io_context(format!("can't read the liftover chain file {}", path)),
+        )?)),
+        None => None,
+    };
+    let mut out_liftover_bed = match liftover {
+        Some(_) => Some(BufWriter::new(File::create(Path::new(&format!(
+            "{}.liftover.bed",
+            args.output_path
+        )))?)),
+        None => None,
     };
 
-    let mut out_bed = BufWriter::new(File::create(Path::new(&args.output_path)).unwrap());
+    let mut out_bed = BufWriter::new(File::create(Path::new(&args.output_path))?);
+    let mut merge_stats = MergeStats::default();
     let mut keys = interval_collection.keys().cloned().collect::<Vec<_>>();
     keys.sort();
-    keys.into_iter().for_each(|key| {
+    for key in keys {
         let intervals = interval_collection.get_mut(&key).unwrap();
-        let interval_groups = group_intervals(intervals);
-        interval_groups.into_iter().for_each(|intervals| {
+        let mut interval_groups = merge_overlapping_intervals(intervals, args.merge_gap);
+        if args.deduplicate_by_overlap {
+            interval_groups = deduplicate_group_intervals(interval_groups);
+        };
+        for intervals in interval_groups {
             if intervals.2.is_empty() {
-                return;
+                continue;
             }
             let itvl_group_bgn = intervals.0;
             let itvl_group_end = intervals.1;
             if itvl_group_bgn > itvl_group_end {
-                return;
+                continue;
             };
 
+            merge_stats.add_group(&key, &intervals.2);
+
             let mut label_count = FxHashMap::<String, u32>::default();
             let mut total_interval_counts = 0u32;
             intervals.2.iter().for_each(|(_interval, payload)| {
@@ -121,7 +358,7 @@ fn main() {
                 total_interval_counts += 1;
             });
 
-            writeln!(
+            write!(
                 out_bed,
                 "{}\t{}\t{}\tmerged:{}:{}",
                 key,
@@ -129,10 +366,32 @@ fn main() {
                 itvl_group_end,
                 label_count.len(),
                 total_interval_counts
-            )
-            .expect("unable to write the output file");
+            )?;
+            if args.weight_by_length {
+                let weighted_scores = get_weighted_scores(&intervals.2)
+                    .into_iter()
+                    .map(|(label, score)| format!("{}={:0.4}", label, score))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                write!(out_bed, "\t{}", weighted_scores)?;
+            };
+            writeln!(out_bed)?;
 
-            intervals.2.iter().for_each(|(interval, payload)| {
+            if let (Some(liftover), Some(out_liftover_bed)) =
+                (liftover.as_ref(), out_liftover_bed.as_mut())
+            {
+                write_liftover_record(
+                    out_liftover_bed,
+                    liftover,
+                    &key,
+                    itvl_group_bgn,
+                    itvl_group_end,
+                    label_count.len(),
+                    total_interval_counts,
+                )?;
+            };
+
+            for (interval, payload) in intervals.2.iter() {
                 let number_haplotype = label_count.len();
                 let e = label_count.entry(payload.0.clone()).or_default();
                 writeln!(
@@ -147,9 +406,226 @@ fn main() {
                     itvl_group_end,
                     number_haplotype,
                     *e,
-                )
-                .expect("unable to write the output file");
+                )?;
+            }
+        }
+    }
+
+    if let Some(output_json_stats) = args.output_json_stats {
+        let out_json = BufWriter::new(File::create(Path::new(&output_json_stats))?);
+        serde_json::to_writer_pretty(out_json, &merge_stats)?;
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_interval(bgn: u32, end: u32, label: &str) -> Interval {
+        ((bgn, end), (label.to_string(), "SV".to_string()))
+    }
+
+    #[test]
+    fn test_parse_input_manifest() {
+        let manifest = "hap1\t/data/hap1.svcnd.bed\n\nhap2\t/data/hap2.svcnd.bed\n";
+        let input_files = parse_input_manifest(io::Cursor::new(manifest)).unwrap();
+        assert_eq!(
+            input_files,
+            vec![
+                ("hap1".to_string(), "/data/hap1.svcnd.bed".to_string()),
+                ("hap2".to_string(), "/data/hap2.svcnd.bed".to_string()),
+            ]
+        );
+
+        // a line without the input file path is an error instead of a panic
+        assert!(matches!(
+            parse_input_manifest(io::Cursor::new("hap1\t/data/hap1.svcnd.bed\nhap2\n")),
+            Err(PgrError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_glob_input_files() {
+        let dir = std::env::temp_dir().join(format!(
+            "pgr_merge_svcnd_bed_glob_test_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        ["hap1.bed", "hap2.bed", "notes.txt"]
+            .iter()
+            .for_each(|name| {
+                File::create(dir.join(name)).unwrap();
             });
+        let pattern = dir.join("*.bed").to_string_lossy().to_string();
+        let input_files = glob_input_files(&pattern).unwrap();
+        let labels = input_files.iter().map(|v| v.0.clone()).collect::<Vec<_>>();
+        assert_eq!(labels, vec!["hap1".to_string(), "hap2".to_string()]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(
+            glob_input_files("a/**b"),
+            Err(PgrError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_svcnd_bed() {
+        let mut interval_collection = FxHashMap::<String, Vec<Interval>>::default();
+        let bed = "# comment\nchr1\t100\t200\tSV\n\nchr2\t10\t20\tDEL\n";
+        read_svcnd_bed(io::Cursor::new(bed), "hap1", &mut interval_collection).unwrap();
+        assert_eq!(
+            interval_collection.get("chr1").unwrap(),
+            &vec![test_interval(100, 200, "hap1")]
+        );
+        assert_eq!(interval_collection.len(), 2);
+
+        let reason = |bed: &str| {
+            let mut interval_collection = FxHashMap::<String, Vec<Interval>>::default();
+            match read_svcnd_bed(io::Cursor::new(bed), "hap1", &mut interval_collection) {
+                Err(PgrError::BedParse { reason, .. }) => reason,
+                _ => panic!("expect a bed parse error"),
+            }
+        };
+        assert_eq!(reason("chr1\t100\t200\n"), "fewer than 4 columns");
+        assert_eq!(reason("chr1\tabc\t200\tSV\n"), "invalid start");
+        assert_eq!(reason("chr1\t100\t-1\tSV\n"), "invalid end");
+        assert_eq!(reason("chr1\t200\t100\tSV\n"), "end before start");
+    }
+
+    #[test]
+    fn test_select_input_files() {
+        let input_files = ["hap1", "hap2", "hap3"]
+            .iter()
+            .map(|label| (label.to_string(), format!("{}.bed", label)))
+            .collect::<Vec<_>>();
+        let get_labels = |label_filter: &[&str], label_exclude: &[&str]| {
+            let to_vec = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            select_input_files(
+                input_files.clone(),
+                &to_vec(label_filter),
+                &to_vec(label_exclude),
+            )
+            .map(|v| v.into_iter().map(|(l, _)| l).collect::<Vec<_>>())
+        };
+        assert_eq!(get_labels(&[], &[]).unwrap(), vec!["hap1", "hap2", "hap3"]);
+        assert_eq!(
+            get_labels(&["hap3", "hap1"], &[]).unwrap(),
+            vec!["hap1", "hap3"]
+        );
+        assert_eq!(get_labels(&[], &["hap2"]).unwrap(), vec!["hap1", "hap3"]);
+        assert_eq!(
+            get_labels(&["hap1", "hap2"], &["hap2"]).unwrap(),
+            vec!["hap1"]
+        );
+        assert!(matches!(
+            get_labels(&["hap4"], &[]),
+            Err(PgrError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            get_labels(&[], &["hap4"]),
+            Err(PgrError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_glob_and_stdin_conflict() {
+        let args = CmdOptions::try_parse_from([
+            "pgr-merge-svcnd-bed",
+            "*.bed",
+            "out.bed",
+            "--glob",
+            "--stdin",
+        ]);
+        assert!(args.is_err());
+    }
+
+    #[test]
+    fn test_merge_stats() {
+        let mut interval_collection = FxHashMap::<String, Vec<Interval>>::default();
+        interval_collection.insert(
+            "chr1".to_string(),
+            vec![
+                test_interval(100, 200, "hap1"),
+                test_interval(150, 300, "hap1"),
+                test_interval(180, 250, "hap2"),
+                test_interval(1000, 1100, "hap2"),
+            ],
+        );
+        interval_collection.insert("chr2".to_string(), vec![test_interval(10, 20, "hap1")]);
+
+        let mut merge_stats = MergeStats::default();
+        let mut keys = interval_collection.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        keys.into_iter().for_each(|key| {
+            let intervals = interval_collection.get_mut(&key).unwrap();
+            merge_overlapping_intervals(intervals, 0)
+                .into_iter()
+                .for_each(|(_bgn, _end, intervals)| merge_stats.add_group(&key, &intervals));
         });
-    });
+
+        let json = serde_json::to_value(&merge_stats).unwrap();
+        let expected = serde_json::json!({
+            "total_merged_regions": 3,
+            "per_chromosome_counts": {"chr1": 2, "chr2": 1},
+            "label_coverage": {"hap1": 210, "hap2": 170},
+            "unique_to_single_label_count": 2
+        });
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn test_deduplicate_group_intervals() {
+        let mut intervals = vec![
+            // self-overlapping records of hap1 from split alignments
+            test_interval(100, 200, "hap1"),
+            test_interval(150, 400, "hap1"),
+            test_interval(380, 420, "hap1"),
+            test_interval(180, 250, "hap2"),
+            // two records of hap2 with the same length, the first one is kept
+            test_interval(1000, 1100, "hap2"),
+            test_interval(1050, 1150, "hap2"),
+        ];
+        let interval_groups =
+            deduplicate_group_intervals(merge_overlapping_intervals(&mut intervals, 0));
+        assert_eq!(
+            interval_groups,
+            vec![
+                (
+                    100,
+                    420,
+                    vec![
+                        test_interval(150, 400, "hap1"),
+                        test_interval(180, 250, "hap2")
+                    ]
+                ),
+                (1000, 1150, vec![test_interval(1000, 1100, "hap2")]),
+            ]
+        );
+
+        // the groups without duplicated labels are not changed
+        let mut intervals = vec![test_interval(10, 20, "hap1"), test_interval(15, 30, "hap2")];
+        let interval_groups = merge_overlapping_intervals(&mut intervals, 0);
+        assert_eq!(
+            deduplicate_group_intervals(interval_groups.clone()),
+            interval_groups
+        );
+    }
+
+    #[test]
+    fn test_weighted_scores() {
+        let intervals = vec![
+            test_interval(100, 200, "hap2"),
+            test_interval(150, 400, "hap1"),
+            test_interval(180, 230, "hap2"),
+        ];
+        assert_eq!(
+            get_weighted_scores(&intervals),
+            vec![("hap1".to_string(), 0.625), ("hap2".to_string(), 0.375)]
+        );
+        assert_eq!(
+            get_weighted_scores(&[test_interval(10, 10, "hap1")]),
+            vec![("hap1".to_string(), 0.0)]
+        );
+    }
 }
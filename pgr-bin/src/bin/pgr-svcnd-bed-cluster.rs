@@ -0,0 +1,163 @@
+const VERSION_STRING: &str = env!("VERSION_STRING");
+use clap::{self, CommandFactory, Parser};
+use pgr_bin::error::PgrError;
+use pgr_bin::reader::open_possibly_compressed;
+use pgr_bin::svcnd::{cluster_intervals, parse_merged_annotation};
+use rustc_hash::FxHashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+
+/// Cluster the merged regions in the output of pgr-merge-svcnd-bed on the same chromosome by the gaps between them
+/// Each merged region is written with its cluster ID and the number of the merged regions in the cluster
+#[derive(Parser, Debug)]
+#[clap(name = "pgr-svcnd-bed-cluster")]
+#[clap(author, version)]
+#[clap(about, long_about = None)]
+struct CmdOptions {
+    /// path to the merged bed file from pgr-merge-svcnd-bed
+    merged_bed_path: String,
+    /// the path of the output file
+    output_path: String,
+    /// the merged regions with a gap (in bases) at most this to any region of a cluster join the cluster
+    #[clap(long, default_value_t = 10000)]
+    cluster_distance: u32,
+    /// only write the clusters with at least this many merged regions
+    #[clap(long, default_value_t = 1)]
+    min_cluster_size: usize,
+}
+
+/// the (bgn, end, chr) of a merged region and its header line
+type MergedRegion = ((u32, u32, String), String);
+
+/// the merged regions from `reader` sorted by the positions, the sub-records and the comment lines are skipped
+fn read_merged_regions<R: BufRead>(reader: R) -> Result<Vec<MergedRegion>, PgrError> {
+    let mut regions = Vec::<MergedRegion>::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        };
+        let fields = line.split('\t').collect::<Vec<&str>>();
+        if !fields.get(3).is_some_and(|v| v.starts_with("merged:")) {
+            continue;
+        };
+        let parse_error = |reason: &str| PgrError::BedParse {
+            line: line.clone(),
+            reason: reason.to_string(),
+        };
+        parse_merged_annotation(fields[3])
+            .ok_or_else(|| parse_error("invalid merged annotation"))?;
+        let bgn = fields[1]
+            .parse::<u32>()
+            .map_err(|_| parse_error("invalid start"))?;
+        let end = fields[2]
+            .parse::<u32>()
+            .map_err(|_| parse_error("invalid end"))?;
+        regions.push(((bgn, end, fields[0].to_string()), line));
+    }
+    regions.sort_by(|a, b| (&a.0 .2, a.0 .0, a.0 .1).cmp(&(&b.0 .2, b.0 .0, b.0 .1)));
+    Ok(regions)
+}
+
+/// write the merged regions in the clusters with at least `min_cluster_size` regions
+/// with the cluster ID and the cluster size in two more columns, return the number of the clusters written
+fn write_clusters<W: Write>(
+    out: &mut W,
+    regions: &[MergedRegion],
+    cluster_distance: u32,
+    min_cluster_size: usize,
+) -> Result<usize, io::Error> {
+    let intervals = regions.iter().map(|v| v.0.clone()).collect::<Vec<_>>();
+    let cluster_ids = cluster_intervals(&intervals, cluster_distance);
+    let mut cluster_size = FxHashMap::<usize, usize>::default();
+    cluster_ids
+        .iter()
+        .for_each(|id| *cluster_size.entry(*id).or_default() += 1);
+    for ((_, line), id) in regions.iter().zip(cluster_ids.iter()) {
+        let size = cluster_size[id];
+        if size >= min_cluster_size {
+            writeln!(out, "{}\t{}\t{}", line, id, size)?;
+        };
+    }
+    Ok(cluster_size
+        .values()
+        .filter(|&&size| size >= min_cluster_size)
+        .count())
+}
+
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
+
+    let reader = open_possibly_compressed(Path::new(&args.merged_bed_path))?;
+    let regions = read_merged_regions(reader)?;
+    let mut out_bed = BufWriter::new(File::create(Path::new(&args.output_path))?);
+    let number_clusters = write_clusters(
+        &mut out_bed,
+        &regions,
+        args.cluster_distance,
+        args.min_cluster_size,
+    )?;
+    eprintln!(
+        "{} clusters of {} merged regions are written",
+        number_clusters,
+        regions.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MERGED_BED: &str = "\
+chr2\t10\t20\tmerged:1:1
+chr2\t10\t20\thap3:SV:10-20:1:1
+chr1\t1000\t1100\tmerged:1:1
+chr1\t1000\t1100\thap2:DEL:1000-1100:1:1
+chr1\t100\t300\tmerged:2:2
+chr1\t100\t200\thap1:SV:100-300:2:1
+chr1\t180\t300\thap2:SV:100-300:2:1
+";
+
+    #[test]
+    fn test_write_clusters() {
+        let regions = read_merged_regions(io::Cursor::new(TEST_MERGED_BED)).unwrap();
+        assert_eq!(
+            regions.iter().map(|v| v.0.clone()).collect::<Vec<_>>(),
+            vec![
+                (100, 300, "chr1".to_string()),
+                (1000, 1100, "chr1".to_string()),
+                (10, 20, "chr2".to_string()),
+            ]
+        );
+
+        let mut out = Vec::<u8>::new();
+        assert_eq!(write_clusters(&mut out, &regions, 1000, 1).unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "chr1\t100\t300\tmerged:2:2\t0\t2\n\
+             chr1\t1000\t1100\tmerged:1:1\t0\t2\n\
+             chr2\t10\t20\tmerged:1:1\t1\t1\n"
+        );
+
+        let mut out = Vec::<u8>::new();
+        assert_eq!(write_clusters(&mut out, &regions, 100, 2).unwrap(), 0);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_read_merged_regions_errors() {
+        let reason = |bed: &str| match read_merged_regions(io::Cursor::new(bed)) {
+            Err(PgrError::BedParse { reason, .. }) => reason,
+            _ => panic!("expect a bed parse error"),
+        };
+        assert_eq!(
+            reason("chr1\t100\t300\tmerged:2\n"),
+            "invalid merged annotation"
+        );
+        assert_eq!(reason("chr1\tx\t300\tmerged:2:2\n"), "invalid start");
+        assert_eq!(reason("chr1\t100\t\tmerged:2:2\n"), "invalid end");
+    }
+}
@@ -0,0 +1,283 @@
+const VERSION_STRING: &str = env!("VERSION_STRING");
+use clap::{self, CommandFactory, Parser};
+use pgr_bin::error::PgrError;
+use pgr_bin::reader::open_possibly_compressed;
+use pgr_bin::svcnd::{parse_merged_annotation, parse_sub_record_annotation};
+use rustc_hash::FxHashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+
+/// Filter the merged regions in the output of pgr-merge-svcnd-bed by the number of haplotypes and the labels
+/// The header line of each kept merged region is written with all of its sub-records in the original format
+#[derive(Parser, Debug)]
+#[clap(name = "pgr-svcnd-bed-filter")]
+#[clap(author, version)]
+#[clap(about, long_about = None)]
+struct CmdOptions {
+    /// path to the merged bed file from pgr-merge-svcnd-bed
+    merged_bed_path: String,
+    /// the path of the output file
+    output_path: String,
+    /// keep the merged regions with at least this many haplotypes (the N of "merged:N:M")
+    #[clap(long)]
+    min_haplotypes: Option<usize>,
+    /// keep the merged regions with at most this many haplotypes (the N of "merged:N:M")
+    #[clap(long)]
+    max_haplotypes: Option<usize>,
+    /// keep the merged regions with a sub-record from any of these labels, as a comma-separated list or by repeating the option
+    #[clap(long, value_delimiter = ',')]
+    labels_include: Vec<String>,
+    /// drop the merged regions with a sub-record from any of these labels, as a comma-separated list or by repeating the option
+    #[clap(long, value_delimiter = ',')]
+    labels_exclude: Vec<String>,
+}
+
+struct GroupFilter {
+    min_haplotypes: Option<usize>,
+    max_haplotypes: Option<usize>,
+    labels_include: FxHashSet<String>,
+    labels_exclude: FxHashSet<String>,
+}
+
+impl GroupFilter {
+    fn keep(&self, number_haplotypes: usize, labels: &[String]) -> bool {
+        if let Some(min_haplotypes) = self.min_haplotypes {
+            if number_haplotypes < min_haplotypes {
+                return false;
+            };
+        };
+        if let Some(max_haplotypes) = self.max_haplotypes {
+            if number_haplotypes > max_haplotypes {
+                return false;
+            };
+        };
+        if !self.labels_include.is_empty()
+            && !labels.iter().any(|l| self.labels_include.contains(l))
+        {
+            return false;
+        };
+        !labels.iter().any(|l| self.labels_exclude.contains(l))
+    }
+}
+
+/// the 4th column of a bed line
+fn get_annotation(line: &str) -> Option<&str> {
+    line.split('\t').nth(3)
+}
+
+fn write_group<W: Write>(
+    out: &mut W,
+    filter: &GroupFilter,
+    group: &[String],
+    number_haplotypes: usize,
+) -> Result<bool, io::Error> {
+    let labels = group[1..]
+        .iter()
+        .flat_map(|line| get_annotation(line).and_then(parse_sub_record_annotation))
+        .map(|(l, _)| l.to_string())
+        .collect::<Vec<_>>();
+    if !filter.keep(number_haplotypes, &labels) {
+        return Ok(false);
+    };
+    for line in group.iter() {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(true)
+}
+
+/// filter the merged regions from `reader`, the comment lines are copied as they are,
+/// return the number of the merged regions kept and the total number of the merged regions
+fn filter_merged_bed<R: BufRead, W: Write>(
+    reader: R,
+    out: &mut W,
+    filter: &GroupFilter,
+) -> Result<(usize, usize), PgrError> {
+    let mut kept = 0_usize;
+    let mut total = 0_usize;
+    // the header line of the current merged region and its sub-records
+    let mut group = Vec::<String>::new();
+    let mut number_haplotypes = 0_usize;
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with('#') {
+            writeln!(out, "{}", line)?;
+            continue;
+        };
+        if line.trim().is_empty() {
+            continue;
+        };
+        let parse_error = |reason: &str| PgrError::BedParse {
+            line: line.clone(),
+            reason: reason.to_string(),
+        };
+        let annotation =
+            get_annotation(&line).ok_or_else(|| parse_error("fewer than 4 columns"))?;
+        if annotation.starts_with("merged:") {
+            if !group.is_empty() && write_group(out, filter, &group, number_haplotypes)? {
+                kept += 1;
+            };
+            number_haplotypes = parse_merged_annotation(annotation)
+                .ok_or_else(|| parse_error("invalid merged annotation"))?
+                .0;
+            group.clear();
+            group.push(line);
+            total += 1;
+        } else if group.is_empty() {
+            return Err(parse_error("a sub-record before any merged region"));
+        } else {
+            group.push(line);
+        }
+    }
+    if !group.is_empty() && write_group(out, filter, &group, number_haplotypes)? {
+        kept += 1;
+    };
+    Ok((kept, total))
+}
+
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
+
+    let filter = GroupFilter {
+        min_haplotypes: args.min_haplotypes,
+        max_haplotypes: args.max_haplotypes,
+        labels_include: args.labels_include.into_iter().collect(),
+        labels_exclude: args.labels_exclude.into_iter().collect(),
+    };
+    let reader = open_possibly_compressed(Path::new(&args.merged_bed_path))?;
+    let mut out_bed = BufWriter::new(File::create(Path::new(&args.output_path))?);
+    let (kept, total) = filter_merged_bed(reader, &mut out_bed, &filter)?;
+    eprintln!("{} of {} merged regions are kept", kept, total);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MERGED_BED: &str = "\
+chr1\t100\t300\tmerged:2:3
+chr1\t100\t200\thap1:SV:100-300:2:1
+chr1\t150\t300\thap1:SV:100-300:2:2
+chr1\t180\t250\thap2:SV:100-300:2:1
+chr1\t1000\t1100\tmerged:1:1
+chr1\t1000\t1100\thap2:DEL:a:b:1000-1100:1:1
+chr2\t10\t20\tmerged:1:1
+chr2\t10\t20\thap3:SV:10-20:1:1
+";
+
+    fn test_filter(
+        min_haplotypes: Option<usize>,
+        max_haplotypes: Option<usize>,
+        labels_include: &[&str],
+        labels_exclude: &[&str],
+    ) -> (String, (usize, usize)) {
+        let filter = GroupFilter {
+            min_haplotypes,
+            max_haplotypes,
+            labels_include: labels_include.iter().map(|s| s.to_string()).collect(),
+            labels_exclude: labels_exclude.iter().map(|s| s.to_string()).collect(),
+        };
+        let mut out = Vec::<u8>::new();
+        let counts =
+            filter_merged_bed(io::Cursor::new(TEST_MERGED_BED), &mut out, &filter).unwrap();
+        (String::from_utf8(out).unwrap(), counts)
+    }
+
+    #[test]
+    fn test_filter_by_haplotypes() {
+        let (out, counts) = test_filter(None, None, &[], &[]);
+        assert_eq!(out, TEST_MERGED_BED);
+        assert_eq!(counts, (3, 3));
+
+        let (out, counts) = test_filter(Some(2), None, &[], &[]);
+        assert_eq!(
+            out,
+            TEST_MERGED_BED
+                .lines()
+                .take(4)
+                .collect::<Vec<_>>()
+                .join("\n")
+                + "\n"
+        );
+        assert_eq!(counts, (1, 3));
+
+        let (out, counts) = test_filter(None, Some(1), &[], &[]);
+        assert_eq!(
+            out,
+            TEST_MERGED_BED
+                .lines()
+                .skip(4)
+                .collect::<Vec<_>>()
+                .join("\n")
+                + "\n"
+        );
+        assert_eq!(counts, (2, 3));
+    }
+
+    #[test]
+    fn test_filter_by_labels() {
+        let (out, counts) = test_filter(None, None, &["hap2"], &[]);
+        assert_eq!(
+            out,
+            TEST_MERGED_BED
+                .lines()
+                .take(6)
+                .collect::<Vec<_>>()
+                .join("\n")
+                + "\n"
+        );
+        assert_eq!(counts, (2, 3));
+
+        let (out, counts) = test_filter(None, None, &[], &["hap1"]);
+        assert_eq!(
+            out,
+            TEST_MERGED_BED
+                .lines()
+                .skip(4)
+                .collect::<Vec<_>>()
+                .join("\n")
+                + "\n"
+        );
+        assert_eq!(counts, (2, 3));
+
+        // the regions unique to hap2
+        let (out, counts) = test_filter(None, Some(1), &["hap2"], &[]);
+        assert_eq!(
+            out,
+            TEST_MERGED_BED
+                .lines()
+                .skip(4)
+                .take(2)
+                .collect::<Vec<_>>()
+                .join("\n")
+                + "\n"
+        );
+        assert_eq!(counts, (1, 3));
+    }
+
+    #[test]
+    fn test_sub_record_before_header() {
+        let filter = GroupFilter {
+            min_haplotypes: None,
+            max_haplotypes: None,
+            labels_include: FxHashSet::default(),
+            labels_exclude: FxHashSet::default(),
+        };
+        let reason =
+            |bed: &str| match filter_merged_bed(io::Cursor::new(bed), &mut io::sink(), &filter) {
+                Err(PgrError::BedParse { reason, .. }) => reason,
+                _ => panic!("expect a bed parse error"),
+            };
+        assert_eq!(
+            reason("chr1\t100\t200\thap1:SV:100-300:2:1\n"),
+            "a sub-record before any merged region"
+        );
+        assert_eq!(
+            reason("chr1\t100\t200\tmerged:2:x\n"),
+            "invalid merged annotation"
+        );
+        assert_eq!(reason("chr1\t100\t200\n"), "fewer than 4 columns");
+    }
+}
@@ -0,0 +1,359 @@
+const VERSION_STRING: &str = env!("VERSION_STRING");
+use clap::{self, CommandFactory, Parser};
+use pgr_bin::error::PgrError;
+use pgr_bin::reader::open_possibly_compressed;
+use pgr_bin::svcnd::{parse_merged_annotation, parse_sub_record_annotation};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Convert the merged regions in the output of pgr-merge-svcnd-bed to a VCF file
+/// Each merged region is a VCF record and the labels of its sub-records are the samples with the SV
+#[derive(Parser, Debug)]
+#[clap(name = "pgr-svcnd-bed-to-vcf")]
+#[clap(author, version)]
+#[clap(about, long_about = None)]
+struct CmdOptions {
+    /// path to the merged bed file from pgr-merge-svcnd-bed
+    merged_bed_path: String,
+    /// the path of the output VCF file
+    output_path: String,
+    /// path to a file of "label<tab>sample name" lines to map the labels to the VCF samples, the labels of the
+    /// same sample (e.g. the two haplotypes) are counted together in the genotype; the samples are in the order
+    /// of the file and the labels not in the file are used as the sample names
+    #[clap(long)]
+    sample_names: Option<String>,
+}
+
+/// a merged region and the (label, svcnd annotation) of its sub-records
+#[derive(Debug, PartialEq)]
+struct MergedRegion {
+    chr: String,
+    bgn: u32,
+    end: u32,
+    number_haplotypes: usize,
+    number_intervals: u32,
+    sub_records: Vec<(String, String)>,
+}
+
+/// read the merged regions, the comment lines and the empty lines are skipped
+fn read_merged_bed<R: BufRead>(reader: R) -> Result<Vec<MergedRegion>, PgrError> {
+    let mut merged_regions = Vec::<MergedRegion>::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        };
+        let parse_error = |reason: &str| PgrError::BedParse {
+            line: line.clone(),
+            reason: reason.to_string(),
+        };
+        let fields = line.split('\t').collect::<Vec<&str>>();
+        if fields.len() < 4 {
+            return Err(parse_error("fewer than 4 columns"));
+        };
+        if fields[3].starts_with("merged:") {
+            let (number_haplotypes, number_intervals) = parse_merged_annotation(fields[3])
+                .ok_or_else(|| parse_error("invalid merged annotation"))?;
+            merged_regions.push(MergedRegion {
+                chr: fields[0].to_string(),
+                bgn: fields[1]
+                    .parse::<u32>()
+                    .map_err(|_| parse_error("invalid start"))?,
+                end: fields[2]
+                    .parse::<u32>()
+                    .map_err(|_| parse_error("invalid end"))?,
+                number_haplotypes,
+                number_intervals,
+                sub_records: vec![],
+            });
+        } else {
+            let (label, annotation) = parse_sub_record_annotation(fields[3])
+                .ok_or_else(|| parse_error("invalid sub-record annotation"))?;
+            merged_regions
+                .last_mut()
+                .ok_or_else(|| parse_error("a sub-record before any merged region"))?
+                .sub_records
+                .push((label.to_string(), annotation.to_string()));
+        }
+    }
+    Ok(merged_regions)
+}
+
+/// read the "label<tab>sample name" lines
+fn read_sample_names<R: BufRead>(reader: R) -> Result<Vec<(String, String)>, PgrError> {
+    let mut sample_names = Vec::<(String, String)>::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        };
+        let fields = line.trim().split('\t').collect::<Vec<&str>>();
+        if fields.len() < 2 {
+            return Err(PgrError::BedParse {
+                line: line.clone(),
+                reason: "expect \"label<tab>sample name\"".to_string(),
+            });
+        };
+        sample_names.push((fields[0].to_string(), fields[1].to_string()));
+    }
+    Ok(sample_names)
+}
+
+/// the SV type from the annotation of a svcnd bed record of pgr-alnmap, the gaps between the alignments
+/// on the target ("TG") and the annotated deletions are DEL and the others are BND
+fn get_sv_type(annotation: &str) -> &'static str {
+    match annotation.split(':').next() {
+        Some("TG") | Some("DEL") => "DEL",
+        _ => "BND",
+    }
+}
+
+/// the genotype from the number of the labels of a sample with the SV
+fn get_genotype(count: usize) -> &'static str {
+    match count {
+        0 => "0/0",
+        1 => "0/1",
+        _ => "1/1",
+    }
+}
+
+/// write the header and one record for each merged region, a region is a DEL if all of its sub-records are
+/// deletions, or a BND otherwise; the regions are 0-based half-open as bed and POS is the 1-based start
+fn write_vcf<W: Write>(
+    out: &mut W,
+    merged_regions: &[MergedRegion],
+    sample_names: &[(String, String)],
+) -> Result<(), io::Error> {
+    let mut label_to_sample = sample_names
+        .iter()
+        .map(|(label, sample)| (label.clone(), sample.clone()))
+        .collect::<FxHashMap<_, _>>();
+    let mut samples = Vec::<String>::new();
+    let mut sample_set = FxHashSet::<String>::default();
+    sample_names.iter().for_each(|(_, sample)| {
+        if sample_set.insert(sample.clone()) {
+            samples.push(sample.clone());
+        };
+    });
+    let mut other_labels = merged_regions
+        .iter()
+        .flat_map(|r| r.sub_records.iter().map(|(label, _)| label))
+        .filter(|label| !label_to_sample.contains_key(*label))
+        .cloned()
+        .collect::<Vec<_>>();
+    other_labels.sort();
+    other_labels.dedup();
+    other_labels.into_iter().for_each(|label| {
+        if sample_set.insert(label.clone()) {
+            samples.push(label.clone());
+        };
+        label_to_sample.insert(label.clone(), label);
+    });
+    let sample_idx = samples
+        .iter()
+        .enumerate()
+        .map(|(idx, sample)| (sample.clone(), idx))
+        .collect::<FxHashMap<_, _>>();
+
+    writeln!(out, "##fileformat=VCFv4.2")?;
+    writeln!(out, "##source=pgr-svcnd-bed-to-vcf")?;
+    let mut chrs = FxHashSet::<&str>::default();
+    for r in merged_regions {
+        if chrs.insert(&r.chr) {
+            writeln!(out, "##contig=<ID={}>", r.chr)?;
+        };
+    }
+    writeln!(
+        out,
+        r#"##INFO=<ID=SVTYPE,Number=1,Type=String,Description="Type of structural variant">"#
+    )?;
+    writeln!(
+        out,
+        r#"##INFO=<ID=END,Number=1,Type=Integer,Description="End position of the merged region">"#
+    )?;
+    writeln!(
+        out,
+        r#"##INFO=<ID=SVLEN,Number=1,Type=Integer,Description="Length of the merged region, negative for DEL">"#
+    )?;
+    writeln!(
+        out,
+        r#"##INFO=<ID=NHAP,Number=1,Type=Integer,Description="Number of the labels with the merged region">"#
+    )?;
+    writeln!(
+        out,
+        r#"##INFO=<ID=NINTV,Number=1,Type=Integer,Description="Number of the intervals in the merged region">"#
+    )?;
+    writeln!(out, r#"##FILTER=<ID=PASS,Description="All filters passed">"#)?;
+    writeln!(out, r#"##ALT=<ID=DEL,Description="Deletion">"#)?;
+    writeln!(out, r#"##ALT=<ID=BND,Description="Breakend">"#)?;
+    writeln!(
+        out,
+        r#"##FORMAT=<ID=GT,Number=1,Type=String,Description="Genotype">"#
+    )?;
+    write!(out, "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT")?;
+    for sample in samples.iter() {
+        write!(out, "\t{}", sample)?;
+    }
+    writeln!(out)?;
+
+    for r in merged_regions {
+        let sv_type = if !r.sub_records.is_empty()
+            && r.sub_records.iter().all(|(_, a)| get_sv_type(a) == "DEL")
+        {
+            "DEL"
+        } else {
+            "BND"
+        };
+        let sv_len = r.end.saturating_sub(r.bgn) as i64;
+        let sv_len = if sv_type == "DEL" { -sv_len } else { sv_len };
+        // the labels of each sample with the SV, a label with several sub-records is counted once
+        let mut sample_labels = vec![FxHashSet::<&str>::default(); samples.len()];
+        r.sub_records.iter().for_each(|(label, _)| {
+            let idx = sample_idx[label_to_sample.get(label).unwrap()];
+            sample_labels[idx].insert(label);
+        });
+        write!(
+            out,
+            "{}\t{}\t.\tN\t<{}>\t.\tPASS\tSVTYPE={};END={};SVLEN={};NHAP={};NINTV={}\tGT",
+            r.chr,
+            r.bgn + 1,
+            sv_type,
+            sv_type,
+            r.end,
+            sv_len,
+            r.number_haplotypes,
+            r.number_intervals
+        )?;
+        for labels in sample_labels {
+            write!(out, "\t{}", get_genotype(labels.len()))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
+
+    let reader = open_possibly_compressed(Path::new(&args.merged_bed_path))?;
+    let merged_regions = read_merged_bed(reader)?;
+    let sample_names = match args.sample_names.as_ref() {
+        Some(path) => read_sample_names(BufReader::new(File::open(Path::new(path)).map_err(
+            PgrError::io_context(format!("can't open the sample names file {}", path)),
+        )?))?,
+        None => vec![],
+    };
+    let mut out_vcf = BufWriter::new(File::create(Path::new(&args.output_path))?);
+    write_vcf(&mut out_vcf, &merged_regions, &sample_names)?;
+    eprintln!("{} merged regions are written", merged_regions.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MERGED_BED: &str = "\
+chr1\t100\t300\tmerged:2:3
+chr1\t100\t200\thap1:SVC:ctg1:10-20:0:0:A:100-300:2:1
+chr1\t150\t300\thap1:SVC:ctg1:50-90:0:0:A:100-300:2:2
+chr1\t180\t250\thap2:SVC:ctg2:10-80:0:0:L:100-300:2:1
+chr1\t1000\t1100\tmerged:2:2
+chr1\t1000\t1100\thap2:TG:ctg2>ctg3:0:500:800:0:0:1000-1100:2:1
+chr1\t1000\t1100\thap3:TG:ctg4>ctg5:0:500:700:0:0:1000-1100:2:1
+";
+
+    fn get_records(vcf: &str) -> Vec<Vec<String>> {
+        vcf.lines()
+            .filter(|line| !line.starts_with("##"))
+            .map(|line| line.split('\t').map(|s| s.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_read_merged_bed() {
+        let merged_regions = read_merged_bed(io::Cursor::new(TEST_MERGED_BED)).unwrap();
+        assert_eq!(merged_regions.len(), 2);
+        assert_eq!(
+            merged_regions[1],
+            MergedRegion {
+                chr: "chr1".to_string(),
+                bgn: 1000,
+                end: 1100,
+                number_haplotypes: 2,
+                number_intervals: 2,
+                sub_records: vec![
+                    ("hap2".to_string(), "TG:ctg2>ctg3:0:500:800:0:0".to_string()),
+                    ("hap3".to_string(), "TG:ctg4>ctg5:0:500:700:0:0".to_string()),
+                ],
+            }
+        );
+
+        let reason = |bed: &str| match read_merged_bed(io::Cursor::new(bed)) {
+            Err(PgrError::BedParse { reason, .. }) => reason,
+            _ => panic!("expect a bed parse error"),
+        };
+        assert_eq!(
+            reason("chr1\t100\t200\thap1:SV:100-300:2:1\n"),
+            "a sub-record before any merged region"
+        );
+        assert_eq!(reason("chr1\t100\t200\n"), "fewer than 4 columns");
+        assert_eq!(
+            reason("chr1\t100\t200\tmerged:2\n"),
+            "invalid merged annotation"
+        );
+        assert_eq!(reason("chr1\tabc\t200\tmerged:2:3\n"), "invalid start");
+    }
+
+    #[test]
+    fn test_write_vcf() {
+        let merged_regions = read_merged_bed(io::Cursor::new(TEST_MERGED_BED)).unwrap();
+        let mut out = Vec::<u8>::new();
+        write_vcf(&mut out, &merged_regions, &[]).unwrap();
+        let vcf = String::from_utf8(out).unwrap();
+        assert!(vcf.starts_with("##fileformat=VCFv4.2\n"));
+        assert!(vcf.contains("##contig=<ID=chr1>\n"));
+        let records = get_records(&vcf);
+        assert_eq!(records[0][9..], ["hap1", "hap2", "hap3"]);
+        assert_eq!(
+            records[1],
+            [
+                "chr1",
+                "101",
+                ".",
+                "N",
+                "<BND>",
+                ".",
+                "PASS",
+                "SVTYPE=BND;END=300;SVLEN=200;NHAP=2;NINTV=3",
+                "GT",
+                "0/1",
+                "0/1",
+                "0/0"
+            ]
+        );
+        assert_eq!(records[2][4], "<DEL>");
+        assert_eq!(records[2][7], "SVTYPE=DEL;END=1100;SVLEN=-100;NHAP=2;NINTV=2");
+        assert_eq!(records[2][9..], ["0/0", "0/1", "0/1"]);
+    }
+
+    #[test]
+    fn test_sample_names() {
+        let sample_names =
+            read_sample_names(io::Cursor::new("hap2\tHG002\nhap3\tHG002\n\n")).unwrap();
+        assert_eq!(sample_names.len(), 2);
+        assert!(read_sample_names(io::Cursor::new("hap2\n")).is_err());
+
+        let merged_regions = read_merged_bed(io::Cursor::new(TEST_MERGED_BED)).unwrap();
+        let mut out = Vec::<u8>::new();
+        write_vcf(&mut out, &merged_regions, &sample_names).unwrap();
+        let records = get_records(&String::from_utf8(out).unwrap());
+        // the samples in the file go first, both haplotypes of HG002 have the DEL
+        assert_eq!(records[0][9..], ["HG002", "hap1"]);
+        assert_eq!(records[1][9..], ["0/1", "0/1"]);
+        assert_eq!(records[2][9..], ["1/1", "0/0"]);
+    }
+}
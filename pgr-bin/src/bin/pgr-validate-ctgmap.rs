@@ -0,0 +1,39 @@
+const VERSION_STRING: &str = env!("VERSION_STRING");
+use clap::{self, CommandFactory, Parser};
+use pgr_bin::ctgmap::{read_validated_ctgmap_json, validate_ctgmap_set};
+use pgr_bin::error::PgrError;
+
+/// Check the records in a ctgmap.json file, report the invalid records to stderr and exit with 1 if any is found
+#[derive(Parser, Debug)]
+#[clap(name = "pgr-validate-ctgmap")]
+#[clap(author, version)]
+#[clap(about, long_about = None)]
+struct CmdOptions {
+    /// path to a ctgmap.json file
+    ctgmap_json_path: String,
+}
+
+fn main() -> Result<(), PgrError> {
+    CmdOptions::command().version(VERSION_STRING).get_matches();
+    let args = CmdOptions::parse();
+
+    // a file that can't be read or parsed is reported as the error of main, which also exits with 1
+    let ctgmap_set = read_validated_ctgmap_json(&args.ctgmap_json_path)?;
+    let errors = validate_ctgmap_set(&ctgmap_set);
+    errors.iter().for_each(|e| {
+        let r = &ctgmap_set.records[e.record_index];
+        eprintln!(
+            "record {} ({}:{}-{} @ {}:{}-{}): {:?}: {}",
+            e.record_index, r.t_name, r.ts, r.te, r.q_name, r.qs, r.qe, e.kind, e.description
+        );
+    });
+    if !errors.is_empty() {
+        eprintln!(
+            "{} validation error(s) found in {} records",
+            errors.len(),
+            ctgmap_set.records.len()
+        );
+        std::process::exit(1);
+    };
+    Ok(())
+}
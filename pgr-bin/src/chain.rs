@@ -0,0 +1,232 @@
+use rustc_hash::FxHashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// an ungapped aligned block of a chain, the query coordinates are on the query strand of the chain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainBlock {
+    pub t_start: u32,
+    pub q_start: u32,
+    pub size: u32,
+}
+
+/// a chain record from a UCSC chain file, the blocks are sorted by the target coordinates
+#[derive(Clone, Debug)]
+pub struct ChainRecord {
+    pub score: f64,
+    pub t_name: String,
+    pub t_size: u32,
+    pub t_start: u32,
+    pub t_end: u32,
+    pub q_name: String,
+    pub q_size: u32,
+    pub q_strand: char,
+    pub q_start: u32,
+    pub q_end: u32,
+    pub id: String,
+    pub blocks: Vec<ChainBlock>,
+}
+
+fn parse_error(line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("fail to parse the chain file on \"{}\"", line),
+    )
+}
+
+/// parse the records of a UCSC chain file
+pub fn parse_chain<R: BufRead>(reader: R) -> Result<Vec<ChainRecord>, io::Error> {
+    let mut chains = Vec::<ChainRecord>::new();
+    let mut t_pos = 0_u32;
+    let mut q_pos = 0_u32;
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        };
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+        if fields[0] == "chain" {
+            if fields.len() < 12 {
+                return Err(parse_error(line));
+            };
+            let parse_u32 = |s: &str| s.parse::<u32>().map_err(|_| parse_error(line));
+            let chain = ChainRecord {
+                score: fields[1].parse::<f64>().map_err(|_| parse_error(line))?,
+                t_name: fields[2].to_string(),
+                t_size: parse_u32(fields[3])?,
+                t_start: parse_u32(fields[5])?,
+                t_end: parse_u32(fields[6])?,
+                q_name: fields[7].to_string(),
+                q_size: parse_u32(fields[8])?,
+                q_strand: fields[9].chars().next().unwrap_or('+'),
+                q_start: parse_u32(fields[10])?,
+                q_end: parse_u32(fields[11])?,
+                id: fields.get(12).unwrap_or(&"").to_string(),
+                blocks: vec![],
+            };
+            t_pos = chain.t_start;
+            q_pos = chain.q_start;
+            chains.push(chain);
+        } else {
+            let chain = chains.last_mut().ok_or_else(|| parse_error(line))?;
+            let values = fields
+                .iter()
+                .map(|s| s.parse::<u32>().map_err(|_| parse_error(line)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let size = values[0];
+            chain.blocks.push(ChainBlock {
+                t_start: t_pos,
+                q_start: q_pos,
+                size,
+            });
+            // the last line of a chain only has the block size
+            let (dt, dq) = if values.len() >= 3 {
+                (values[1], values[2])
+            } else {
+                (0, 0)
+            };
+            t_pos += size + dt;
+            q_pos += size + dq;
+        }
+    }
+    Ok(chains)
+}
+
+/// read a UCSC chain file
+pub fn read_chain_file<P: AsRef<Path>>(path: P) -> Result<Vec<ChainRecord>, io::Error> {
+    parse_chain(BufReader::new(File::open(path)?))
+}
+
+impl ChainRecord {
+    /// map the target interval `[bgn, end)` through the blocks of the chain,
+    /// return the query interval on the forward strand and the number of bases mapped
+    pub fn map_interval(&self, bgn: u32, end: u32) -> Option<(u32, u32, u32)> {
+        // the blocks do not overlap and are sorted, so the first block ends after `bgn` can be found by a binary search
+        let first = self
+            .blocks
+            .partition_point(|block| block.t_start + block.size <= bgn);
+        let mut q_range: Option<(u32, u32)> = None;
+        let mut mapped = 0_u32;
+        for block in self.blocks[first..].iter() {
+            if block.t_start >= end {
+                break;
+            };
+            let ovlp_bgn = bgn.max(block.t_start);
+            let ovlp_end = end.min(block.t_start + block.size);
+            if ovlp_bgn >= ovlp_end {
+                continue;
+            };
+            let qb = block.q_start + (ovlp_bgn - block.t_start);
+            let qe = block.q_start + (ovlp_end - block.t_start);
+            mapped += ovlp_end - ovlp_bgn;
+            q_range = Some(match q_range {
+                Some((s, e)) => (s.min(qb), e.max(qe)),
+                None => (qb, qe),
+            });
+        }
+        let (qb, qe) = q_range?;
+        if self.q_strand == '-' {
+            Some((self.q_size - qe, self.q_size - qb, mapped))
+        } else {
+            Some((qb, qe, mapped))
+        }
+    }
+}
+
+/// the chains indexed by the target sequence names for lifting target intervals over to the query assembly
+pub struct LiftOver {
+    chains: FxHashMap<String, Vec<ChainRecord>>,
+}
+
+impl LiftOver {
+    pub fn new(chains: Vec<ChainRecord>) -> Self {
+        let mut chain_map = FxHashMap::<String, Vec<ChainRecord>>::default();
+        chains.into_iter().for_each(|chain| {
+            chain_map
+                .entry(chain.t_name.clone())
+                .or_default()
+                .push(chain);
+        });
+        chain_map
+            .values_mut()
+            .for_each(|chains| chains.sort_by_key(|chain| chain.t_start));
+        LiftOver { chains: chain_map }
+    }
+
+    /// lift `t_name:[bgn, end)` over with the chain mapping the most bases (ties broken by the chain score),
+    /// return `(q_name, q_bgn, q_end, q_strand)` or `None` if no base of the interval can be mapped
+    pub fn lift(&self, t_name: &str, bgn: u32, end: u32) -> Option<(String, u32, u32, char)> {
+        let chains = self.chains.get(t_name)?;
+        chains
+            .iter()
+            .filter(|chain| chain.t_start < end && bgn < chain.t_end)
+            .flat_map(|chain| {
+                chain
+                    .map_interval(bgn, end)
+                    .map(|(qb, qe, mapped)| (mapped, chain, qb, qe))
+            })
+            .max_by(|a, b| a.0.cmp(&b.0).then(a.1.score.total_cmp(&b.1.score)))
+            .map(|(_, chain, qb, qe)| (chain.q_name.clone(), qb, qe, chain.q_strand))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_CHAIN: &str = "\
+chain 1000 chr1 1000 + 100 400 chrA 2000 + 0 310 1
+100 50 60
+150
+
+chain 500 chr1 1000 + 600 700 chrB 500 - 100 200 2
+100
+";
+
+    #[test]
+    fn test_parse_chain() {
+        let chains = parse_chain(io::Cursor::new(TEST_CHAIN)).unwrap();
+        assert_eq!(chains.len(), 2);
+        assert_eq!(
+            chains[0].blocks,
+            vec![
+                ChainBlock {
+                    t_start: 100,
+                    q_start: 0,
+                    size: 100
+                },
+                ChainBlock {
+                    t_start: 250,
+                    q_start: 160,
+                    size: 150
+                },
+            ]
+        );
+        assert_eq!(chains[1].q_strand, '-');
+        assert_eq!(chains[1].id, "2");
+    }
+
+    #[test]
+    fn test_lift() {
+        let liftover = LiftOver::new(parse_chain(io::Cursor::new(TEST_CHAIN)).unwrap());
+        assert_eq!(
+            liftover.lift("chr1", 150, 200),
+            Some(("chrA".to_string(), 50, 100, '+'))
+        );
+        // spanning the gap between the two blocks
+        assert_eq!(
+            liftover.lift("chr1", 150, 300),
+            Some(("chrA".to_string(), 50, 210, '+'))
+        );
+        // a gap in the target
+        assert_eq!(liftover.lift("chr1", 210, 240), None);
+        // reverse strand, the query coordinates are converted to the forward strand
+        assert_eq!(
+            liftover.lift("chr1", 600, 650),
+            Some(("chrB".to_string(), 350, 400, '-'))
+        );
+        assert_eq!(liftover.lift("chr2", 0, 100), None);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::ctgmap::CtgMapSet;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// the union of the intervals as the sorted non-overlapping intervals, the adjacent intervals are joined
+pub fn merge_intervals(intervals: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut intervals = intervals
+        .iter()
+        .filter(|(s, e)| s < e)
+        .cloned()
+        .collect::<Vec<_>>();
+    intervals.sort();
+    let mut merged = Vec::<(u32, u32)>::new();
+    intervals
+        .into_iter()
+        .for_each(|(s, e)| match merged.last_mut() {
+            Some(last) if s <= last.1 => last.1 = last.1.max(e),
+            _ => merged.push((s, e)),
+        });
+    merged
+}
+
+/// the intervals of [0, `length`) not covered by any of the intervals
+pub fn get_uncovered_intervals(intervals: &[(u32, u32)], length: u32) -> Vec<(u32, u32)> {
+    let mut uncovered = Vec::<(u32, u32)>::new();
+    let mut c_pos = 0_u32;
+    merge_intervals(intervals).into_iter().for_each(|(s, e)| {
+        if s > c_pos {
+            uncovered.push((c_pos, s.min(length)));
+        };
+        c_pos = c_pos.max(e);
+    });
+    if c_pos < length {
+        uncovered.push((c_pos, length));
+    };
+    uncovered.retain(|(s, e)| s < e);
+    uncovered
+}
+
+/// the Jaccard similarity (the bases in both / the bases in either) of the bases covered by the two sets of intervals,
+/// 0 if neither covers any base
+pub fn compute_jaccard(intervals_a: &[(u32, u32)], intervals_b: &[(u32, u32)]) -> f64 {
+    let (a, b) = (merge_intervals(intervals_a), merge_intervals(intervals_b));
+    let covered =
+        |intervals: &[(u32, u32)]| intervals.iter().map(|(s, e)| (e - s) as u64).sum::<u64>();
+    let mut intersection = 0_u64;
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let (s, e) = (a[i].0.max(b[j].0), a[i].1.min(b[j].1));
+        if s < e {
+            intersection += (e - s) as u64;
+        };
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    let union = covered(&a) + covered(&b) - intersection;
+    if union == 0 {
+        0.0
+    } else {
+        intersection as f64 / union as f64
+    }
+}
+
+/// the N50 of the lengths, the length at which the longer ones (including itself) have half of the total bases
+pub fn get_n50(lengths: &[u32]) -> u32 {
+    let mut lengths = lengths.to_vec();
+    lengths.sort_by(|a, b| b.cmp(a));
+    let total = lengths.iter().map(|&v| v as u64).sum::<u64>();
+    let mut acc = 0_u64;
+    for len in lengths {
+        acc += len as u64;
+        if acc * 2 >= total {
+            return len;
+        };
+    }
+    0
+}
+
+/// the statistics of the alignment records of an assembly on a target
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TargetAlignmentStats {
+    /// the N50 of the lengths of the query contigs aligned to the target
+    pub n50: u32,
+    /// the fraction of the target bases covered by the alignment records
+    pub coverage_breadth: f64,
+    pub number_contigs: usize,
+    /// the number of the uncovered intervals between the covered ones
+    pub number_gaps: usize,
+}
+
+/// the target intervals of the records of each target, the records with q_dup are skipped
+pub fn get_target_intervals(ctgmap_set: &CtgMapSet) -> FxHashMap<String, Vec<(u32, u32)>> {
+    let mut tgt_intervals = FxHashMap::<String, Vec<(u32, u32)>>::default();
+    ctgmap_set.records.iter().for_each(|r| {
+        if r.q_dup {
+            return;
+        };
+        tgt_intervals
+            .entry(r.t_name.clone())
+            .or_default()
+            .push((r.ts, r.te));
+    });
+    tgt_intervals
+}
+
+/// the statistics of the records of an assembly on each target, the records with q_dup are skipped
+pub fn get_target_alignment_stats(
+    ctgmap_set: &CtgMapSet,
+) -> FxHashMap<String, TargetAlignmentStats> {
+    let query_length = ctgmap_set
+        .query_length
+        .iter()
+        .map(|(_, q_name, q_len)| (q_name.as_str(), *q_len))
+        .collect::<FxHashMap<_, _>>();
+    let mut tgt_contigs = FxHashMap::<&str, FxHashMap<&str, u32>>::default();
+    ctgmap_set.records.iter().for_each(|r| {
+        if r.q_dup {
+            return;
+        };
+        let q_len = *query_length.get(r.q_name.as_str()).unwrap_or(&r.ctg_len);
+        tgt_contigs
+            .entry(r.t_name.as_str())
+            .or_default()
+            .insert(r.q_name.as_str(), q_len);
+    });
+    let tgt_intervals = get_target_intervals(ctgmap_set);
+    ctgmap_set
+        .target_length
+        .iter()
+        .filter_map(|(_, t_name, t_len)| {
+            let contigs = tgt_contigs.get(t_name.as_str())?;
+            let merged = merge_intervals(&tgt_intervals[t_name]);
+            let covered = merged.iter().map(|(s, e)| (e - s) as u64).sum::<u64>();
+            let stats = TargetAlignmentStats {
+                n50: get_n50(&contigs.values().cloned().collect::<Vec<_>>()),
+                coverage_breadth: if *t_len == 0 {
+                    0.0
+                } else {
+                    covered as f64 / *t_len as f64
+                },
+                number_contigs: contigs.len(),
+                number_gaps: merged.len().saturating_sub(1),
+            };
+            Some((t_name.clone(), stats))
+        })
+        .collect()
+}
+
+/// the targets in either of the two sets, in the order of `target_length` of the first one and then the second one
+pub fn get_union_targets(set_a: &CtgMapSet, set_b: &CtgMapSet) -> Vec<(String, u32)> {
+    let mut t_names = FxHashSet::<&str>::default();
+    let mut target_length = set_a.target_length.clone();
+    target_length.sort();
+    let mut target_length_b = set_b.target_length.clone();
+    target_length_b.sort();
+    target_length
+        .iter()
+        .chain(target_length_b.iter())
+        .filter(|(_, t_name, _)| t_names.insert(t_name.as_str()))
+        .map(|(_, t_name, t_len)| (t_name.clone(), *t_len))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ctgmap::CtgMapRec;
+
+    fn test_record(t_name: &str, ts: u32, te: u32, q_name: &str) -> CtgMapRec {
+        CtgMapRec {
+            t_name: t_name.to_string(),
+            ts,
+            te,
+            q_name: q_name.to_string(),
+            qs: 0,
+            qe: te - ts,
+            ctg_len: 1000,
+            orientation: 0,
+            ctg_orientation: 0,
+            t_dup: false,
+            t_ovlp: false,
+            q_dup: false,
+            q_ovlp: false,
+            identity: None,
+        }
+    }
+
+    #[test]
+    fn test_jaccard() {
+        assert_eq!(
+            merge_intervals(&[(50, 150), (0, 100), (150, 200), (300, 300), (400, 500)]),
+            vec![(0, 200), (400, 500)]
+        );
+        assert_eq!(compute_jaccard(&[(0, 100)], &[(0, 100)]), 1.0);
+        assert_eq!(compute_jaccard(&[(0, 100)], &[(50, 150)]), 50.0 / 150.0);
+        // the overlapping intervals of the same set are counted once
+        assert_eq!(
+            compute_jaccard(&[(0, 100), (50, 100), (200, 300)], &[(50, 250)]),
+            100.0 / 300.0
+        );
+        assert_eq!(compute_jaccard(&[(0, 100)], &[(100, 200)]), 0.0);
+        assert_eq!(compute_jaccard(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn test_uncovered_intervals() {
+        assert_eq!(
+            get_uncovered_intervals(&[(100, 200), (150, 300), (500, 600)], 1000),
+            vec![(0, 100), (300, 500), (600, 1000)]
+        );
+        assert_eq!(get_uncovered_intervals(&[(0, 1000)], 1000), vec![]);
+        // the intervals past the end of the sequence are ignored
+        assert_eq!(
+            get_uncovered_intervals(&[(0, 100), (1200, 1300)], 1000),
+            vec![(100, 1000)]
+        );
+        assert_eq!(get_uncovered_intervals(&[], 10), vec![(0, 10)]);
+    }
+
+    #[test]
+    fn test_n50() {
+        assert_eq!(get_n50(&[100, 200, 300, 400]), 300);
+        assert_eq!(get_n50(&[1000, 10, 10]), 1000);
+        assert_eq!(get_n50(&[]), 0);
+    }
+
+    #[test]
+    fn test_target_alignment_stats() {
+        let mut dup_record = test_record("chr1", 800, 900, "ctg3");
+        dup_record.q_dup = true;
+        let ctgmap_set = CtgMapSet {
+            records: vec![
+                test_record("chr1", 0, 100, "ctg1"),
+                test_record("chr1", 50, 200, "ctg1"),
+                test_record("chr1", 500, 600, "ctg2"),
+                dup_record,
+                test_record("chr2", 0, 50, "ctg2"),
+            ],
+            target_length: vec![
+                (0, "chr1".to_string(), 1000),
+                (1, "chr2".to_string(), 100),
+                (2, "chr3".to_string(), 100),
+            ],
+            query_length: vec![(0, "ctg1".to_string(), 300), (1, "ctg2".to_string(), 500)],
+        };
+        let stats = get_target_alignment_stats(&ctgmap_set);
+        assert_eq!(
+            stats["chr1"],
+            TargetAlignmentStats {
+                n50: 500,
+                coverage_breadth: 0.3,
+                number_contigs: 2,
+                number_gaps: 1,
+            }
+        );
+        assert_eq!(stats["chr2"].coverage_breadth, 0.5);
+        assert_eq!(stats["chr2"].number_gaps, 0);
+        assert!(!stats.contains_key("chr3"));
+
+        let set_b = CtgMapSet {
+            records: vec![],
+            target_length: vec![(1, "chr4".to_string(), 10), (0, "chr2".to_string(), 100)],
+            query_length: vec![],
+        };
+        assert_eq!(
+            get_union_targets(&ctgmap_set, &set_b),
+            vec![
+                ("chr1".to_string(), 1000),
+                ("chr2".to_string(), 100),
+                ("chr3".to_string(), 100),
+                ("chr4".to_string(), 10),
+            ]
+        );
+    }
+}
@@ -0,0 +1,627 @@
+use crate::error::PgrError;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CtgMapRec {
+    pub t_name: String,
+    pub ts: u32,
+    pub te: u32,
+    pub q_name: String,
+    pub qs: u32,
+    pub qe: u32,
+    pub ctg_len: u32,
+    pub orientation: u32,
+    pub ctg_orientation: u32,
+    pub t_dup: bool,
+    pub t_ovlp: bool,
+    pub q_dup: bool,
+    pub q_ovlp: bool,
+    /// the fraction of the matching bases of the alignment block, if the producer of the ctgmap.json has it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CtgMapSet {
+    pub records: Vec<CtgMapRec>,
+    pub target_length: Vec<(u32, String, u32)>,
+    pub query_length: Vec<(u32, String, u32)>,
+}
+
+/// read a ctgmap.json file generated by `pgr-alnmap`
+pub fn read_ctgmap_json<P: AsRef<Path>>(path: P) -> Result<CtgMapSet, PgrError> {
+    let path = path.as_ref();
+    // let serde drive the reading so we don't keep a copy of the whole file in memory
+    let ctgmap_json_file = BufReader::new(File::open(path).map_err(PgrError::io_context(
+        format!("can't open the ctgmap.json file {}", path.display()),
+    ))?);
+    let ctgmap_set = serde_json::from_reader(ctgmap_json_file)?;
+    Ok(ctgmap_set)
+}
+
+/// the fields of a record in a ctgmap.json file, `identity` is optional
+const RECORD_STRING_FIELDS: [&str; 2] = ["t_name", "q_name"];
+const RECORD_INTEGER_FIELDS: [&str; 7] = [
+    "ts",
+    "te",
+    "qs",
+    "qe",
+    "ctg_len",
+    "orientation",
+    "ctg_orientation",
+];
+const RECORD_BOOL_FIELDS: [&str; 4] = ["t_dup", "t_ovlp", "q_dup", "q_ovlp"];
+
+fn is_u32(v: &Value) -> bool {
+    v.as_u64().is_some_and(|v| v <= u32::MAX as u64)
+}
+
+/// check the layout of a ctgmap.json file against the fields of a `CtgMapSet`, so all the missing fields
+/// and the values of wrong types are reported instead of the first one serde stops at; return all the errors found
+pub fn validate_ctgmap_json_schema(json: &str) -> Result<(), Vec<String>> {
+    let value = serde_json::from_str::<Value>(json).map_err(|e| vec![e.to_string()])?;
+    validate_ctgmap_json_value(&value)
+}
+
+/// `validate_ctgmap_json_schema` for a ctgmap.json file already parsed to a JSON value
+pub fn validate_ctgmap_json_value(value: &Value) -> Result<(), Vec<String>> {
+    let top = value
+        .as_object()
+        .ok_or_else(|| vec!["the top level should be an object".to_string()])?;
+    let mut errors = Vec::<String>::new();
+    match top.get("records") {
+        Some(Value::Array(records)) => records.iter().enumerate().for_each(|(idx, r)| {
+            let r = match r.as_object() {
+                Some(r) => r,
+                None => {
+                    errors.push(format!("records[{}] should be an object", idx));
+                    return;
+                }
+            };
+            let mut check_field =
+                |field: &str, check: fn(&Value) -> bool, type_name: &str| match r.get(field) {
+                    Some(v) if check(v) => {}
+                    Some(v) => errors.push(format!(
+                        "records[{}].{} should be {}, not {}",
+                        idx, field, type_name, v
+                    )),
+                    None => errors.push(format!("records[{}] has no \"{}\"", idx, field)),
+                };
+            RECORD_STRING_FIELDS
+                .iter()
+                .for_each(|f| check_field(f, Value::is_string, "a string"));
+            RECORD_INTEGER_FIELDS
+                .iter()
+                .for_each(|f| check_field(f, is_u32, "a non-negative integer"));
+            RECORD_BOOL_FIELDS
+                .iter()
+                .for_each(|f| check_field(f, Value::is_boolean, "a boolean"));
+            if let Some(v) = r.get("identity").filter(|v| !v.is_null() && !v.is_number()) {
+                errors.push(format!(
+                    "records[{}].identity should be a number, not {}",
+                    idx, v
+                ));
+            };
+        }),
+        Some(_) => errors.push("\"records\" should be an array".to_string()),
+        None => errors.push("no \"records\"".to_string()),
+    };
+    ["target_length", "query_length"].iter().for_each(|key| {
+        match top.get(*key) {
+            Some(Value::Array(lengths)) => lengths.iter().enumerate().for_each(|(idx, v)| {
+                let valid = v.as_array().is_some_and(|v| {
+                    v.len() == 3 && is_u32(&v[0]) && v[1].is_string() && is_u32(&v[2])
+                });
+                if !valid {
+                    errors.push(format!(
+                        "{}[{}] should be [id, name, length] with a non-negative id and length, not {}",
+                        key, idx, v
+                    ));
+                };
+            }),
+            Some(_) => errors.push(format!("\"{}\" should be an array", key)),
+            None => errors.push(format!("no \"{}\"", key)),
+        };
+    });
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// read a ctgmap.json file like `read_ctgmap_json`, if the file can not be deserialized to a `CtgMapSet`,
+/// it is read again as a JSON value to report all the errors found by `validate_ctgmap_json_value`
+pub fn read_validated_ctgmap_json<P: AsRef<Path>>(path: P) -> Result<CtgMapSet, PgrError> {
+    let path = path.as_ref();
+    let parse_error = match read_ctgmap_json(path) {
+        Err(PgrError::JsonParse(e)) => e,
+        result => return result,
+    };
+    let ctgmap_json_file = BufReader::new(File::open(path).map_err(PgrError::io_context(
+        format!("can't open the ctgmap.json file {}", path.display()),
+    ))?);
+    // a file that is not even valid JSON is reported with the syntax error
+    let value = match serde_json::from_reader::<_, Value>(ctgmap_json_file) {
+        Ok(value) => value,
+        Err(_) => return Err(PgrError::JsonParse(parse_error)),
+    };
+    validate_ctgmap_json_value(&value).map_err(|errors| {
+        PgrError::InvalidCtgmap(format!(
+            "{} errors in {}:\n{}",
+            errors.len(),
+            path.display(),
+            errors.join("\n")
+        ))
+    })?;
+    Err(PgrError::JsonParse(parse_error))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationErrorKind {
+    TargetStartAfterEnd,
+    QueryStartAfterEnd,
+    InvalidOrientation,
+    UnknownTarget,
+    UnknownQuery,
+    TargetEndExceedsLength,
+    QueryEndExceedsLength,
+    ContigLengthMismatch,
+}
+
+#[derive(Clone, Debug)]
+pub struct ValidationError {
+    /// the index of the record in `CtgMapSet::records`
+    pub record_index: usize,
+    pub kind: ValidationErrorKind,
+    pub description: String,
+}
+
+/// check the invariants of the records in a ctgmap set, return all violations found
+pub fn validate_ctgmap_set(set: &CtgMapSet) -> Vec<ValidationError> {
+    let target_length = set
+        .target_length
+        .iter()
+        .map(|(_, t_name, t_len)| (t_name.as_str(), *t_len))
+        .collect::<FxHashMap<_, _>>();
+    let query_length = set
+        .query_length
+        .iter()
+        .map(|(_, q_name, q_len)| (q_name.as_str(), *q_len))
+        .collect::<FxHashMap<_, _>>();
+
+    let mut errors = Vec::<ValidationError>::new();
+    set.records.iter().enumerate().for_each(|(idx, r)| {
+        let mut add_error = |kind, description: String| {
+            errors.push(ValidationError {
+                record_index: idx,
+                kind,
+                description,
+            })
+        };
+        if r.ts > r.te {
+            add_error(
+                ValidationErrorKind::TargetStartAfterEnd,
+                format!("ts ({}) > te ({}) on {}", r.ts, r.te, r.t_name),
+            );
+        };
+        if r.orientation == 0 && r.qs > r.qe {
+            add_error(
+                ValidationErrorKind::QueryStartAfterEnd,
+                format!(
+                    "qs ({}) > qe ({}) on {} with orientation 0",
+                    r.qs, r.qe, r.q_name
+                ),
+            );
+        };
+        if r.orientation > 1 || r.ctg_orientation > 1 {
+            add_error(
+                ValidationErrorKind::InvalidOrientation,
+                format!(
+                    "orientation ({}) and ctg_orientation ({}) should be 0 or 1",
+                    r.orientation, r.ctg_orientation
+                ),
+            );
+        };
+        match target_length.get(r.t_name.as_str()) {
+            Some(&t_len) => {
+                if r.ts.max(r.te) > t_len {
+                    add_error(
+                        ValidationErrorKind::TargetEndExceedsLength,
+                        format!(
+                            "{}:{}-{} exceeds the target length {}",
+                            r.t_name, r.ts, r.te, t_len
+                        ),
+                    );
+                };
+            }
+            None => add_error(
+                ValidationErrorKind::UnknownTarget,
+                format!("{} is not in target_length", r.t_name),
+            ),
+        };
+        let q_len = match query_length.get(r.q_name.as_str()) {
+            Some(&q_len) => {
+                if r.ctg_len != q_len {
+                    add_error(
+                        ValidationErrorKind::ContigLengthMismatch,
+                        format!(
+                            "ctg_len ({}) differs from the query length {} of {}",
+                            r.ctg_len, q_len, r.q_name
+                        ),
+                    );
+                };
+                q_len
+            }
+            None => {
+                add_error(
+                    ValidationErrorKind::UnknownQuery,
+                    format!("{} is not in query_length", r.q_name),
+                );
+                r.ctg_len
+            }
+        };
+        if r.qs.max(r.qe) > q_len {
+            add_error(
+                ValidationErrorKind::QueryEndExceedsLength,
+                format!(
+                    "{}:{}-{} exceeds the query length {}",
+                    r.q_name, r.qs, r.qe, q_len
+                ),
+            );
+        };
+    });
+    errors
+}
+
+/// merge the ctgmap sets in the order given, the entries of `target_length` and `query_length` are
+/// deduplicated by name with the length of the first one kept and renumbered in the order they appear,
+/// a record with the same (q_name, t_name, qs, qe) as an earlier one is dropped unless `prefer_longer`
+/// is set and its target span is longer, then it replaces the earlier one;
+/// return the merged set and the number of the dropped records
+pub fn merge_ctgmap_sets(sets: Vec<CtgMapSet>, prefer_longer: bool) -> (CtgMapSet, usize) {
+    let mut target_length = Vec::<(u32, String, u32)>::new();
+    let mut query_length = Vec::<(u32, String, u32)>::new();
+    let mut target_ids = FxHashSet::<String>::default();
+    let mut query_ids = FxHashSet::<String>::default();
+    let mut records = Vec::<CtgMapRec>::new();
+    let mut record_index = FxHashMap::<(String, String, u32, u32), usize>::default();
+    let mut dropped = 0_usize;
+    sets.into_iter().for_each(|set| {
+        set.target_length
+            .into_iter()
+            .for_each(|(_, t_name, t_len)| {
+                if target_ids.insert(t_name.clone()) {
+                    target_length.push((target_length.len() as u32, t_name, t_len));
+                };
+            });
+        set.query_length.into_iter().for_each(|(_, q_name, q_len)| {
+            if query_ids.insert(q_name.clone()) {
+                query_length.push((query_length.len() as u32, q_name, q_len));
+            };
+        });
+        set.records.into_iter().for_each(|r| {
+            let key = (r.q_name.clone(), r.t_name.clone(), r.qs, r.qe);
+            match record_index.get(&key) {
+                Some(&idx) => {
+                    dropped += 1;
+                    let span = |r: &CtgMapRec| r.te.saturating_sub(r.ts);
+                    if prefer_longer && span(&r) > span(&records[idx]) {
+                        records[idx] = r;
+                    };
+                }
+                None => {
+                    record_index.insert(key, records.len());
+                    records.push(r);
+                }
+            }
+        });
+    });
+    (
+        CtgMapSet {
+            records,
+            target_length,
+            query_length,
+        },
+        dropped,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_set(record: CtgMapRec) -> CtgMapSet {
+        CtgMapSet {
+            records: vec![record],
+            target_length: vec![(0, "chr1".to_string(), 1000)],
+            query_length: vec![(0, "ctg1".to_string(), 500)],
+        }
+    }
+
+    fn test_record() -> CtgMapRec {
+        CtgMapRec {
+            t_name: "chr1".to_string(),
+            ts: 100,
+            te: 200,
+            q_name: "ctg1".to_string(),
+            qs: 10,
+            qe: 110,
+            ctg_len: 500,
+            orientation: 0,
+            ctg_orientation: 0,
+            t_dup: false,
+            t_ovlp: false,
+            q_dup: false,
+            q_ovlp: false,
+            identity: None,
+        }
+    }
+
+    fn error_kinds(record: CtgMapRec) -> Vec<ValidationErrorKind> {
+        validate_ctgmap_set(&test_set(record))
+            .into_iter()
+            .map(|e| {
+                assert_eq!(e.record_index, 0);
+                e.kind
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_record() {
+        assert!(error_kinds(test_record()).is_empty());
+        // qs > qe is allowed for the reverse orientation
+        let record = CtgMapRec {
+            qs: 110,
+            qe: 10,
+            orientation: 1,
+            ..test_record()
+        };
+        assert!(error_kinds(record).is_empty());
+    }
+
+    #[test]
+    fn test_target_start_after_end() {
+        let record = CtgMapRec {
+            ts: 300,
+            ..test_record()
+        };
+        assert_eq!(
+            error_kinds(record),
+            vec![ValidationErrorKind::TargetStartAfterEnd]
+        );
+    }
+
+    #[test]
+    fn test_query_start_after_end() {
+        let record = CtgMapRec {
+            qs: 200,
+            ..test_record()
+        };
+        assert_eq!(
+            error_kinds(record),
+            vec![ValidationErrorKind::QueryStartAfterEnd]
+        );
+    }
+
+    #[test]
+    fn test_invalid_orientation() {
+        let record = CtgMapRec {
+            ctg_orientation: 2,
+            ..test_record()
+        };
+        assert_eq!(
+            error_kinds(record),
+            vec![ValidationErrorKind::InvalidOrientation]
+        );
+    }
+
+    #[test]
+    fn test_unknown_names() {
+        let record = CtgMapRec {
+            t_name: "chr2".to_string(),
+            q_name: "ctg2".to_string(),
+            ..test_record()
+        };
+        assert_eq!(
+            error_kinds(record),
+            vec![
+                ValidationErrorKind::UnknownTarget,
+                ValidationErrorKind::UnknownQuery
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exceeds_length() {
+        let record = CtgMapRec {
+            te: 1001,
+            ..test_record()
+        };
+        assert_eq!(
+            error_kinds(record),
+            vec![ValidationErrorKind::TargetEndExceedsLength]
+        );
+        let record = CtgMapRec {
+            qe: 501,
+            ..test_record()
+        };
+        assert_eq!(
+            error_kinds(record),
+            vec![ValidationErrorKind::QueryEndExceedsLength]
+        );
+    }
+
+    #[test]
+    fn test_contig_length_mismatch() {
+        let record = CtgMapRec {
+            ctg_len: 400,
+            ..test_record()
+        };
+        assert_eq!(
+            error_kinds(record),
+            vec![ValidationErrorKind::ContigLengthMismatch]
+        );
+    }
+
+    #[test]
+    fn test_merge_ctgmap_sets() {
+        let set1 = CtgMapSet {
+            records: vec![
+                test_record(),
+                CtgMapRec {
+                    ts: 300,
+                    te: 400,
+                    qs: 200,
+                    qe: 300,
+                    ..test_record()
+                },
+            ],
+            target_length: vec![(0, "chr1".to_string(), 1000)],
+            query_length: vec![(0, "ctg1".to_string(), 500)],
+        };
+        let set2 = CtgMapSet {
+            records: vec![
+                // the same block as the first record of set1 with a longer target span
+                CtgMapRec {
+                    te: 250,
+                    ..test_record()
+                },
+                CtgMapRec {
+                    t_name: "chr2".to_string(),
+                    q_name: "ctg2".to_string(),
+                    ..test_record()
+                },
+            ],
+            target_length: vec![(0, "chr2".to_string(), 2000), (1, "chr1".to_string(), 1000)],
+            query_length: vec![(0, "ctg2".to_string(), 500), (1, "ctg1".to_string(), 500)],
+        };
+        let copy = |set: &CtgMapSet| CtgMapSet {
+            records: set.records.clone(),
+            target_length: set.target_length.clone(),
+            query_length: set.query_length.clone(),
+        };
+
+        let (merged, dropped) = merge_ctgmap_sets(vec![copy(&set1), copy(&set2)], false);
+        assert_eq!(dropped, 1);
+        assert_eq!(
+            merged.target_length,
+            vec![(0, "chr1".to_string(), 1000), (1, "chr2".to_string(), 2000)]
+        );
+        assert_eq!(
+            merged.query_length,
+            vec![(0, "ctg1".to_string(), 500), (1, "ctg2".to_string(), 500)]
+        );
+        let blocks = |set: &CtgMapSet| {
+            set.records
+                .iter()
+                .map(|r| (r.t_name.clone(), r.ts, r.te))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            blocks(&merged),
+            vec![
+                ("chr1".to_string(), 100, 200),
+                ("chr1".to_string(), 300, 400),
+                ("chr2".to_string(), 100, 200)
+            ]
+        );
+        assert!(validate_ctgmap_set(&merged).is_empty());
+
+        let (merged, dropped) = merge_ctgmap_sets(vec![set1, set2], true);
+        assert_eq!(dropped, 1);
+        assert_eq!(blocks(&merged)[0], ("chr1".to_string(), 100, 250));
+    }
+
+    #[test]
+    fn test_record_identity() {
+        // the records without the identity are read and written as before
+        let json = serde_json::to_string(&test_record()).unwrap();
+        assert!(!json.contains("identity"));
+        let record: CtgMapRec = serde_json::from_str(&json).unwrap();
+        assert_eq!(record.identity, None);
+        let json = json.replace("}", ",\"identity\":0.98}");
+        let record: CtgMapRec = serde_json::from_str(&json).unwrap();
+        assert_eq!(record.identity, Some(0.98));
+    }
+
+    #[test]
+    fn test_ctgmap_json_schema() {
+        let set = test_set(test_record());
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(validate_ctgmap_json_schema(&json), Ok(()));
+
+        let json = r#"{"records": [{"t_name": "chr1", "ts": -1, "te": 200, "qs": 1.5, "qe": 110,
+            "ctg_len": 500, "orientation": 0, "ctg_orientation": 0, "q_name": "ctg1",
+            "t_dup": false, "t_ovlp": false, "q_dup": false, "q_ovlp": 0}, 1],
+            "target_length": [[0, "chr1", 1000], [1, "chr2"]]}"#;
+        assert_eq!(
+            validate_ctgmap_json_schema(json),
+            Err(vec![
+                "records[0].ts should be a non-negative integer, not -1".to_string(),
+                "records[0].qs should be a non-negative integer, not 1.5".to_string(),
+                "records[0].q_ovlp should be a boolean, not 0".to_string(),
+                "records[1] should be an object".to_string(),
+                "target_length[1] should be [id, name, length] with a non-negative id and length, \
+                 not [1,\"chr2\"]"
+                    .to_string(),
+                "no \"query_length\"".to_string(),
+            ])
+        );
+        let json = r#"{"records": [{"t_name": "chr1"}], "target_length": [], "query_length": {}}"#;
+        let errors = validate_ctgmap_json_schema(json).unwrap_err();
+        assert_eq!(errors.len(), 13);
+        assert_eq!(errors[0], "records[0] has no \"q_name\"");
+        assert_eq!(errors[12], "\"query_length\" should be an array");
+        assert!(validate_ctgmap_json_schema("[]").is_err());
+        assert!(validate_ctgmap_json_schema("{").is_err());
+    }
+
+    #[test]
+    fn test_read_ctgmap_json_errors() {
+        let dir =
+            std::env::temp_dir().join(format!("pgr_read_ctgmap_json_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("missing.ctgmap.json");
+        assert!(matches!(read_ctgmap_json(&path), Err(PgrError::Io(_))));
+        let path = dir.join("bad.ctgmap.json");
+        std::fs::write(&path, "{\"records\": [").unwrap();
+        assert!(matches!(
+            read_ctgmap_json(&path),
+            Err(PgrError::JsonParse(_))
+        ));
+        assert!(matches!(
+            read_validated_ctgmap_json(&path),
+            Err(PgrError::JsonParse(_))
+        ));
+        let path = dir.join("schema.ctgmap.json");
+        std::fs::write(
+            &path,
+            r#"{"records": [], "target_length": [[0, "chr1", -1]], "query_length": 1}"#,
+        )
+        .unwrap();
+        match read_validated_ctgmap_json(&path) {
+            Err(PgrError::InvalidCtgmap(description)) => {
+                assert!(description.starts_with("2 errors in "));
+                assert!(description.ends_with("\"query_length\" should be an array"));
+            }
+            _ => panic!("expect an InvalidCtgmap error"),
+        };
+        std::fs::write(
+            &path,
+            r#"{"records": [], "target_length": [[0, "chr1", 1000]], "query_length": []}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            read_validated_ctgmap_json(&path).unwrap().target_length,
+            vec![(0, "chr1".to_string(), 1000)]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+use std::io;
+
+/// the errors of the tools, returned from `main` to exit with a readable message instead of a panic
+pub enum PgrError {
+    Io(io::Error),
+    JsonParse(serde_json::Error),
+    /// a line of a bed file that can not be parsed and the reason
+    BedParse {
+        line: String,
+        reason: String,
+    },
+    /// a line of a GFF3 file that can not be parsed and the reason
+    GffParse {
+        line: String,
+        reason: String,
+    },
+    InvalidCtgmap(String),
+    /// a command line argument out of its valid range or a combination of the arguments not supported
+    InvalidArgument(String),
+}
+
+impl PgrError {
+    /// wrap an I/O error with what was being done, e.g. "can't open the bed file a.bed"
+    pub fn io_context<C: fmt::Display>(context: C) -> impl FnOnce(io::Error) -> PgrError {
+        move |e| PgrError::Io(io::Error::new(e.kind(), format!("{}: {}", context, e)))
+    }
+}
+
+impl fmt::Display for PgrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgrError::Io(e) => write!(f, "I/O error, {}", e),
+            PgrError::JsonParse(e) => write!(f, "JSON error, {}", e),
+            PgrError::BedParse { line, reason } => {
+                write!(f, "fail to parse the bed line \"{}\", {}", line, reason)
+            }
+            PgrError::GffParse { line, reason } => {
+                write!(f, "fail to parse the GFF3 line \"{}\", {}", line, reason)
+            }
+            PgrError::InvalidCtgmap(description) => write!(f, "invalid ctgmap, {}", description),
+            PgrError::InvalidArgument(description) => {
+                write!(f, "invalid argument, {}", description)
+            }
+        }
+    }
+}
+
+// `main` prints the returned error with `Debug`, so it is the same as the `Display` message
+impl fmt::Debug for PgrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for PgrError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PgrError::Io(e) => Some(e),
+            PgrError::JsonParse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PgrError {
+    fn from(e: io::Error) -> Self {
+        PgrError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for PgrError {
+    fn from(e: serde_json::Error) -> Self {
+        PgrError::JsonParse(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let e = io::Error::new(io::ErrorKind::NotFound, "no such file");
+        let e = PgrError::io_context("can't open the bed file a.bed")(e);
+        assert_eq!(
+            e.to_string(),
+            "I/O error, can't open the bed file a.bed: no such file"
+        );
+        assert!(matches!(&e, PgrError::Io(e) if e.kind() == io::ErrorKind::NotFound));
+
+        let e = PgrError::from(serde_json::from_str::<Vec<u32>>("[1,").unwrap_err());
+        assert!(matches!(e, PgrError::JsonParse(_)));
+        assert!(e.to_string().starts_with("JSON error, "));
+
+        let e = PgrError::BedParse {
+            line: "chr1\tabc".to_string(),
+            reason: "invalid start".to_string(),
+        };
+        assert_eq!(
+            format!("{:?}", e),
+            "fail to parse the bed line \"chr1\tabc\", invalid start"
+        );
+        let e = PgrError::GffParse {
+            line: "chr1\t.\tgene".to_string(),
+            reason: "fewer than 9 columns".to_string(),
+        };
+        assert_eq!(
+            e.to_string(),
+            "fail to parse the GFF3 line \"chr1\t.\tgene\", fewer than 9 columns"
+        );
+        let e = PgrError::InvalidCtgmap("ts > te".to_string());
+        assert_eq!(e.to_string(), "invalid ctgmap, ts > te");
+        let e = PgrError::InvalidArgument("--track-height should be positive".to_string());
+        assert_eq!(
+            e.to_string(),
+            "invalid argument, --track-height should be positive"
+        );
+    }
+}
@@ -0,0 +1,9 @@
+pub mod chain;
+pub mod compare;
+pub mod ctgmap;
+pub mod error;
+pub mod paf;
+pub mod plot;
+pub mod reader;
+pub mod render;
+pub mod svcnd;
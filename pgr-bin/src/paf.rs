@@ -0,0 +1,359 @@
+use crate::ctgmap::{CtgMapRec, CtgMapSet};
+use crate::error::PgrError;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::fmt;
+use std::io::BufRead;
+
+/// a PAF record, the optional SAM-like tags are kept as they are
+#[derive(Clone, Debug, PartialEq)]
+pub struct PafRecord {
+    pub q_name: String,
+    pub q_len: u32,
+    pub q_start: u32,
+    pub q_end: u32,
+    pub strand: char,
+    pub t_name: String,
+    pub t_len: u32,
+    pub t_start: u32,
+    pub t_end: u32,
+    pub n_match: u32,
+    pub aln_len: u32,
+    pub mapq: u8,
+    pub tags: Vec<String>,
+}
+
+impl fmt::Display for PafRecord {
+    /// a PAF line without the trailing newline
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.q_name,
+            self.q_len,
+            self.q_start,
+            self.q_end,
+            self.strand,
+            self.t_name,
+            self.t_len,
+            self.t_start,
+            self.t_end,
+            self.n_match,
+            self.aln_len,
+            self.mapq
+        )?;
+        self.tags.iter().try_for_each(|tag| write!(f, "\t{}", tag))
+    }
+}
+
+/// parse the records of a PAF file, the lines start with '#' are skipped
+pub fn parse_paf<R: BufRead>(reader: R) -> Result<Vec<PafRecord>, PgrError> {
+    let mut records = Vec::<PafRecord>::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        };
+        let parse_error = |reason: &str| PgrError::BedParse {
+            line: line.clone(),
+            reason: reason.to_string(),
+        };
+        let fields = line.trim_end().split('\t').collect::<Vec<&str>>();
+        if fields.len() < 12 {
+            return Err(parse_error("fewer than 12 columns"));
+        };
+        let parse_u32 = |s: &str, column: &str| {
+            s.parse::<u32>()
+                .map_err(|_| parse_error(&format!("invalid {}", column)))
+        };
+        let strand = match fields[4] {
+            "+" => '+',
+            "-" => '-',
+            _ => return Err(parse_error("invalid strand")),
+        };
+        records.push(PafRecord {
+            q_name: fields[0].to_string(),
+            q_len: parse_u32(fields[1], "query length")?,
+            q_start: parse_u32(fields[2], "query start")?,
+            q_end: parse_u32(fields[3], "query end")?,
+            strand,
+            t_name: fields[5].to_string(),
+            t_len: parse_u32(fields[6], "target length")?,
+            t_start: parse_u32(fields[7], "target start")?,
+            t_end: parse_u32(fields[8], "target end")?,
+            n_match: parse_u32(fields[9], "number of matches")?,
+            aln_len: parse_u32(fields[10], "alignment length")?,
+            mapq: fields[11]
+                .parse::<u8>()
+                .map_err(|_| parse_error("invalid mapping quality"))?,
+            tags: fields[12..].iter().map(|s| s.to_string()).collect(),
+        });
+    }
+    Ok(records)
+}
+
+/// sweep the blocks on each target (`by_target`) or each query sorted by the start coordinates
+fn mark_dup_ovlp(records: &mut [CtgMapRec], by_target: bool) {
+    let interval = |r: &CtgMapRec| {
+        if by_target {
+            (r.ts, r.te)
+        } else {
+            (r.qs, r.qe)
+        }
+    };
+    let mut blocks = FxHashMap::<String, Vec<usize>>::default();
+    records.iter().enumerate().for_each(|(idx, r)| {
+        let name = if by_target { &r.t_name } else { &r.q_name };
+        blocks.entry(name.clone()).or_default().push(idx);
+    });
+    blocks.into_values().for_each(|mut indices| {
+        indices.sort_by_key(|&idx| interval(&records[idx]).0);
+        let mut c_end = 0_u32;
+        let mut first = true;
+        indices.into_iter().for_each(|idx| {
+            let (s, e) = interval(&records[idx]);
+            let r = &mut records[idx];
+            // the intervals are half-open, abutting blocks do not overlap
+            if first || s >= c_end {
+                first = false;
+                c_end = e;
+            } else if e <= c_end {
+                if by_target {
+                    r.t_dup = true;
+                } else {
+                    r.q_dup = true;
+                }
+            } else {
+                if by_target {
+                    r.t_ovlp = true;
+                } else {
+                    r.q_ovlp = true;
+                }
+                c_end = e;
+            }
+        });
+    });
+}
+
+/// convert the PAF records to a ctgmap set, the `ctg_orientation` of a query contig to a target is the
+/// orientation with more aligned bases, the dup / ovlp flags are set similar to `pgr-alnmap`:
+/// a block contained in the previous blocks is a duplicate and a block extending them is an overlap
+pub fn paf_to_ctgmap_set(paf_records: &[PafRecord]) -> CtgMapSet {
+    let mut target_length = Vec::<(u32, String, u32)>::new();
+    let mut query_length = Vec::<(u32, String, u32)>::new();
+    let mut target_ids = FxHashSet::<String>::default();
+    let mut query_ids = FxHashSet::<String>::default();
+    // (fwd_count, rev_count) of the aligned bases for each (query, target) pair
+    let mut orientation_count = FxHashMap::<(String, String), (u64, u64)>::default();
+    paf_records.iter().for_each(|r| {
+        if target_ids.insert(r.t_name.clone()) {
+            target_length.push((target_length.len() as u32, r.t_name.clone(), r.t_len));
+        };
+        if query_ids.insert(r.q_name.clone()) {
+            query_length.push((query_length.len() as u32, r.q_name.clone(), r.q_len));
+        };
+        let e = orientation_count
+            .entry((r.q_name.clone(), r.t_name.clone()))
+            .or_default();
+        let aln_len = r.q_end.saturating_sub(r.q_start) as u64;
+        if r.strand == '+' {
+            e.0 += aln_len;
+        } else {
+            e.1 += aln_len;
+        }
+    });
+
+    let mut records = paf_records
+        .iter()
+        .map(|r| {
+            let (fwd_count, rev_count) = orientation_count
+                .get(&(r.q_name.clone(), r.t_name.clone()))
+                .unwrap();
+            CtgMapRec {
+                t_name: r.t_name.clone(),
+                ts: r.t_start,
+                te: r.t_end,
+                q_name: r.q_name.clone(),
+                qs: r.q_start,
+                qe: r.q_end,
+                ctg_len: r.q_len,
+                orientation: if r.strand == '+' { 0 } else { 1 },
+                ctg_orientation: if fwd_count > rev_count { 0 } else { 1 },
+                t_dup: false,
+                t_ovlp: false,
+                q_dup: false,
+                q_ovlp: false,
+                identity: None,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    mark_dup_ovlp(&mut records, true);
+    mark_dup_ovlp(&mut records, false);
+
+    CtgMapSet {
+        records,
+        target_length,
+        query_length,
+    }
+}
+
+/// convert the records of a ctgmap set to PAF records, there is no base-level alignment in a ctgmap,
+/// so the number of matching bases and the block length are both the longer span of the block,
+/// MAPQ is 255 (missing) and the strand is from `orientation` (0: '+', 1: '-')
+pub fn ctgmap_set_to_paf(ctgmap_set: &CtgMapSet) -> Vec<PafRecord> {
+    let target_length = ctgmap_set
+        .target_length
+        .iter()
+        .map(|v| (v.1.as_str(), v.2))
+        .collect::<FxHashMap<_, _>>();
+    let query_length = ctgmap_set
+        .query_length
+        .iter()
+        .map(|v| (v.1.as_str(), v.2))
+        .collect::<FxHashMap<_, _>>();
+    ctgmap_set
+        .records
+        .iter()
+        .map(|r| {
+            let aln_len = r.te.saturating_sub(r.ts).max(r.qe.saturating_sub(r.qs));
+            PafRecord {
+                q_name: r.q_name.clone(),
+                q_len: *query_length.get(r.q_name.as_str()).unwrap_or(&r.ctg_len),
+                q_start: r.qs,
+                q_end: r.qe,
+                strand: if r.orientation == 0 { '+' } else { '-' },
+                t_name: r.t_name.clone(),
+                t_len: *target_length.get(r.t_name.as_str()).unwrap_or(&r.te),
+                t_start: r.ts,
+                t_end: r.te,
+                n_match: aln_len,
+                aln_len,
+                mapq: 255,
+                tags: vec![],
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ctgmap::validate_ctgmap_set;
+    use std::io;
+
+    const TEST_PAF: &str = "\
+ctg1\t1000\t0\t600\t+\tchr1\t5000\t100\t700\t590\t600\t60\ttp:A:P
+ctg1\t1000\t600\t1000\t-\tchr1\t5000\t800\t1200\t390\t400\t60
+ctg2\t500\t0\t300\t+\tchr1\t5000\t200\t500\t300\t300\t0
+ctg2\t500\t200\t500\t+\tchr2\t3000\t0\t300\t300\t300\t60
+";
+
+    #[test]
+    fn test_parse_paf() {
+        let records = parse_paf(io::Cursor::new(TEST_PAF)).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].tags, vec!["tp:A:P".to_string()]);
+        assert_eq!(records[1].strand, '-');
+        assert_eq!(records[2].mapq, 0);
+        assert!(matches!(
+            parse_paf(io::Cursor::new("ctg1\t1000\t0\n")),
+            Err(PgrError::BedParse { reason, .. }) if reason == "fewer than 12 columns"
+        ));
+        let line = "ctg1\t1000\t0\t600\t*\tchr1\t5000\t100\t700\t590\t600\t60";
+        match parse_paf(io::Cursor::new(line)) {
+            Err(PgrError::BedParse { line: l, reason }) => {
+                assert_eq!(l, line);
+                assert_eq!(reason, "invalid strand");
+            }
+            _ => panic!("expect a BedParse error"),
+        };
+        assert!(matches!(
+            parse_paf(io::Cursor::new(line.replace('*', "+").replace("5000", "x"))),
+            Err(PgrError::BedParse { reason, .. }) if reason == "invalid target length"
+        ));
+    }
+
+    #[test]
+    fn test_paf_to_ctgmap_set() {
+        let records = parse_paf(io::Cursor::new(TEST_PAF)).unwrap();
+        let ctgmap_set = paf_to_ctgmap_set(&records);
+        assert_eq!(
+            ctgmap_set.target_length,
+            vec![(0, "chr1".to_string(), 5000), (1, "chr2".to_string(), 3000)]
+        );
+        assert_eq!(
+            ctgmap_set.query_length,
+            vec![(0, "ctg1".to_string(), 1000), (1, "ctg2".to_string(), 500)]
+        );
+        let flags = ctgmap_set
+            .records
+            .iter()
+            .map(|r| {
+                (
+                    r.orientation,
+                    r.ctg_orientation,
+                    r.t_dup,
+                    r.t_ovlp,
+                    r.q_dup,
+                    r.q_ovlp,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            flags,
+            vec![
+                (0, 0, false, false, false, false),
+                (1, 0, false, false, false, false),
+                // chr1:200-500 is within chr1:100-700
+                (0, 0, true, false, false, false),
+                // ctg2:200-500 extends ctg2:0-300
+                (0, 0, false, false, false, true),
+            ]
+        );
+
+        // the JSON output can be read back and passes the validation
+        let json = serde_json::to_string(&ctgmap_set).unwrap();
+        let ctgmap_set: CtgMapSet = serde_json::from_str(&json).unwrap();
+        assert_eq!(ctgmap_set.records.len(), 4);
+        assert!(validate_ctgmap_set(&ctgmap_set).is_empty());
+    }
+
+    #[test]
+    fn test_ctgmap_set_to_paf() {
+        let records = parse_paf(io::Cursor::new(TEST_PAF)).unwrap();
+        let ctgmap_set = paf_to_ctgmap_set(&records);
+        let paf_records = ctgmap_set_to_paf(&ctgmap_set);
+        assert_eq!(
+            paf_records[1].to_string(),
+            "ctg1\t1000\t600\t1000\t-\tchr1\t5000\t800\t1200\t400\t400\t255"
+        );
+        assert_eq!(
+            records[0].to_string(),
+            TEST_PAF.lines().next().unwrap().to_string()
+        );
+
+        // round-trip through the PAF text and back to a ctgmap set
+        let paf = paf_records
+            .iter()
+            .map(|r| format!("{}\n", r))
+            .collect::<String>();
+        let paf_records2 = parse_paf(io::Cursor::new(paf)).unwrap();
+        assert_eq!(paf_records, paf_records2);
+        let ctgmap_set2 = paf_to_ctgmap_set(&paf_records2);
+        assert_eq!(ctgmap_set.target_length, ctgmap_set2.target_length);
+        assert_eq!(ctgmap_set.query_length, ctgmap_set2.query_length);
+        let fields = |set: &CtgMapSet| {
+            set.records
+                .iter()
+                .map(|r| {
+                    (
+                        (r.t_name.clone(), r.ts, r.te, r.q_name.clone(), r.qs, r.qe),
+                        (r.ctg_len, r.orientation, r.ctg_orientation),
+                        (r.t_dup, r.t_ovlp, r.q_dup, r.q_ovlp),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(fields(&ctgmap_set), fields(&ctgmap_set2));
+    }
+}
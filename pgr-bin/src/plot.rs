@@ -0,0 +1,208 @@
+use crate::ctgmap::CtgMapRec;
+
+/// the scale from bases to the panel coordinates, `offset` is the total length of the plotted targets with the padding,
+/// the second value is true if `total_target_bases` is smaller than `offset` and the panels extend past the panel width
+pub fn get_scaling_factor(
+    panel_width: f64,
+    total_target_bases: Option<f64>,
+    offset: f64,
+) -> (f64, bool) {
+    match total_target_bases {
+        Some(total_target_bases) if total_target_bases > 0.0 => (
+            panel_width * 0.8 / total_target_bases,
+            total_target_bases < offset,
+        ),
+        // offset stays at zero when there is no target to plot, avoid a NaN viewBox
+        _ if offset == 0.0 => (1.0, false),
+        _ => (panel_width * 0.8 / offset, false),
+    }
+}
+
+/// the intervals of the constant alignment depth on a target by sweeping the start / end coordinates
+/// of the records, the adjacent intervals with the same depth are merged and the intervals of depth 0 are skipped
+pub fn get_depth_intervals(intervals: &[(u32, u32)]) -> Vec<(u32, u32, u32)> {
+    let mut events = intervals
+        .iter()
+        .filter(|(s, e)| s < e)
+        .flat_map(|&(s, e)| [(s, 1_i64), (e, -1_i64)])
+        .collect::<Vec<_>>();
+    events.sort();
+    let mut depth_intervals = Vec::<(u32, u32, u32)>::new();
+    let mut depth = 0_i64;
+    let mut c_pos = 0_u32;
+    events.into_iter().for_each(|(pos, delta)| {
+        if pos > c_pos && depth > 0 {
+            match depth_intervals.last_mut() {
+                Some(last) if last.1 == c_pos && last.2 == depth as u32 => last.1 = pos,
+                _ => depth_intervals.push((c_pos, pos, depth as u32)),
+            }
+        };
+        depth += delta;
+        c_pos = pos;
+    });
+    depth_intervals
+}
+
+/// parse a region string as "CHR:START-END", the commas in the positions are ignored
+pub fn parse_region(region: &str) -> Result<(String, u32, u32), String> {
+    let err_msg = || {
+        format!(
+            "the region \"{}\" should be in the form of CHR:START-END",
+            region
+        )
+    };
+    let (r_name, range) = region.rsplit_once(':').ok_or_else(err_msg)?;
+    let (bgn, end) = range.split_once('-').ok_or_else(err_msg)?;
+    let parse_pos = |s: &str| s.replace(',', "").parse::<u32>().map_err(|_| err_msg());
+    let (bgn, end) = (parse_pos(bgn)?, parse_pos(end)?);
+    if r_name.is_empty() || bgn >= end {
+        return Err(err_msg());
+    };
+    Ok((r_name.to_string(), bgn, end))
+}
+
+/// clip the record to `[bgn, end)` of the target (`by_target`) or the query coordinates,
+/// the coordinates on the other side are adjusted proportionally with the alignment orientation
+pub fn clip_record(record: &CtgMapRec, bgn: u32, end: u32, by_target: bool) -> Option<CtgMapRec> {
+    let (ts, te) = (record.ts, record.te);
+    let (qs, qe) = (record.qs.min(record.qe), record.qs.max(record.qe));
+    let ((s, e), (os, oe)) = if by_target {
+        ((ts, te), (qs, qe))
+    } else {
+        ((qs, qe), (ts, te))
+    };
+    if s >= end || e <= bgn {
+        return None;
+    };
+    let (cs, ce) = (s.max(bgn), e.min(end));
+    let span = (e - s).max(1) as f64;
+    let other_span = (oe - os) as f64;
+    let head = ((cs - s) as f64 / span * other_span).round() as u32;
+    let tail = ((e - ce) as f64 / span * other_span).round() as u32;
+    // the start of the target is aligned to the end of the query for the reverse orientation
+    let (head, tail) = if record.orientation == 0 {
+        (head, tail)
+    } else {
+        (tail, head)
+    };
+    // the rounding may cut one base more than the other side has
+    let head = head.min(oe - os);
+    let tail = tail.min(oe - os - head);
+    let (cos, coe) = (os + head, oe - tail);
+    let ((ts, te), (qs, qe)) = if by_target {
+        ((cs, ce), (cos, coe))
+    } else {
+        ((cos, coe), (cs, ce))
+    };
+    let (qs, qe) = if record.qs > record.qe {
+        (qe, qs)
+    } else {
+        (qs, qe)
+    };
+    Some(CtgMapRec {
+        ts,
+        te,
+        qs,
+        qe,
+        ..record.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_record(t_name: &str, ts: u32, te: u32, q_name: &str, qs: u32, qe: u32) -> CtgMapRec {
+        CtgMapRec {
+            t_name: t_name.to_string(),
+            ts,
+            te,
+            q_name: q_name.to_string(),
+            qs,
+            qe,
+            ctg_len: 1000,
+            orientation: 0,
+            ctg_orientation: 0,
+            t_dup: false,
+            t_ovlp: false,
+            q_dup: false,
+            q_ovlp: false,
+            identity: None,
+        }
+    }
+
+    #[test]
+    fn test_scaling_factor() {
+        assert_eq!(get_scaling_factor(1000.0, None, 4000.0), (0.2, false));
+        assert_eq!(
+            get_scaling_factor(1000.0, Some(8000.0), 4000.0),
+            (0.1, false)
+        );
+        assert_eq!(get_scaling_factor(1000.0, Some(0.0), 4000.0), (0.2, false));
+        assert_eq!(get_scaling_factor(1000.0, None, 0.0), (1.0, false));
+        // a shared scale too small for the targets of this plot
+        assert_eq!(
+            get_scaling_factor(1000.0, Some(2000.0), 4000.0),
+            (0.4, true)
+        );
+    }
+
+    #[test]
+    fn test_depth_intervals() {
+        assert_eq!(
+            get_depth_intervals(&[(0, 100), (50, 150), (150, 200), (300, 400), (300, 350)]),
+            vec![
+                (0, 50, 1),
+                (50, 100, 2),
+                (100, 200, 1),
+                (300, 350, 2),
+                (350, 400, 1)
+            ]
+        );
+        assert!(get_depth_intervals(&[(10, 10)]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_region() {
+        assert_eq!(
+            parse_region("chr1:1,000,000-5000000"),
+            Ok(("chr1".to_string(), 1_000_000, 5_000_000))
+        );
+        assert_eq!(
+            parse_region("HLA-A*01:01:100-200"),
+            Ok(("HLA-A*01:01".to_string(), 100, 200))
+        );
+        assert!(parse_region("chr1").is_err());
+        assert!(parse_region("chr1:200-100").is_err());
+        assert!(parse_region(":100-200").is_err());
+    }
+
+    #[test]
+    fn test_clip_record() {
+        let record = test_record("chr1", 1000, 2000, "ctg1", 100, 1100);
+        // overlapping the start of the region
+        let clipped = clip_record(&record, 1500, 3000, true).unwrap();
+        assert_eq!(
+            (clipped.ts, clipped.te, clipped.qs, clipped.qe),
+            (1500, 2000, 600, 1100)
+        );
+        // overlapping the end of the region with the reverse orientation
+        let record = CtgMapRec {
+            orientation: 1,
+            ..record
+        };
+        let clipped = clip_record(&record, 0, 1200, true).unwrap();
+        assert_eq!(
+            (clipped.ts, clipped.te, clipped.qs, clipped.qe),
+            (1000, 1200, 900, 1100)
+        );
+        // clipping by the query coordinates
+        let clipped = clip_record(&record, 0, 600, false).unwrap();
+        assert_eq!(
+            (clipped.ts, clipped.te, clipped.qs, clipped.qe),
+            (1500, 2000, 100, 600)
+        );
+        assert!(clip_record(&record, 2000, 3000, true).is_none());
+        assert!(clip_record(&record, 0, 1000, true).is_none());
+    }
+}
@@ -0,0 +1,71 @@
+use crate::error::PgrError;
+use flate2::bufread::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// wrap a reader with the gzip (including bgzip) or the zstd decoder by the magic bytes at its start,
+/// a reader without the magic bytes is returned as it is
+fn wrap_possibly_compressed<R: BufRead + 'static>(mut reader: R) -> io::Result<Box<dyn BufRead>> {
+    // the magic bytes are checked without consuming them from the reader
+    let head = reader.fill_buf()?;
+    if head.starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else if head.starts_with(&ZSTD_MAGIC) {
+        Ok(Box::new(BufReader::new(zstd::Decoder::with_buffer(
+            reader,
+        )?)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// open a plain text, gzip or zstd compressed file as a `BufRead`, the compression is detected by
+/// the magic bytes rather than the file extension
+pub fn open_possibly_compressed(path: &Path) -> Result<Box<dyn BufRead>, PgrError> {
+    let context = || PgrError::io_context(format!("can't open the file {}", path.display()));
+    let file = File::open(path).map_err(context())?;
+    wrap_possibly_compressed(BufReader::new(file)).map_err(context())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{Cursor, Write};
+
+    const BED: &str = "chr1\t100\t200\nchr2\t300\t400\n";
+
+    fn read_lines(reader: Box<dyn BufRead>) -> Vec<String> {
+        reader.lines().map(|line| line.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_wrap_possibly_compressed() {
+        let lines = vec!["chr1\t100\t200", "chr2\t300\t400"];
+
+        let reader = wrap_possibly_compressed(Cursor::new(BED.as_bytes().to_vec())).unwrap();
+        assert_eq!(read_lines(reader), lines);
+
+        // two gzip members as in a bgzip file
+        let mut gz = vec![];
+        for part in ["chr1\t100\t200\n", "chr2\t300\t400\n"] {
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            encoder.write_all(part.as_bytes()).unwrap();
+            gz.extend(encoder.finish().unwrap());
+        }
+        let reader = wrap_possibly_compressed(Cursor::new(gz)).unwrap();
+        assert_eq!(read_lines(reader), lines);
+
+        let zst = zstd::encode_all(BED.as_bytes(), 0).unwrap();
+        let reader = wrap_possibly_compressed(Cursor::new(zst)).unwrap();
+        assert_eq!(read_lines(reader), lines);
+
+        let reader = wrap_possibly_compressed(Cursor::new(vec![])).unwrap();
+        assert!(read_lines(reader).is_empty());
+    }
+}
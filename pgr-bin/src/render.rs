@@ -0,0 +1,4674 @@
+use crate::compare::get_uncovered_intervals;
+use crate::ctgmap::{CtgMapRec, CtgMapSet};
+use crate::error::PgrError;
+use crate::plot::{clip_record, get_scaling_factor};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+use std::borrow::{Borrow, Cow};
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+use svg::node::{element, Node};
+use svg::Document;
+
+pub type CytoRecord = (u32, u32, String, String);
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct CytoBands {
+    pub cytobands: FxHashMap<String, Vec<CytoRecord>>,
+}
+
+impl CytoBands {
+    /// parse a UCSC cytoBand.txt file with the "chrom, chromStart, chromEnd, name, gieStain" columns
+    pub fn from_ucsc_txt<R: BufRead>(reader: R) -> Result<CytoBands, io::Error> {
+        let mut cytobands = FxHashMap::<String, Vec<CytoRecord>>::default();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            };
+            let parse_error = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("fail to parse the cytoband record on \"{}\"", line),
+                )
+            };
+            let fields = line.trim_end().split('\t').collect::<Vec<&str>>();
+            if fields.len() < 5 {
+                return Err(parse_error());
+            };
+            let bgn = fields[1].parse::<u32>().map_err(|_| parse_error())?;
+            let end = fields[2].parse::<u32>().map_err(|_| parse_error())?;
+            cytobands.entry(fields[0].to_string()).or_default().push((
+                bgn,
+                end,
+                fields[3].to_string(),
+                fields[4].to_string(),
+            ));
+        }
+        Ok(CytoBands { cytobands })
+    }
+}
+
+/// the order of the query contigs on the query tracks, after the ones listed in --query-order
+#[derive(Clone, Copy, clap::ValueEnum, Default, Debug)]
+pub enum QuerySort {
+    /// by the target start of the longest alignment block of each contig
+    #[default]
+    Position,
+    /// by the contig length, the longest first
+    Length,
+    /// by the contig name
+    Name,
+    /// by the median target position of the alignment blocks of each contig, set by --minimize-crossings
+    #[value(skip)]
+    MedianPosition,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum, Default, Debug)]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub fn bg_color(&self) -> &'static str {
+        match self {
+            Theme::Light => "#fff",
+            Theme::Dark => "#1a1a1a",
+        }
+    }
+
+    /// the color for the reference tracks and the text labels
+    pub fn fg_color(&self) -> &'static str {
+        match self {
+            Theme::Light => "#000",
+            Theme::Dark => "#ccc",
+        }
+    }
+
+    /// the outline color of the alignment ribbons
+    pub fn stroke_color(&self) -> &'static str {
+        match self {
+            Theme::Light => "#000",
+            Theme::Dark => "#fff",
+        }
+    }
+
+    /// the color of a cytoband by its gieStain value, the gray levels are inverted in the dark theme
+    pub fn cytoband_color(&self, gie_stain: &str) -> &'static str {
+        match (gie_stain, self) {
+            ("acen", _) => "#FF0",
+            ("gvar", _) => "#88B",
+            ("stalk", _) => "#69C",
+            ("gpos25", _) => "#777",
+            ("gpos50", Theme::Light) => "#555",
+            ("gpos50", Theme::Dark) => "#999",
+            ("gpos75", Theme::Light) => "#333",
+            ("gpos75", Theme::Dark) => "#BBB",
+            // "gpos100" and the other "gpos" values
+            (s, Theme::Light) if s.starts_with("gpos") => "#000",
+            (s, Theme::Dark) if s.starts_with("gpos") => "#DDD",
+            // "gneg" and the unknown values
+            (_, Theme::Light) => "#AAA",
+            (_, Theme::Dark) => "#555",
+        }
+    }
+}
+
+/// the position of the chromosome names of the per-chromosome panels
+#[derive(Clone, Copy, clap::ValueEnum, Default, Debug, PartialEq)]
+pub enum LabelPosition {
+    /// in the left margin of the panels
+    #[default]
+    Left,
+    /// to the right of the panels, past the 80% of the panel width taken by the overview scale
+    Right,
+    /// centered over the panels
+    Inside,
+}
+
+/// the order of the targets in the overview and the per-chromosome panels
+#[derive(Clone, Copy, clap::ValueEnum, Default, Debug)]
+pub enum ChromosomeSort {
+    /// the digits in the names are compared as numbers, e.g. chr2 before chr10 and scaffold_2 before scaffold_10
+    #[default]
+    Natural,
+    /// by the target name
+    Lexicographic,
+    /// by the target length, the longest first
+    ByLengthDesc,
+    /// in the order of `target_length` in the ctgmap.json file
+    AsInput,
+}
+
+/// the transform of the target and the query contig lengths in the overview panel
+#[derive(Clone, Copy, clap::ValueEnum, Default, Debug, PartialEq)]
+pub enum LengthTransform {
+    #[default]
+    Linear,
+    Sqrt,
+    Log,
+}
+
+impl LengthTransform {
+    /// the transformed length, scaled to keep `max_len` (the longest target) unchanged so that the padding
+    /// between the targets is still comparable to the lengths in bases
+    pub fn apply(&self, len: u32, max_len: u32) -> f64 {
+        let f = |v: f64| match self {
+            LengthTransform::Linear => v,
+            LengthTransform::Sqrt => v.sqrt(),
+            LengthTransform::Log => v.ln_1p(),
+        };
+        if *self == LengthTransform::Linear || max_len == 0 {
+            return len as f64;
+        };
+        f(len as f64) * max_len as f64 / f(max_len as f64)
+    }
+
+    /// the factor from the positions in a sequence of `len` bases to the positions in its transformed length,
+    /// the positions within a sequence stay linear
+    pub fn position_factor(&self, len: u32, max_len: u32) -> f64 {
+        if *self == LengthTransform::Linear || len == 0 {
+            1.0
+        } else {
+            self.apply(len, max_len) / len as f64
+        }
+    }
+}
+
+/// the y positions of the tracks in a per-chromosome panel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackLayout {
+    pub ref_y: f64,
+    pub alt_tgt_y: f64,
+    pub query_y: f64,
+    pub alt_qry_y: f64,
+    pub trapezoid_top_y: f64,
+    pub trapezoid_bottom_y: f64,
+}
+
+impl Default for TrackLayout {
+    fn default() -> Self {
+        TrackLayout {
+            ref_y: 6.0,
+            alt_tgt_y: 14.0,
+            query_y: 95.0,
+            alt_qry_y: 105.0,
+            trapezoid_top_y: 14.0,
+            trapezoid_bottom_y: 88.0,
+        }
+    }
+}
+
+impl TrackLayout {
+    /// the alternative hits tracks are `track_height` under the reference and the query tracks,
+    /// the ribbons span `inter_track_gap` from the alternative hits of the reference down to the query track;
+    /// `TrackLayout::new(8.0, 74.0)` is the default layout
+    pub fn new(track_height: f64, inter_track_gap: f64) -> Self {
+        let ref_y = 6.0;
+        let alt_tgt_y = ref_y + track_height;
+        let trapezoid_top_y = alt_tgt_y;
+        let trapezoid_bottom_y = trapezoid_top_y + inter_track_gap;
+        // keep a small margin between the ribbons and the query track, half of the track is above `query_y`
+        let query_y = trapezoid_bottom_y + 0.5 * track_height + 3.0;
+        let alt_qry_y = query_y + track_height + 2.0;
+        TrackLayout {
+            ref_y,
+            alt_tgt_y,
+            query_y,
+            alt_qry_y,
+            trapezoid_top_y,
+            trapezoid_bottom_y,
+        }
+    }
+
+    /// the height of a per-chromosome panel without the additional assemblies
+    pub fn panel_height(&self) -> f64 {
+        self.alt_qry_y + 25.0
+    }
+}
+
+/// the opacity of the alignment ribbons and of the reference / query tracks
+#[derive(Clone, Copy, Debug)]
+pub struct Opacity {
+    pub ribbon: f64,
+    pub track: f64,
+}
+
+impl Default for Opacity {
+    fn default() -> Self {
+        Opacity {
+            ribbon: 0.7,
+            track: 0.7,
+        }
+    }
+}
+
+/// the SVG coordinates of an alignment ribbon, `ts`/`te` on the target track and `qs`/`qe` on the query track
+#[derive(Serialize, Clone, Debug)]
+pub struct RibbonLayout {
+    pub q_name: String,
+    pub ts: f64,
+    pub te: f64,
+    pub qs: f64,
+    pub qe: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TargetLayout {
+    pub t_name: String,
+    pub t_len: u32,
+    /// the offset (in bases, or in the transformed lengths with --length-transform) of the target in the overview panel
+    pub offset: f64,
+    pub overview_ribbons: Vec<RibbonLayout>,
+    /// the y offset of the per-chromosome panel, the ribbon coordinates are relative to the panel
+    pub chr_panel_y: Option<f64>,
+    /// it is different for each target with --auto-scale-chr
+    pub chr_scaling_factor: Option<f64>,
+    pub chr_ribbons: Vec<RibbonLayout>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlotLayout {
+    pub overview_scaling_factor: f64,
+    pub targets: Vec<TargetLayout>,
+}
+
+pub static CMAP: [&str; 97] = [
+    "#870098", "#00aaa5", "#3bff00", "#ec0000", "#00a2c3", "#00f400", "#ff1500", "#0092dd",
+    "#00dc00", "#ff8100", "#007ddd", "#00c700", "#ffb100", "#0038dd", "#00af00", "#fcd200",
+    "#0000d5", "#009a00", "#f1e700", "#0000b1", "#00a55d", "#d4f700", "#4300a2", "#00aa93",
+    "#a1ff00", "#dc0000", "#00aaab", "#1dff00", "#f40000", "#009fcb", "#00ef00", "#ff2d00",
+    "#008ddd", "#00d700", "#ff9900", "#0078dd", "#00c200", "#ffb900", "#0025dd", "#00aa00",
+    "#f9d700", "#0000c9", "#009b13", "#efed00", "#0300aa", "#00a773", "#ccf900", "#63009e",
+    "#00aa98", "#84ff00", "#e10000", "#00a7b3", "#00ff00", "#f90000", "#009bd7", "#00ea00",
+    "#ff4500", "#0088dd", "#00d200", "#ffa100", "#005ddd", "#00bc00", "#ffc100", "#0013dd",
+    "#00a400", "#f7dd00", "#0000c1", "#009f33", "#e8f000", "#1800a7", "#00aa88", "#c4fc00",
+    "#78009b", "#00aaa0", "#67ff00", "#e60000", "#00a4bb", "#00fa00", "#fe0000", "#0098dd",
+    "#00e200", "#ff5d00", "#0082dd", "#00cc00", "#ffa900", "#004bdd", "#00b400", "#ffc900",
+    "#0000dd", "#009f00", "#f4e200", "#0000b9", "#00a248", "#dcf400", "#2d00a4", "#00aa8d",
+    "#bcff00",
+];
+
+pub static OKABE_ITO_CMAP: [&str; 8] = [
+    "#e69f00", "#56b4e9", "#009e73", "#f0e442", "#0072b2", "#d55e00", "#cc79a7", "#999999",
+];
+
+pub static VIRIDIS_CMAP: [&str; 12] = [
+    "#440154", "#482173", "#433e85", "#38598c", "#2d708e", "#25858e", "#1e9b8a", "#2bb07f",
+    "#51c56a", "#85d54a", "#c2df23", "#fde725",
+];
+
+/// the 64-bit FNV-1a hash of the bytes of `s`, unlike `DefaultHasher` it doesn't change between the Rust versions
+/// and the platforms, so a contig gets the same color in every run
+pub fn calculate_hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// convert a "#rrggbb" color to the CIE L*a*b* color space (D65 white point)
+pub fn hex_to_lab(color: &str) -> (f64, f64, f64) {
+    let v = u32::from_str_radix(color.trim_start_matches('#'), 16).unwrap_or(0);
+    let to_linear = |c: u32| {
+        let c = c as f64 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (
+        to_linear((v >> 16) & 0xff),
+        to_linear((v >> 8) & 0xff),
+        to_linear(v & 0xff),
+    );
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f64| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    (
+        116.0 * f(y) - 16.0,
+        500.0 * (f(x) - f(y)),
+        200.0 * (f(y) - f(z)),
+    )
+}
+
+/// mix a "#rrggbb" color with the gray of its luminance, `saturation` is kept from the color,
+/// a color in another format is returned as it is
+pub fn desaturate_color(color: &str, saturation: f64) -> String {
+    let hex = color.trim_start_matches('#');
+    let v = match u32::from_str_radix(hex, 16) {
+        Ok(v) if hex.len() == 6 => v,
+        _ => return color.to_string(),
+    };
+    let (r, g, b) = (
+        ((v >> 16) & 0xff) as f64,
+        ((v >> 8) & 0xff) as f64,
+        (v & 0xff) as f64,
+    );
+    let gray = 0.299 * r + 0.587 * g + 0.114 * b;
+    let mix = |c: f64| (gray + (c - gray) * saturation).round().clamp(0.0, 255.0) as u32;
+    format!("#{:02x}{:02x}{:02x}", mix(r), mix(g), mix(b))
+}
+
+/// mix a "#rrggbb" color with white, `fraction` is the part of white in the mix,
+/// a color in another format is returned as it is
+pub fn lighten_color(color: &str, fraction: f64) -> String {
+    let hex = color.trim_start_matches('#');
+    let v = match u32::from_str_radix(hex, 16) {
+        Ok(v) if hex.len() == 6 => v,
+        _ => return color.to_string(),
+    };
+    let mix = |c: u32| {
+        (c as f64 + (255.0 - c as f64) * fraction)
+            .round()
+            .clamp(0.0, 255.0) as u32
+    };
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        mix((v >> 16) & 0xff),
+        mix((v >> 8) & 0xff),
+        mix(v & 0xff)
+    )
+}
+
+/// the identity at and under which the ribbons get the lightest shade, most alignment blocks of the assemblies
+/// are above it so the gradient is spent on the divergent ones
+pub const MIN_SHADED_IDENTITY: f32 = 0.9;
+
+/// the part of white mixed into the contig color of a ribbon of `identity`, from 0 at the identity 1
+/// to 0.7 at `MIN_SHADED_IDENTITY` and under
+pub fn get_identity_shade(identity: f32) -> f64 {
+    let t = (1.0 - identity.clamp(MIN_SHADED_IDENTITY, 1.0)) / (1.0 - MIN_SHADED_IDENTITY);
+    0.7 * t as f64
+}
+
+/// the fill of a ribbon, the contig color, desaturated for the reverse strand with --shade-reverse-strand,
+/// and lighter for the lower identity if the record has one
+pub fn get_ribbon_fill(color: &str, record: &CtgMapRec, shade_reverse_strand: bool) -> String {
+    let fill = if shade_reverse_strand && record.orientation == 1 {
+        desaturate_color(color, 0.3)
+    } else {
+        color.to_string()
+    };
+    match record.identity {
+        Some(identity) => lighten_color(&fill, get_identity_shade(identity)),
+        None => fill,
+    }
+}
+
+pub fn lab_distance(c0: &(f64, f64, f64), c1: &(f64, f64, f64)) -> f64 {
+    ((c0.0 - c1.0).powi(2) + (c0.1 - c1.1).powi(2) + (c0.2 - c1.2).powi(2)).sqrt()
+}
+
+/// assign the colors to the query contigs so the contigs next to each other get distinct colors
+pub struct ColorAssigner {
+    pub cmap: &'static [&'static str],
+    pub ctg_colors: FxHashMap<String, &'static str>,
+}
+
+impl ColorAssigner {
+    /// the number of the previously assigned contigs to compare with when picking a color
+    const NEIGHBOR_WINDOW: usize = 4;
+
+    /// `q_names` are the query contigs in the order they appear along the targets
+    pub fn new(q_names: &[String], cmap: &'static [&'static str]) -> Self {
+        let lab_colors = cmap.iter().map(|c| hex_to_lab(c)).collect::<Vec<_>>();
+        let mut ctg_colors = FxHashMap::<String, &'static str>::default();
+        let mut assigned = Vec::<usize>::new();
+        let mut used = vec![false; cmap.len()];
+        q_names.iter().for_each(|q_name| {
+            if ctg_colors.contains_key(q_name) || cmap.is_empty() {
+                return;
+            };
+            // start a new round once every color has been used
+            if used.iter().all(|&u| u) {
+                used.iter_mut().for_each(|u| *u = false);
+            };
+            let neighbors = &assigned[assigned.len().saturating_sub(Self::NEIGHBOR_WINDOW)..];
+            let mut best_idx = 0;
+            let mut best_distance = f64::MIN;
+            (0..cmap.len()).filter(|&idx| !used[idx]).for_each(|idx| {
+                let distance = neighbors
+                    .iter()
+                    .map(|&n_idx| lab_distance(&lab_colors[idx], &lab_colors[n_idx]))
+                    .fold(f64::MAX, f64::min);
+                if distance > best_distance {
+                    best_distance = distance;
+                    best_idx = idx;
+                };
+            });
+            used[best_idx] = true;
+            assigned.push(best_idx);
+            ctg_colors.insert(q_name.clone(), cmap[best_idx]);
+        });
+        ColorAssigner { cmap, ctg_colors }
+    }
+
+    pub fn get_color(&self, q_name: &String) -> &'static str {
+        match self.ctg_colors.get(q_name) {
+            Some(color) => color,
+            None => self.cmap[(calculate_hash(q_name) % self.cmap.len() as u64) as usize],
+        }
+    }
+}
+
+/// the key of --sort-chromosomes natural, the name is split into the runs of non-digits each followed by a run
+/// of digits compared as a number, e.g. ("scaffold_", 2) < ("scaffold_", 10), a missing number is 0 and a number
+/// overflowing u64 is u64::MAX
+pub fn natural_sort_key(s: &str) -> Vec<(String, u64)> {
+    let mut key = Vec::<(String, u64)>::new();
+    let mut rest = s;
+    while !rest.is_empty() {
+        let alpha_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (alpha, tail) = rest.split_at(alpha_len);
+        let digit_len = tail
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (digits, tail) = tail.split_at(digit_len);
+        let n = if digits.is_empty() {
+            0
+        } else {
+            digits.parse::<u64>().unwrap_or(u64::MAX)
+        };
+        key.push((alpha.to_string(), n));
+        rest = tail;
+    }
+    key
+}
+
+/// sort the (id, name, length) of the targets, the ties are broken by the ids
+pub fn sort_targets(target_length: &mut [(u32, String, u32)], chromosome_sort: ChromosomeSort) {
+    match chromosome_sort {
+        ChromosomeSort::Natural => {
+            target_length.sort_by_key(|v| (natural_sort_key(&v.1), v.0));
+        }
+        ChromosomeSort::Lexicographic => {
+            target_length.sort_by(|a, b| (&a.1, a.0).cmp(&(&b.1, b.0)));
+        }
+        ChromosomeSort::ByLengthDesc => {
+            target_length.sort_by_key(|v| (std::cmp::Reverse(v.2), v.0));
+        }
+        ChromosomeSort::AsInput => (),
+    }
+}
+
+/// assign each query contig to the target with the most aligned bases, the q_dup records are not counted
+pub fn assign_contigs_to_targets(records: &[CtgMapRec]) -> FxHashMap<String, String> {
+    let mut ctg_target_hit_len = FxHashMap::<String, FxHashMap<String, u32>>::default();
+    records.iter().for_each(|r| {
+        if r.q_dup {
+            return;
+        };
+        let e = ctg_target_hit_len.entry(r.q_name.clone()).or_default();
+        let e2 = e.entry(r.t_name.clone()).or_default();
+        *e2 += (r.qe as i32 - r.qs as i32).unsigned_abs();
+    });
+
+    let mut ctg2tgt = FxHashMap::<String, String>::default();
+    ctg_target_hit_len.into_iter().for_each(|(ctg, tgt_len)| {
+        let mut tgt_len = tgt_len.into_iter().collect::<Vec<_>>();
+        if !tgt_len.is_empty() {
+            tgt_len.sort_by(|a, b| b.1.cmp(&a.1));
+            let tgt = tgt_len[0].0.clone();
+            ctg2tgt.insert(ctg.clone(), tgt.clone());
+        };
+    });
+    ctg2tgt
+}
+
+/// the median of the target midpoints of the alignment blocks of each query contig
+pub fn get_median_target_positions<R: Borrow<CtgMapRec>>(records: &[R]) -> FxHashMap<String, u32> {
+    let mut midpoints = FxHashMap::<String, Vec<u32>>::default();
+    records.iter().for_each(|r| {
+        let r: &CtgMapRec = r.borrow();
+        midpoints
+            .entry(r.q_name.clone())
+            .or_default()
+            .push(r.ts + (r.te - r.ts) / 2);
+    });
+    midpoints
+        .into_iter()
+        .map(|(q_name, mut v)| {
+            v.sort();
+            (q_name, v[v.len() / 2])
+        })
+        .collect()
+}
+
+/// sort the best alignment blocks of the query contigs to lay out the query track,
+/// the contigs in `query_order` go first, the others by `query_sort`,
+/// `records` are all the alignment blocks on the target for `QuerySort::MedianPosition`
+pub fn sort_query_blocks<R: Borrow<CtgMapRec>>(
+    best_query_block: &mut [&CtgMapRec],
+    records: &[R],
+    query_order: &FxHashMap<String, usize>,
+    query_sort: QuerySort,
+    query_length: &FxHashMap<String, u32>,
+) {
+    let median_positions = match query_sort {
+        QuerySort::MedianPosition => get_median_target_positions(records),
+        _ => FxHashMap::default(),
+    };
+    let rank = |v: &CtgMapRec| *query_order.get(&v.q_name).unwrap_or(&usize::MAX);
+    let q_len = |v: &CtgMapRec| *query_length.get(&v.q_name).unwrap_or(&v.ctg_len);
+    let median = |v: &CtgMapRec| *median_positions.get(&v.q_name).unwrap_or(&v.ts);
+    best_query_block.sort_by(|&a, &b| {
+        rank(a).cmp(&rank(b)).then_with(|| match query_sort {
+            QuerySort::Position => a.ts.cmp(&b.ts),
+            QuerySort::Length => q_len(b).cmp(&q_len(a)).then(a.ts.cmp(&b.ts)),
+            QuerySort::Name => a.q_name.cmp(&b.q_name),
+            QuerySort::MedianPosition => median(a).cmp(&median(b)).then(a.ts.cmp(&b.ts)),
+        })
+    });
+}
+
+/// the (bgn, end, color) regions of a bed file by the sequence names
+pub type ColoredBedRegions = FxHashMap<String, Vec<(u32, u32, &'static str)>>;
+
+/// a gene of a --gene-gff file, the coordinates are 0-based half-open as the bed regions
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneFeature {
+    pub bgn: u32,
+    pub end: u32,
+    pub strand: char,
+    pub name: String,
+}
+
+/// the points of a gene arrow from `b` to `e` centered at `y`, pointing to the end of its strand,
+/// a gene without a strand is a plain box
+pub fn get_gene_arrow_points(b: f64, e: f64, y: f64, strand: char) -> String {
+    let (y0, y1) = (y - 1.5, y + 1.5);
+    let tip = (e - b).min(2.0);
+    match strand {
+        '+' => format!(
+            "{b:0.4},{y0:0.4} {:0.4},{y0:0.4} {e:0.4},{y:0.4} {:0.4},{y1:0.4} {b:0.4},{y1:0.4}",
+            e - tip,
+            e - tip
+        ),
+        '-' => format!(
+            "{b:0.4},{y:0.4} {:0.4},{y0:0.4} {e:0.4},{y0:0.4} {e:0.4},{y1:0.4} {:0.4},{y1:0.4}",
+            b + tip,
+            b + tip
+        ),
+        _ => format!("{b:0.4},{y0:0.4} {e:0.4},{y0:0.4} {e:0.4},{y1:0.4} {b:0.4},{y1:0.4}"),
+    }
+}
+
+pub fn format_bp(bp: u32) -> String {
+    if bp >= 1_000_000 {
+        format!("{} Mbp", bp / 1_000_000)
+    } else if bp >= 1_000 {
+        format!("{} kbp", bp / 1_000)
+    } else {
+        format!("{} bp", bp)
+    }
+}
+
+/// the length of an alignment block for the tooltips, with one decimal in kbp or Mbp
+pub fn format_span(bp: u32) -> String {
+    if bp >= 1_000_000 {
+        format!("{:0.1} Mbp", bp as f64 / 1_000_000.0)
+    } else if bp >= 1_000 {
+        format!("{:0.1} kbp", bp as f64 / 1_000.0)
+    } else {
+        format!("{} bp", bp)
+    }
+}
+
+pub fn get_scale_bar_group(
+    scale_bar_len: u32,
+    scaling_factor: f64,
+    x: f64,
+    y: f64,
+    font_size: f64,
+    theme: Theme,
+    font_family: &str,
+) -> element::Group {
+    let mut group = element::Group::new().set("class", "scale_bar");
+    let b = x;
+    let e = x + scale_bar_len as f64 * scaling_factor;
+    let (y0, y1) = (y - 2.0, y + 2.0);
+    let path_str = format!(
+        "M {b:0.4} {y0:0.4} L {b:0.4} {y1:0.4} M {b:0.4} {y:0.4} L {e:0.4} {y:0.4} M {e:0.4} {y0:0.4} L {e:0.4} {y1:0.4}"
+    );
+    let path = element::Path::new()
+        .set("stroke", theme.fg_color())
+        .set("stroke-width", 1)
+        .set("fill", "none")
+        .set("d", path_str);
+    group.append(path);
+    let text = element::Text::new(format_bp(scale_bar_len))
+        .set("x", b)
+        .set("y", y - 4.0)
+        .set("font-size", format!("{font_size}px"))
+        .set("font-family", font_family)
+        .set("fill", theme.fg_color());
+    group.append(text);
+    group
+}
+
+/// draw a tick mark every `tick_interval` bases above the reference track at `y`, labeled in Mbp
+pub fn get_tick_group(
+    t_len: u32,
+    tick_interval: f64,
+    scaling_factor: f64,
+    y: f64,
+    theme: Theme,
+    font_family: &str,
+) -> element::Group {
+    let mut group = element::Group::new().set("class", "ticks");
+    let mut path_str = String::new();
+    let mut i = 1_u32;
+    loop {
+        let pos = i as f64 * tick_interval;
+        if pos > t_len as f64 {
+            break;
+        };
+        let x = pos * scaling_factor;
+        let y0 = y - 5.0;
+        path_str.push_str(&format!("M {x:0.4} {y0:0.4} L {x:0.4} {y:0.4} "));
+        // round to 1 kbp so the labels do not show the floating point errors
+        let label = (pos / 1e3).round() / 1e3;
+        let text = element::Text::new(format!("{}", label))
+            .set("x", x)
+            .set("y", y0 - 1.0)
+            .set("font-size", "5px")
+            .set("font-family", font_family)
+            .set("text-anchor", "middle")
+            .set("fill", theme.fg_color());
+        group.append(text);
+        i += 1;
+    }
+    let path = element::Path::new()
+        .set("stroke", theme.fg_color())
+        .set("stroke-width", 0.5)
+        .set("fill", "none")
+        .set("d", path_str.trim_end().to_string());
+    group.append(path);
+    group
+}
+
+/// pick the smallest of 1, 5, 10 and 50 Mbp that keeps the ticks at least 40 pixels apart,
+/// `scaling_factor` (pixels per base) accounts for the panel width
+pub fn get_ruler_interval(t_len: u32, scaling_factor: f64) -> u32 {
+    let track_width = t_len as f64 * scaling_factor;
+    [1_000_000, 5_000_000, 10_000_000, 50_000_000]
+        .into_iter()
+        .find(|&interval| (t_len / interval) as f64 * 40.0 <= track_width)
+        .unwrap_or(50_000_000)
+}
+
+/// append the ruler ticks along the reference track of a chromosome, labeled in Mbp
+pub fn draw_ruler(
+    group: &mut element::Group,
+    t_len: u32,
+    scaling_factor: f64,
+    tick_interval_bp: u32,
+    theme: Theme,
+    font_family: &str,
+) {
+    group.append(get_tick_group(
+        t_len,
+        tick_interval_bp.max(1) as f64,
+        scaling_factor,
+        2.0,
+        theme,
+        font_family,
+    ));
+}
+
+/// split a target of `t_len` bases into `n_bins` bins (the last one may be shorter) and get the mean depth of the
+/// `intervals` over each bin as (bin_bgn, bin_end, depth)
+pub fn get_coverage_bins(
+    intervals: &[(u32, u32)],
+    t_len: u32,
+    n_bins: usize,
+) -> Vec<(u32, u32, f64)> {
+    if t_len == 0 || n_bins == 0 {
+        return vec![];
+    };
+    let bin_size = (t_len as u64).div_ceil(n_bins as u64).max(1);
+    let mut covered = vec![0_u64; t_len.div_ceil(bin_size as u32) as usize];
+    intervals.iter().for_each(|&(s, e)| {
+        let (s, e) = (s as u64, e.min(t_len) as u64);
+        if s >= e {
+            return;
+        };
+        (s / bin_size..=(e - 1) / bin_size).for_each(|idx| {
+            let bin_bgn = idx * bin_size;
+            covered[idx as usize] += e.min(bin_bgn + bin_size) - s.max(bin_bgn);
+        });
+    });
+    covered
+        .into_iter()
+        .enumerate()
+        .map(|(idx, covered)| {
+            let bgn = idx as u64 * bin_size;
+            let end = (bgn + bin_size).min(t_len as u64);
+            (bgn as u32, end as u32, covered as f64 / (end - bgn) as f64)
+        })
+        .collect()
+}
+
+/// draw a duplicated block with a dashed outline in the contig color and a lighter fill
+pub fn set_dup_style(path: element::Path, color: &str, stroke_width: f64) -> element::Path {
+    path.set("stroke", color)
+        .set("stroke-width", stroke_width)
+        .set(
+            "stroke-dasharray",
+            format!("{} {}", stroke_width * 3.0, stroke_width * 2.0),
+        )
+        .set("stroke-opacity", 0.9)
+        .set("fill-opacity", 0.3)
+}
+
+pub static DUP_TARGET_COLOR: &str = "#1E90FF";
+
+pub static DUP_QUERY_COLOR: &str = "#FFA500";
+
+pub static DOUBLE_DUP_COLOR: &str = "#999";
+
+/// with --highlight-duplications, the fill of a block duplicated on only one side
+pub fn get_dup_highlight_fill(record: &CtgMapRec) -> Option<&'static str> {
+    match (record.t_dup, record.q_dup) {
+        (true, false) => Some(DUP_TARGET_COLOR),
+        (false, true) => Some(DUP_QUERY_COLOR),
+        _ => None,
+    }
+}
+
+/// with --highlight-duplications, a block duplicated on both sides is a thin line from the middle of its
+/// target span at `y` to the middle of its query span at `y2` instead of a ribbon
+pub fn get_double_dup_line(ts: f64, te: f64, qs: f64, qe: f64, y: f64, y2: f64) -> element::Path {
+    let (t_mid, q_mid) = (0.5 * (ts + te), 0.5 * (qs + qe));
+    element::Path::new()
+        .set("class", "double_dup")
+        .set("stroke", DOUBLE_DUP_COLOR)
+        .set("stroke-width", 0.5)
+        .set("fill", "none")
+        .set("d", format!("M {t_mid:0.4} {y:0.4} L {q_mid:0.4} {y2:0.4}"))
+}
+
+/// with --highlight-query, outline the ribbons of the highlighted contig at the full opacity and dim the others
+pub fn set_highlight_query_style(
+    path: element::Path,
+    highlighted: bool,
+    theme: Theme,
+    stroke_width: f64,
+) -> element::Path {
+    if highlighted {
+        path.set("stroke", theme.stroke_color())
+            .set("stroke-width", stroke_width)
+            .set("stroke-opacity", 1.0)
+            .set("opacity", 1.0)
+    } else {
+        path.set("opacity", 0.2)
+    }
+}
+
+pub static INVERSION_COLOR: &str = "#FF8C00";
+
+/// the diagonal hatching for the inverted alignments, the tile is a tenth of the bounding box of the ribbon,
+/// so a thin inversion gets as many stripes as a wide one
+pub fn create_inversion_pattern() -> element::Definitions {
+    let background = element::Rectangle::new()
+        .set("x", 0)
+        .set("y", 0)
+        .set("width", 0.1)
+        .set("height", 1)
+        .set("fill", INVERSION_COLOR)
+        .set("fill-opacity", 0.3);
+    let stripe = element::Path::new()
+        .set("d", "M 0 1 L 0.05 0 L 0.1 0 L 0.05 1 Z")
+        .set("fill", INVERSION_COLOR);
+    let pattern = element::Pattern::new()
+        .set("id", "inversion_hatch")
+        .set("patternUnits", "objectBoundingBox")
+        .set("patternContentUnits", "objectBoundingBox")
+        .set("width", 0.1)
+        .set("height", 1)
+        .add(background)
+        .add(stripe);
+    element::Definitions::new().add(pattern)
+}
+
+pub static GAP_COLOR: &str = "#BBB";
+
+/// the diagonal hatching of the gap markers, the stripes are 4 units apart in the panel
+pub fn create_gap_pattern() -> element::Definitions {
+    let stripe = element::Path::new()
+        .set("d", "M 0 0 L 0 4")
+        .set("stroke", GAP_COLOR)
+        .set("stroke-width", 1.5);
+    let pattern = element::Pattern::new()
+        .set("id", "gap_hatch")
+        .set("patternUnits", "userSpaceOnUse")
+        .set("width", 4)
+        .set("height", 4)
+        .set("patternTransform", "rotate(45)")
+        .add(stripe);
+    element::Definitions::new().add(pattern)
+}
+
+/// draw the regions of [0, `t_len`) not in `covered` and at least `gap_min_length` bases long as the hatched
+/// rectangles around the reference track at `y`, with the pattern defined by `create_gap_pattern`
+pub fn draw_gap_markers(
+    group: &mut element::Group,
+    t_len: u32,
+    covered: &[(u32, u32)],
+    scaling_factor: f64,
+    y: f64,
+    gap_min_length: u32,
+) {
+    get_uncovered_intervals(covered, t_len)
+        .into_iter()
+        .filter(|(bgn, end)| end - bgn >= gap_min_length)
+        .for_each(|(bgn, end)| {
+            let b = bgn as f64 * scaling_factor;
+            let e = end as f64 * scaling_factor;
+            let mut rect = element::Rectangle::new()
+                .set("class", "gap")
+                .set("x", format!("{b:0.4}"))
+                .set("y", format!("{:0.4}", y - 10.0))
+                .set("width", format!("{:0.4}", e - b))
+                .set("height", 20)
+                .set("fill", "url(#gap_hatch)")
+                .set("stroke", GAP_COLOR)
+                .set("stroke-width", 0.25);
+            rect.append(element::Title::new(format!(
+                "no alignment {}-{}, {}",
+                bgn,
+                end,
+                format_span(end - bgn)
+            )));
+            group.append(rect);
+        });
+}
+
+/// draw the thin horizontal lines from 0 to `width` at each of `ys` to guide the eye along the tracks of a panel
+pub fn draw_grid_lines(group: &mut element::Group, width: f64, ys: &[f64], theme: Theme) {
+    ys.iter().for_each(|y| {
+        let path = element::Path::new()
+            .set("class", "grid")
+            .set("stroke", theme.fg_color())
+            .set("stroke-width", 0.5)
+            .set("stroke-opacity", 0.15)
+            .set("d", format!("M 0 {y:0.4} L {width:0.4} {y:0.4}"));
+        group.append(path);
+    });
+}
+
+/// draw an inverted alignment with the pattern defined by `create_inversion_pattern`
+pub fn set_inversion_style(path: element::Path) -> element::Path {
+    path.set("fill", "url(#inversion_hatch)")
+        .set("stroke", INVERSION_COLOR)
+}
+
+/// the orientation to draw a query contig in, all contigs are mirrored with --flip-query
+pub fn get_display_orientation(record: &CtgMapRec, flip_query: bool) -> u32 {
+    match (record.ctg_orientation, flip_query) {
+        (0, true) => 1,
+        (_, true) => 0,
+        (ctg_orientation, false) => ctg_orientation,
+    }
+}
+
+/// the lane of a query contig on the query track, from the longest alignment block of the contig to the target
+pub struct QueryLane {
+    /// the start of the lane on the query track
+    pub offset: f64,
+    pub q_len: u32,
+    /// the display orientation of the contig, see `get_display_orientation`
+    pub orientation: u32,
+}
+
+impl QueryLane {
+    /// the (qs, qe) of the ribbon end in the lane, mirrored with the lane and swapped for the twisted ribbons
+    pub fn get_ribbon_query_span(&self, record: &CtgMapRec) -> (u32, u32) {
+        let (qs, qe) = if self.orientation == 1 {
+            (
+                self.q_len.saturating_sub(record.qe),
+                self.q_len.saturating_sub(record.qs),
+            )
+        } else {
+            (record.qs, record.qe)
+        };
+        if record.orientation != self.orientation {
+            (qe, qs)
+        } else {
+            (qs, qe)
+        }
+    }
+}
+
+/// the outline of a cytoband from `b` to `e` drawn as a filled shape 8 units high centered at `y` for an ideogram,
+/// the centromere (acen) bands taper to the constriction at the p/q boundary and the bands at the two ends of the
+/// chromosome (`first`, `last`) are rounded as the telomere caps; None for the other bands drawn as plain strokes
+pub fn get_cytoband_outline(
+    b: f64,
+    e: f64,
+    y: f64,
+    c_name: &str,
+    gie_stain: &str,
+    first: bool,
+    last: bool,
+) -> Option<String> {
+    let (y0, y1) = (y - 4.0, y + 4.0);
+    if gie_stain == "acen" {
+        // the constriction is 2 units high
+        let (p0, p1) = (y - 1.0, y + 1.0);
+        let outline = if c_name.starts_with('p') {
+            format!("M {b:0.4} {y0:0.4} L {e:0.4} {p0:0.4} L {e:0.4} {p1:0.4} L {b:0.4} {y1:0.4} Z")
+        } else if c_name.starts_with('q') {
+            format!("M {b:0.4} {p0:0.4} L {e:0.4} {y0:0.4} L {e:0.4} {y1:0.4} L {b:0.4} {p1:0.4} Z")
+        } else {
+            // the constriction in the middle of a centromere band without the arm in its name
+            let m = 0.5 * (b + e);
+            format!(
+                "M {b:0.4} {y0:0.4} L {m:0.4} {p0:0.4} L {e:0.4} {y0:0.4} \
+                 L {e:0.4} {y1:0.4} L {m:0.4} {p1:0.4} L {b:0.4} {y1:0.4} Z"
+            )
+        };
+        return Some(outline);
+    };
+    if !first && !last {
+        return None;
+    };
+    let r = if first && last {
+        (0.5 * (e - b)).min(4.0)
+    } else {
+        (e - b).min(4.0)
+    };
+    let (bl, er) = (if first { b + r } else { b }, if last { e - r } else { e });
+    let right = if last {
+        format!("L {er:0.4} {y0:0.4} A {r:0.4} 4 0 0 1 {er:0.4} {y1:0.4}")
+    } else {
+        format!("L {er:0.4} {y0:0.4} L {er:0.4} {y1:0.4}")
+    };
+    let left = if first {
+        format!("L {bl:0.4} {y1:0.4} A {r:0.4} 4 0 0 1 {bl:0.4} {y0:0.4} Z")
+    } else {
+        format!("L {bl:0.4} {y1:0.4} Z")
+    };
+    Some(format!("M {bl:0.4} {y0:0.4} {right} {left}"))
+}
+
+/// the regions of a --ref-annotation-bed file, drawn in a lane above the reference track
+#[derive(Clone, Debug)]
+pub struct RefAnnotationTrack {
+    pub label: String,
+    pub regions: ColoredBedRegions,
+}
+
+/// the y offset of the `idx`-th reference annotation lane from the first one, the lanes are stacked upward
+pub fn get_ref_annotation_dy(idx: usize) -> f64 {
+    -7.0 * idx as f64
+}
+
+/// the options of a per-chromosome panel drawn by `get_chr_svg_group`, the default panel has only the reference
+/// track, the query track and the ribbons
+#[derive(Clone, Copy, Debug)]
+pub struct PanelOptions<'a> {
+    pub scaling_factor: f64,
+    pub theme: Theme,
+    pub font_family: &'a str,
+    pub opacity: Opacity,
+    pub track_layout: TrackLayout,
+    /// the contigs in it go first on the query track, see `sort_query_blocks`
+    pub query_order: Option<&'a FxHashMap<String, usize>>,
+    pub query_sort: QuerySort,
+    pub cytobands: Option<&'a CytoBands>,
+    pub ref_annotation_tracks: &'a [RefAnnotationTrack],
+    pub telomeres: Option<&'a FxHashMap<String, Vec<(u32, u32)>>>,
+    pub genes: Option<&'a FxHashMap<String, Vec<GeneFeature>>>,
+    /// the regions marked under the query track by the query contig names
+    pub query_annotations: Option<&'a FxHashMap<String, Vec<(u32, u32)>>>,
+    pub scale_bar_len: Option<u32>,
+    /// the interval (in bases) of the ruler ticks, chosen by `get_ruler_interval` if it is not given
+    pub tick_interval: Option<f64>,
+    pub ruler: bool,
+    /// the (start, end) of the target in the panel, the query track starts under the start
+    pub region: Option<(u32, u32)>,
+    pub show_dups: bool,
+    pub highlight_inversions: bool,
+    pub highlight_duplications: bool,
+    pub flip_query: bool,
+    pub inversions_only: bool,
+    pub shade_reverse_strand: bool,
+    pub highlight_query: Option<&'a str>,
+    pub coverage_bins: Option<usize>,
+    /// mark the regions of the target without the alignment records and at least this many bases long
+    pub gap_min_length: Option<u32>,
+    pub grid: bool,
+}
+
+impl Default for PanelOptions<'_> {
+    fn default() -> Self {
+        PanelOptions {
+            scaling_factor: 1.0,
+            theme: Theme::default(),
+            font_family: "monospace",
+            opacity: Opacity::default(),
+            track_layout: TrackLayout::default(),
+            query_order: None,
+            query_sort: QuerySort::default(),
+            cytobands: None,
+            ref_annotation_tracks: &[],
+            telomeres: None,
+            genes: None,
+            query_annotations: None,
+            scale_bar_len: None,
+            tick_interval: None,
+            ruler: false,
+            region: None,
+            show_dups: false,
+            highlight_inversions: false,
+            highlight_duplications: false,
+            flip_query: false,
+            inversions_only: false,
+            shade_reverse_strand: false,
+            highlight_query: None,
+            coverage_bins: None,
+            gap_min_length: None,
+            grid: false,
+        }
+    }
+}
+
+/// the records and the contig assignments of an assembly shared by its per-chromosome panels
+pub struct AssemblyRecords<'a, R> {
+    /// the records to each target from the query contigs assigned to the other targets
+    pub tgt_to_alt_qry_records: &'a FxHashMap<String, Vec<R>>,
+    /// the records of each query contig to the targets other than its assigned one
+    pub qry_to_alt_tgt_records: &'a FxHashMap<String, Vec<R>>,
+    pub ctg2tgt: &'a FxHashMap<String, String>,
+    pub query_length: &'a FxHashMap<String, u32>,
+    pub color_assigner: &'a ColorAssigner,
+}
+
+/// the blocks duplicated on both the target and the query are drawn as lines instead of ribbons
+pub fn is_double_dup(record: &CtgMapRec, opts: &PanelOptions) -> bool {
+    record.t_dup && record.q_dup && !opts.show_dups
+}
+
+/// whether the ribbon (or the line, see `is_double_dup`) of the record is drawn by the options,
+/// the same records are drawn in the overview and the per-chromosome panels
+pub fn is_ribbon_drawn(record: &CtgMapRec, opts: &PanelOptions) -> bool {
+    if is_double_dup(record, opts) && !opts.highlight_duplications {
+        return false;
+    };
+    !(opts.inversions_only && record.orientation == record.ctg_orientation)
+}
+
+/// the ribbon of the record with the fill and the highlight styles of the options, `stroke_width` is the width
+/// of the dup outline and half of the one of the highlighted query
+pub fn get_ribbon_path(
+    record: &CtgMapRec,
+    color: &str,
+    path_str: String,
+    opts: &PanelOptions,
+    stroke_width: f64,
+) -> element::Path {
+    let fill = get_ribbon_fill(color, record, opts.shade_reverse_strand);
+    let fill = get_dup_highlight_fill(record)
+        .filter(|_| opts.highlight_duplications)
+        .map(|v| v.to_string())
+        .unwrap_or(fill);
+    let mut path = element::Path::new()
+        .set("class", "ribbon")
+        .set("fill", fill)
+        .set("stroke", opts.theme.stroke_color())
+        .set("stroke-width", 0.25)
+        .set("opacity", opts.opacity.ribbon)
+        .set("stroke-opacity", 0.4)
+        .set("d", path_str);
+    if opts.highlight_inversions && record.orientation != record.ctg_orientation {
+        path = set_inversion_style(path);
+    };
+    if opts.show_dups && (record.t_dup || record.q_dup) {
+        path = set_dup_style(path, color, stroke_width);
+    };
+    if let Some(highlight_query) = opts.highlight_query {
+        path = set_highlight_query_style(
+            path,
+            record.q_name == highlight_query,
+            opts.theme,
+            2.0 * stroke_width,
+        );
+    };
+    path
+}
+
+/// draw the per-chromosome panel of a target with the (id, name, length, offset, records) of the target,
+/// return None if the panel is not drawn
+pub fn get_chr_svg_group<R: Borrow<CtgMapRec>>(
+    target_aln_block_record: &(u32, String, u32, f64, &Vec<R>),
+    assembly: &AssemblyRecords<R>,
+    opts: &PanelOptions,
+) -> Option<(element::Group, Vec<RibbonLayout>)> {
+    let PanelOptions {
+        scaling_factor,
+        theme,
+        font_family,
+        opacity,
+        track_layout,
+        ..
+    } = *opts;
+    let no_query_order = FxHashMap::<String, usize>::default();
+    let query_order = opts.query_order.unwrap_or(&no_query_order);
+    let t_name = target_aln_block_record.1.clone();
+    let mut group = element::Group::new();
+    let t_offset = 0.0;
+    let t_len = target_aln_block_record.2;
+    let y = track_layout.ref_y;
+    if opts.grid {
+        // appended first to stay behind everything else of the panel
+        draw_grid_lines(
+            &mut group,
+            t_len as f64 * scaling_factor,
+            &[track_layout.ref_y, track_layout.query_y],
+            theme,
+        );
+    };
+    if let Some(gap_min_length) = opts.gap_min_length {
+        let covered = target_aln_block_record
+            .4
+            .iter()
+            .map(|r| {
+                let r: &CtgMapRec = r.borrow();
+                (r.ts, r.te)
+            })
+            .collect::<Vec<_>>();
+        draw_gap_markers(
+            &mut group,
+            t_len,
+            &covered,
+            scaling_factor,
+            y,
+            gap_min_length,
+        );
+    };
+    let mut draw_plain_ref_track = || {
+        let b = t_offset * scaling_factor;
+        let e = (t_offset + t_len as f64) * scaling_factor;
+        // let w = 4.0 + ((target_aln_block_records.0 + 1) % 2) as f64 * 1.5;
+        let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
+        let path = element::Path::new()
+            .set("class", "ref_track")
+            .set("stroke", theme.fg_color())
+            .set("stroke-width", 8)
+            .set("opacity", opacity.track)
+            .set("stroke-opacity", opacity.track)
+            .set("d", path_str);
+        group.append(path);
+    };
+    if let Some(cytobands) = opts.cytobands {
+        if let Some(cyto_records) = cytobands.cytobands.get(&t_name) {
+            let c_bgn = cyto_records.iter().map(|v| v.0).min().unwrap_or(0);
+            let c_end = cyto_records.iter().map(|v| v.1).max().unwrap_or(0);
+            cyto_records.iter().for_each(|(cs, ce, c_name, band)| {
+                let b = (t_offset + *cs as f64) * scaling_factor;
+                let e = (t_offset + *ce as f64) * scaling_factor;
+                let color = theme.cytoband_color(band);
+                let outline =
+                    get_cytoband_outline(b, e, y, c_name, band, *cs == c_bgn, *ce == c_end);
+                let mut path = if let Some(outline) = outline {
+                    // a thin outline of the same color to avoid the seams between the filled bands
+                    element::Path::new()
+                        .set("fill", color)
+                        .set("stroke", color)
+                        .set("stroke-width", 0.25)
+                        .set("opacity", opacity.track)
+                        .set("d", outline)
+                } else {
+                    let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
+                    element::Path::new()
+                        .set("stroke", color)
+                        .set("stroke-width", 8)
+                        .set("opacity", opacity.track)
+                        .set("stroke-opacity", opacity.track)
+                        .set("d", path_str)
+                };
+                path.append(element::Title::new(c_name.clone()));
+                group.append(path);
+            })
+        } else {
+            draw_plain_ref_track()
+        };
+    } else {
+        draw_plain_ref_track()
+    }
+    opts.ref_annotation_tracks
+        .iter()
+        .enumerate()
+        .for_each(|(idx, track)| {
+            if let Some(regions) = track.regions.get(&t_name) {
+                let y2 = y - 8.0 + get_ref_annotation_dy(idx);
+                regions.iter().for_each(|(bgn, end, color)| {
+                    let b = (t_offset + *bgn as f64) * scaling_factor;
+                    let e = (t_offset + *end as f64) * scaling_factor;
+                    let path_str = format!("M {b:0.4} {y2:0.4} L {e:0.4} {y2:0.4}");
+                    let mut path = element::Path::new()
+                        .set("stroke", *color)
+                        .set("stroke-width", 6)
+                        .set("opacity", opacity.track)
+                        .set("stroke-opacity", opacity.track)
+                        .set("d", path_str);
+                    path.append(element::Title::new(format!(
+                        "{}: {}-{}",
+                        track.label, bgn, end
+                    )));
+                    group.append(path);
+                });
+            }
+        });
+
+    if let Some(regions) = opts.telomeres.and_then(|t| t.get(&t_name)) {
+        regions
+            .iter()
+            .filter(|(bgn, end)| bgn < end && *end <= t_len)
+            .for_each(|(bgn, end)| {
+                // a triangle pointing down to the middle of the telomere
+                let x = (t_offset + 0.5 * (*bgn as f64 + *end as f64)) * scaling_factor;
+                let (y0, y1) = (y - 12.0, y - 4.0);
+                let points = format!(
+                    "{:0.4},{y0:0.4} {:0.4},{y0:0.4} {x:0.4},{y1:0.4}",
+                    x - 4.0,
+                    x + 4.0
+                );
+                let mut triangle = element::Polygon::new()
+                    .set("points", points)
+                    .set("fill", "#F00")
+                    .set("opacity", opacity.track);
+                triangle.append(element::Title::new(format!("telomere: {}-{}", bgn, end)));
+                group.append(triangle);
+            });
+    };
+
+    if let Some(genes) = opts.genes.and_then(|g| g.get(&t_name)) {
+        // above the lanes of the reference annotations
+        let y2 = y - 8.0 + get_ref_annotation_dy(opts.ref_annotation_tracks.len());
+        genes
+            .iter()
+            .filter(|gene| gene.bgn < gene.end)
+            .for_each(|gene| {
+                let b = (t_offset + gene.bgn as f64) * scaling_factor;
+                let e = (t_offset + gene.end as f64) * scaling_factor;
+                let mut arrow = element::Polygon::new()
+                    .set("points", get_gene_arrow_points(b, e, y2, gene.strand))
+                    .set("fill", theme.fg_color())
+                    .set("opacity", opacity.track);
+                arrow.append(element::Title::new(format!(
+                    "{}: {}-{} {}",
+                    gene.name, gene.bgn, gene.end, gene.strand
+                )));
+                group.append(arrow);
+                // only label the genes wide enough for a readable name, all of them have the tooltips
+                if e - b >= 10.0 {
+                    let text = element::Text::new(gene.name.clone())
+                        .set("x", format!("{b:0.4}"))
+                        .set("y", format!("{:0.4}", y2 - 2.5))
+                        .set("font-size", "3px")
+                        .set("font-family", font_family)
+                        .set("fill", theme.fg_color());
+                    group.append(text);
+                };
+            });
+    };
+
+    if let Some(tgt_to_alt_qry_records) = assembly
+        .tgt_to_alt_qry_records
+        .get(&target_aln_block_record.1)
+    {
+        let t_offset = 0.0;
+        tgt_to_alt_qry_records.iter().for_each(|record| {
+            let record: &CtgMapRec = record.borrow();
+            let b = (t_offset + record.ts as f64) * scaling_factor;
+            let e = (t_offset + record.te as f64) * scaling_factor;
+            let y = track_layout.alt_tgt_y;
+            let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
+            let mut path = element::Path::new()
+                .set("stroke", theme.fg_color())
+                .set("stroke-width", 8)
+                .set("opacity", opacity.track)
+                .set("stroke-opacity", opacity.track)
+                .set("d", path_str);
+            let na = "N/A".to_string();
+            let q_tgt = assembly.ctg2tgt.get(&record.q_name).unwrap_or(&na);
+            path.append(element::Title::new(format!(
+                "{} to {} with {}:{}-{}",
+                record.t_name, q_tgt, record.q_name, record.qs, record.qe
+            )));
+            group.append(path);
+        })
+    };
+    let records = target_aln_block_record
+        .4
+        .iter()
+        .map(|r| r.borrow())
+        .collect::<Vec<&CtgMapRec>>();
+    let mut best_query_block = FxHashMap::<String, &CtgMapRec>::default();
+    records.iter().for_each(|&record| {
+        let e = best_query_block
+            .entry(record.q_name.clone())
+            .or_insert(record);
+        if (e.qs as i32 - e.qe as i32).abs() < (record.qs as i32 - record.qe as i32).abs() {
+            *e = record;
+        }
+    });
+    let mut best_query_block = best_query_block.into_values().collect::<Vec<_>>();
+    sort_query_blocks(
+        &mut best_query_block,
+        &records,
+        query_order,
+        opts.query_sort,
+        assembly.query_length,
+    );
+    // the query track starts under the start of the region
+    let mut q_offset = opts.region.map(|(r_bgn, _)| r_bgn as f64).unwrap_or(0.0);
+    let mut q_offset_map = FxHashMap::<String, QueryLane>::default();
+    best_query_block.into_iter().for_each(|record| {
+        let q_len = assembly
+            .query_length
+            .get(&record.q_name)
+            .unwrap_or(&record.ctg_len);
+        if !q_offset_map.contains_key(&record.q_name) {
+            let ctg_aln_orientation = get_display_orientation(record, opts.flip_query);
+            q_offset_map.insert(
+                record.q_name.clone(),
+                QueryLane {
+                    offset: q_offset,
+                    q_len: *q_len,
+                    orientation: ctg_aln_orientation,
+                },
+            );
+
+            let b = (t_offset + q_offset) * scaling_factor;
+            let e = (t_offset + q_offset + *q_len as f64) * scaling_factor;
+            let y = track_layout.query_y;
+            let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
+            let color = assembly.color_assigner.get_color(&record.q_name);
+            let mut path = element::Path::new()
+                .set("class", "query_track")
+                .set("stroke", color)
+                .set("stroke-width", 8)
+                .set("opacity", opacity.track)
+                .set("stroke-opacity", opacity.track)
+                .set("d", path_str);
+            path.append(element::Title::new(record.q_name.clone()));
+            group.append(path);
+
+            if let Some(qry_to_alt_tgt_records) =
+                assembly.qry_to_alt_tgt_records.get(&record.q_name)
+            {
+                qry_to_alt_tgt_records.iter().for_each(|record| {
+                    let record: &CtgMapRec = record.borrow();
+                    let qe = if ctg_aln_orientation == 0 {
+                        record.qs
+                    } else {
+                        q_len - record.qs
+                    };
+                    let qs = if ctg_aln_orientation == 0 {
+                        record.qe
+                    } else {
+                        q_len - record.qe
+                    };
+                    let b = (t_offset + q_offset + qs as f64) * scaling_factor;
+                    let e = (t_offset + q_offset + qe as f64) * scaling_factor;
+                    let y = track_layout.alt_qry_y;
+                    let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
+                    let color = assembly.color_assigner.get_color(&record.q_name);
+                    let mut path = element::Path::new()
+                        .set("stroke", color)
+                        .set("stroke-width", 8)
+                        .set("opacity", opacity.track)
+                        .set("stroke-opacity", opacity.track)
+                        .set("d", path_str);
+                    path.append(element::Title::new(format!(
+                        "{}@{}:{}-{}",
+                        record.q_name, record.t_name, record.ts, record.te
+                    )));
+                    group.append(path);
+                });
+            };
+
+            if let Some(regions) = opts.query_annotations.and_then(|h| h.get(&record.q_name)) {
+                regions.iter().for_each(|(bgn, end)| {
+                    // the same orientation transformation as the alignment records
+                    let (qs, qe) = if ctg_aln_orientation == 0 {
+                        (*bgn, *end)
+                    } else {
+                        (q_len.saturating_sub(*end), q_len.saturating_sub(*bgn))
+                    };
+                    let b = (t_offset + q_offset + qs as f64) * scaling_factor;
+                    let e = (t_offset + q_offset + qe as f64) * scaling_factor;
+                    let y = track_layout.alt_qry_y;
+                    let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
+                    let mut path = element::Path::new()
+                        .set("stroke", "#00F")
+                        .set("stroke-width", 6)
+                        .set("opacity", opacity.track)
+                        .set("stroke-opacity", opacity.track)
+                        .set("d", path_str);
+                    path.append(element::Title::new(format!(
+                        "{}:{}-{}",
+                        record.q_name, bgn, end
+                    )));
+                    group.append(path);
+                });
+            };
+
+            q_offset += *q_len as f64;
+        };
+    });
+    let mut ribbons = Vec::<RibbonLayout>::new();
+    records.into_iter().for_each(|record| {
+        if !is_ribbon_drawn(record, opts) {
+            return;
+        };
+
+        let ts = record.ts as f64 + t_offset;
+        let te = record.te as f64 + t_offset;
+
+        // the ribbon goes to the lane drawn for the contig, with the length and the orientation of the lane
+        let lane = q_offset_map.get(&record.q_name).unwrap();
+        let q_len = &lane.q_len;
+        let (qs, qe) = lane.get_ribbon_query_span(record);
+        let qs = qs as f64 + t_offset + lane.offset;
+        let qe = qe as f64 + t_offset + lane.offset;
+        let ts = ts * scaling_factor;
+        let te = te * scaling_factor;
+        let qs = qs * scaling_factor;
+        let qe = qe * scaling_factor;
+        let y = track_layout.trapezoid_top_y;
+        let y2 = track_layout.trapezoid_bottom_y;
+        if is_double_dup(record, opts) {
+            group.append(get_double_dup_line(ts, te, qs, qe, y, y2));
+            return;
+        };
+
+        let color = assembly.color_assigner.get_color(&record.q_name);
+        ribbons.push(RibbonLayout {
+            q_name: record.q_name.clone(),
+            ts,
+            te,
+            qs,
+            qe,
+        });
+        let path_str = format!(
+            "M {ts:0.4} {y:0.4} L {te:0.4} {y:0.4} L {qe:0.4} {y2:0.4} L {qs:0.4} {y2:0.4} Z"
+        );
+        let mut path = get_ribbon_path(record, color, path_str, opts, 1.0);
+        let orientation = if record.orientation == 0 { '+' } else { '-' };
+        let t_dup_mark = if record.t_dup { 1 } else { 0 };
+        let q_dup_mark = if record.q_dup { 1 } else { 0 };
+        let t_span = record.te.saturating_sub(record.ts);
+        let q_span = record.qe.saturating_sub(record.qs);
+        let ctg_fraction = if *q_len > 0 {
+            100.0 * q_span as f64 / *q_len as f64
+        } else {
+            0.0
+        };
+        path.append(element::Title::new(format!(
+            "{}:{}-{} @ {}:{}-{} {}:{}:{} {} target / {} query, {:0.1}% of contig",
+            record.t_name,
+            record.ts,
+            record.te,
+            record.q_name,
+            record.qs,
+            record.qe,
+            orientation,
+            t_dup_mark,
+            q_dup_mark,
+            format_span(t_span),
+            format_span(q_span),
+            ctg_fraction
+        )));
+
+        group.append(path);
+    });
+    if let Some(coverage_bins) = opts.coverage_bins {
+        let intervals = target_aln_block_record
+            .4
+            .iter()
+            .map(|r| {
+                let r: &CtgMapRec = r.borrow();
+                (r.ts, r.te)
+            })
+            .collect::<Vec<_>>();
+        let bins = get_coverage_bins(&intervals, t_len, coverage_bins);
+        let max_depth = bins.iter().map(|v| v.2).fold(0.0, f64::max);
+        // over the top of the ribbons, right under the alternative hits of the reference
+        let y = track_layout.alt_tgt_y + 4.0;
+        bins.into_iter()
+            .filter(|(_, _, depth)| *depth > 0.0)
+            .for_each(|(bgn, end, depth)| {
+                let b = (t_offset + bgn as f64) * scaling_factor;
+                let e = (t_offset + end as f64) * scaling_factor;
+                let mut rect = element::Rectangle::new()
+                    .set("x", format!("{b:0.4}"))
+                    .set("y", format!("{y:0.4}"))
+                    .set("width", format!("{:0.4}", e - b))
+                    .set("height", 4)
+                    .set("fill", theme.fg_color())
+                    .set("opacity", format!("{:0.4}", depth / max_depth));
+                rect.append(element::Title::new(format!(
+                    "coverage {}:{}-{}: {:0.2}",
+                    t_name, bgn, end, depth
+                )));
+                group.append(rect);
+            });
+    };
+    if let Some(scale_bar_len) = opts.scale_bar_len {
+        // right-aligned to the end of the reference track or the region
+        let t_end = opts
+            .region
+            .map(|(_, r_end)| r_end.min(t_len))
+            .unwrap_or(t_len);
+        let x = (t_end as f64 - scale_bar_len as f64) * scaling_factor;
+        group.append(get_scale_bar_group(
+            scale_bar_len,
+            scaling_factor,
+            x,
+            -15.0,
+            8.0,
+            theme,
+            font_family,
+        ));
+    };
+    if opts.ruler {
+        let tick_interval_bp = opts
+            .tick_interval
+            .map(|v| v as u32)
+            .unwrap_or_else(|| get_ruler_interval(t_len, scaling_factor));
+        draw_ruler(
+            &mut group,
+            t_len,
+            scaling_factor,
+            tick_interval_bp,
+            theme,
+            font_family,
+        );
+    };
+    Some((group, ribbons))
+}
+
+/// a sub-range of the palette for the `idx`-th of `n` assemblies
+pub fn palette_section(
+    cmap: &'static [&'static str],
+    idx: usize,
+    n: usize,
+) -> &'static [&'static str] {
+    let size = (cmap.len() / n.max(1)).max(1);
+    let start = (idx * size).min(cmap.len().saturating_sub(1));
+    &cmap[start..(start + size).min(cmap.len())]
+}
+
+/// the additional assemblies drawn as extra query tracks under the per-chromosome panels of the primary one
+struct MultiAssemblyPlot<'a> {
+    assemblies: &'a [(CtgMapSet, String)],
+    ctg2tgt: Vec<FxHashMap<String, String>>,
+    buckets: Vec<RecordBuckets<'a>>,
+    query_length: Vec<FxHashMap<String, u32>>,
+    color_assigners: Vec<ColorAssigner>,
+    track_spacing: f64,
+}
+
+impl<'a> MultiAssemblyPlot<'a> {
+    /// the palette section 0 is for the primary assembly, the `i`-th assembly here uses the section `i + 1`,
+    /// the q_dup records to the assigned targets are drawn with `show_dups`
+    fn new(
+        assemblies: &'a [(CtgMapSet, String)],
+        cmap: &'static [&'static str],
+        track_spacing: f64,
+        show_dups: bool,
+    ) -> Self {
+        let n_sections = assemblies.len() + 1;
+        let ctg2tgt = assemblies
+            .iter()
+            .map(|(ctgmap_set, _)| assign_contigs_to_targets(&ctgmap_set.records))
+            .collect::<Vec<_>>();
+        let buckets = assemblies
+            .iter()
+            .zip(ctg2tgt.iter())
+            .map(|((ctgmap_set, _), ctg2tgt)| {
+                bucket_records(&ctgmap_set.records, ctg2tgt, show_dups)
+            })
+            .collect::<Vec<_>>();
+        let query_length = assemblies
+            .iter()
+            .map(|(ctgmap_set, _)| {
+                ctgmap_set
+                    .query_length
+                    .iter()
+                    .map(|v| (v.1.clone(), v.2))
+                    .collect::<FxHashMap<_, _>>()
+            })
+            .collect::<Vec<_>>();
+        let color_assigners = assemblies
+            .iter()
+            .enumerate()
+            .map(|(idx, (ctgmap_set, _))| {
+                let mut records = ctgmap_set.records.iter().collect::<Vec<_>>();
+                records.sort_by(|a, b| (&a.t_name, a.ts).cmp(&(&b.t_name, b.ts)));
+                let mut q_name_set = FxHashSet::<String>::default();
+                let q_names = records
+                    .into_iter()
+                    .filter(|r| q_name_set.insert(r.q_name.clone()))
+                    .map(|r| r.q_name.clone())
+                    .collect::<Vec<_>>();
+                ColorAssigner::new(&q_names, palette_section(cmap, idx + 1, n_sections))
+            })
+            .collect::<Vec<_>>();
+        MultiAssemblyPlot {
+            assemblies,
+            ctg2tgt,
+            buckets,
+            query_length,
+            color_assigners,
+            track_spacing,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.assemblies.len()
+    }
+
+    /// the extra height of a per-chromosome panel for the additional tracks
+    fn extra_height(&self) -> f64 {
+        self.track_spacing * self.len() as f64
+    }
+
+    /// render the tracks of the target one by one, each shifted down by `track_spacing` from the previous one,
+    /// only the alignment tracks of the main panel options are drawn
+    fn render_tracks(
+        &self,
+        t_id: u32,
+        t_name: &str,
+        t_len: u32,
+        opts: &PanelOptions,
+        alt_hits: bool,
+    ) -> element::Group {
+        let mut group = element::Group::new().set("class", "additional_tracks");
+        let no_records = Vec::<Cow<CtgMapRec>>::new();
+        let no_alt_records = FxHashMap::<String, Vec<Cow<CtgMapRec>>>::default();
+        self.assemblies
+            .iter()
+            .enumerate()
+            .for_each(|(idx, (_, label))| {
+                let buckets = &self.buckets[idx];
+                let records = buckets.tgt_to_records.get(t_name).unwrap_or(&no_records);
+                let (tgt_to_alt_qry_records, qry_to_alt_tgt_records) = if alt_hits {
+                    (
+                        &buckets.tgt_to_alt_qry_records,
+                        &buckets.qry_to_alt_tgt_records,
+                    )
+                } else {
+                    (&no_alt_records, &no_alt_records)
+                };
+                let dy = self.track_spacing * (idx + 1) as f64;
+                let mut track = element::Group::new()
+                    .set("transform", format!("translate(0, {dy:0.4})"))
+                    .set("id", format!("{}_{}", label, t_name));
+                let text = element::Text::new(label.clone())
+                    .set("x", 0.0)
+                    .set("y", -2.0)
+                    .set("font-size", "8px")
+                    .set("font-family", opts.font_family)
+                    .set("fill", opts.theme.fg_color());
+                track.append(text);
+                if let Some((track_group, _)) = get_chr_svg_group(
+                    &(t_id, t_name.to_string(), t_len, 0.0, records),
+                    &AssemblyRecords {
+                        tgt_to_alt_qry_records,
+                        qry_to_alt_tgt_records,
+                        ctg2tgt: &self.ctg2tgt[idx],
+                        query_length: &self.query_length[idx],
+                        color_assigner: &self.color_assigners[idx],
+                    },
+                    &PanelOptions {
+                        scaling_factor: opts.scaling_factor,
+                        theme: opts.theme,
+                        font_family: opts.font_family,
+                        opacity: opts.opacity,
+                        track_layout: opts.track_layout,
+                        query_order: opts.query_order,
+                        query_sort: opts.query_sort,
+                        show_dups: opts.show_dups,
+                        highlight_inversions: opts.highlight_inversions,
+                        flip_query: opts.flip_query,
+                        inversions_only: opts.inversions_only,
+                        shade_reverse_strand: opts.shade_reverse_strand,
+                        highlight_query: opts.highlight_query,
+                        ..PanelOptions::default()
+                    },
+                ) {
+                    track.append(track_group);
+                };
+                group.append(track);
+            });
+        group
+    }
+}
+
+/// pick a round scale bar length about a tenth of the largest target
+pub fn get_scale_bar_length(max_t_len: u32) -> u32 {
+    [
+        10_000, 50_000, 100_000, 500_000, 1_000_000, 5_000_000, 10_000_000,
+    ]
+    .into_iter()
+    .filter(|&len| len * 10 <= max_t_len)
+    .max()
+    .unwrap_or(10_000)
+}
+
+/// the height of the document for the per-chromosome panels of `number_targets` targets under the overview panel
+/// (`with_overview`), or for the overview panel only (`overview_only`)
+pub fn get_svg_box_height(
+    number_targets: usize,
+    chr_panel_height: f64,
+    overview_only: bool,
+    with_overview: bool,
+) -> f64 {
+    if overview_only {
+        180.0
+    } else if with_overview {
+        // the panels start at 200 under the overview
+        number_targets as f64 * chr_panel_height + 250.0
+    } else {
+        // the selected targets are stacked vertically
+        (number_targets as f64 * chr_panel_height + 50.0).max(180.0)
+    }
+}
+
+/// draw the title and the subtitle upward from `y`, return the group and the height taken by them
+pub fn get_title_group(
+    title: &Option<String>,
+    subtitle: &Option<String>,
+    y: f64,
+    theme: Theme,
+    font_family: &str,
+) -> (Option<element::Group>, f64) {
+    if title.is_none() && subtitle.is_none() {
+        return (None, 0.0);
+    };
+    let title_height = if title.is_some() { 30.0 } else { 0.0 };
+    let subtitle_height = if subtitle.is_some() { 18.0 } else { 0.0 };
+    let top = y - title_height - subtitle_height;
+    let mut group = element::Group::new().set("id", "title");
+    if let Some(title) = title {
+        let text = element::Text::new(title.clone())
+            .set("x", 0.0)
+            .set("y", top + 22.0)
+            .set("font-size", "20px")
+            .set("font-family", font_family)
+            .set("font-weight", "bold")
+            .set("fill", theme.fg_color());
+        group.append(text);
+    };
+    if let Some(subtitle) = subtitle {
+        let text = element::Text::new(subtitle.clone())
+            .set("x", 0.0)
+            .set("y", top + title_height + 13.0)
+            .set("font-size", "12px")
+            .set("font-family", font_family)
+            .set("fill", theme.fg_color());
+        group.append(text);
+    };
+    (Some(group), title_height + subtitle_height)
+}
+
+/// the width of a per-chromosome panel to the end of the target or the query track (the panels overflow to the right of
+/// the panel width in the stacked layout)
+pub fn get_chr_panel_width<R: Borrow<CtgMapRec>>(
+    target_aln_block_record: &(u32, String, u32, f64, &Vec<R>),
+    query_length: &FxHashMap<String, u32>,
+    scaling_factor: f64,
+) -> f64 {
+    let mut q_set = FxHashSet::<String>::default();
+    let q_len_sum = target_aln_block_record
+        .4
+        .iter()
+        .map(|r| -> &CtgMapRec { r.borrow() })
+        .filter(|r| q_set.insert(r.q_name.clone()))
+        .map(|r| *query_length.get(&r.q_name).unwrap_or(&r.ctg_len) as f64)
+        .sum::<f64>();
+    let span = (target_aln_block_record.2 as f64).max(q_len_sum);
+    span * scaling_factor + 20.0
+}
+
+/// the chromosome name of a per-chromosome panel at the fixed x position of `pos`,
+/// vertically centered at `y_offset`
+pub fn place_label(
+    pos: LabelPosition,
+    t_name: &str,
+    y_offset: f64,
+    panel_width: f64,
+) -> element::Text {
+    let (x, anchor) = match pos {
+        LabelPosition::Left => (-panel_width * 0.04, "start"),
+        LabelPosition::Right => (panel_width * 0.85, "start"),
+        LabelPosition::Inside => (panel_width * 0.4, "middle"),
+    };
+    element::Text::new(t_name)
+        .set("x", x)
+        .set("y", y_offset)
+        .set("text-anchor", anchor)
+        .set("dominant-baseline", "central")
+}
+
+/// a standalone document of a per-chromosome panel with its label, for the split outputs,
+/// `label_margin` is added to the left of the panel
+#[allow(clippy::too_many_arguments)]
+pub fn get_chr_document(
+    chr_panel: Document,
+    label: element::Text,
+    width: f64,
+    height: f64,
+    label_margin: f64,
+    theme: Theme,
+    highlight_inversions: bool,
+    gap_markers: bool,
+    external_css: Option<&str>,
+) -> Document {
+    let width_with_margin = width + label_margin;
+    // avoid a "-0" in the view box without the margin
+    let view_box_x = if label_margin > 0.0 {
+        -label_margin
+    } else {
+        0.0
+    };
+    let mut chr_document = get_plot_document(
+        (view_box_x, 0.0, width_with_margin, height),
+        width_with_margin,
+        height,
+        theme,
+    );
+    if let Some(external_css) = external_css {
+        chr_document.append(element::Style::new(external_css));
+    };
+    if gap_markers {
+        chr_document.append(create_gap_pattern());
+    };
+    if highlight_inversions {
+        chr_document.append(create_inversion_pattern());
+    };
+    chr_document.append(label);
+    chr_document.append(chr_panel);
+    chr_document
+}
+
+/// a document of `width` x `height` over `view_box` with the background of the theme, the CSS background is
+/// ignored by many SVG viewers, it is drawn as a rectangle too with the dark theme
+pub fn get_plot_document(
+    view_box: (f64, f64, f64, f64),
+    width: f64,
+    height: f64,
+    theme: Theme,
+) -> Document {
+    let mut document = Document::new()
+        .set("viewBox", view_box)
+        .set("width", width)
+        .set("height", height)
+        .set("style", format!("background-color: {}", theme.bg_color()));
+    if let Theme::Dark = theme {
+        let background = element::Rectangle::new()
+            .set("x", view_box.0)
+            .set("y", view_box.1)
+            .set("width", "100%")
+            .set("height", "100%")
+            .set("fill", theme.bg_color());
+        document.append(background);
+    };
+    document
+}
+
+/// replace the `<title>` of the SVG paths and lines with a floating div following the cursor,
+/// the alignment ribbon titles "t_name:ts-te @ q_name:qs-qe orientation:t_dup:q_dup" are shown field by field
+static TOOLTIP_JSCRIPT: &str = r#"
+<div id="aln_tooltip"></div>
+<script>
+document.addEventListener('readystatechange', event => {
+    if (event.target.readyState === "complete") {
+        const tooltip = document.getElementById("aln_tooltip");
+        const alnTitle = /^(\S+):(\d+)-(\d+) @ (\S+):(\d+)-(\d+) ([+-]):(\d):(\d) (.+) target \/ (.+) query, (.+) of contig$/;
+        for (const path of document.querySelectorAll("path, line")) {
+            const title = path.querySelector("title");
+            if (title === null) {
+                continue;
+            }
+            const text = title.textContent.trim();
+            // remove the title so the browser does not show its own tooltip as well
+            title.remove();
+            const m = text.match(alnTitle);
+            const lines = m ? [
+                `query: ${m[4]}:${m[5]}-${m[6]}`,
+                `target: ${m[1]}:${m[2]}-${m[3]}`,
+                `orientation: ${m[7]}`,
+                `t_dup: ${m[8]} q_dup: ${m[9]}`,
+                `length: ${m[10]} target / ${m[11]} query`,
+                `${m[12]} of the contig`
+            ] : [text];
+            path.addEventListener('mousemove', ev => {
+                tooltip.replaceChildren();
+                lines.forEach((line, i) => {
+                    if (i > 0) {
+                        tooltip.appendChild(document.createElement("br"));
+                    }
+                    tooltip.appendChild(document.createTextNode(line));
+                });
+                tooltip.style.left = (ev.pageX + 12) + "px";
+                tooltip.style.top = (ev.pageY + 12) + "px";
+                tooltip.style.display = "block";
+            });
+            path.addEventListener('mouseleave', ev => {
+                tooltip.style.display = "none";
+            });
+        }
+    }
+});
+</script>
+"#;
+
+/// drag to pan and click to zoom the per-chromosome panels in the HTML output
+static ZOOM_JSCRIPT: &str = r#"
+        <script>
+        document.addEventListener('readystatechange', event => {
+            if (event.target.readyState === "complete") {
+                var views = document.getElementsByClassName("chr_view");
+                const getViewBox = view => {
+                    const viewBoxValues = view.getAttribute('viewBox').split(' ').map(val => parseFloat(val));
+                    return { x: viewBoxValues[0], y: viewBoxValues[1], width: viewBoxValues[2], height: viewBoxValues[3] };
+                };
+                const setViewBox = (view, viewBox) => {
+                    view.setAttribute('viewBox', `${viewBox.x} ${viewBox.y} ${viewBox.width} ${viewBox.height}`);
+                };
+                for (let i = 0; i < views.length; i++) {
+                    // drag to pan, click to zoom in (alt-click to zoom out), double-click to reset
+                    let drag = null;
+                    views[i].style.cursor = "grab";
+                    views[i].addEventListener('mousedown', function(event) {
+                        if (event.button != 0) {
+                            return;
+                        }
+                        event.preventDefault();
+                        drag = { x: event.clientX, y: event.clientY, viewBox: getViewBox(views[i]), moved: false };
+                    });
+                    window.addEventListener('mousemove', function(event) {
+                        if (drag === null) {
+                            return;
+                        }
+                        const dx = event.clientX - drag.x;
+                        const dy = event.clientY - drag.y;
+                        if (!drag.moved && Math.abs(dx) + Math.abs(dy) < 4) {
+                            return;
+                        }
+                        drag.moved = true;
+                        views[i].style.cursor = "grabbing";
+                        // convert the mouse movement in pixels to the view box units
+                        const rect = views[i].getBoundingClientRect();
+                        let viewBox = { ...drag.viewBox };
+                        viewBox.x -= dx * viewBox.width / rect.width;
+                        viewBox.y -= dy * viewBox.height / rect.height;
+                        setViewBox(views[i], viewBox);
+                    });
+                    views[i].addEventListener('dblclick', function(event) {
+                        // reset to the view box when the document is built
+                        const initialViewBox = views[i].getAttribute('data-initial-viewbox');
+                        if (initialViewBox !== null) {
+                            views[i].setAttribute('viewBox', initialViewBox);
+                        }
+                    });
+                    window.addEventListener('mouseup', function(event) {
+                        if (drag === null) {
+                            return;
+                        }
+                        if (!drag.moved) {
+                            let viewBox = getViewBox(views[i]);
+                            if (event.altKey) {
+                                scalingFactor = 1.25;
+                            } else {
+                                scalingFactor = 0.8; 
+                            };
+                            viewBox.width *= scalingFactor;
+                            setViewBox(views[i], viewBox);
+                        };
+                        views[i].style.cursor = "grab";
+                        drag = null;
+                    });
+                };
+            }
+        });
+        </script>
+        "#;
+
+/// the extension of the output files, "html.gz" for the gzip-compressed HTML
+pub fn get_output_extension(svg: bool, gzip_html: bool) -> &'static str {
+    match (svg, gzip_html) {
+        (true, _) => "svg",
+        (false, true) => "html.gz",
+        (false, false) => "html",
+    }
+}
+
+/// write the SVG document, or the HTML page with the zooming and the tooltip scripts if `svg` is false,
+/// the HTML page is gzip-compressed if `gzip_html` is true
+pub fn write_plot_file(
+    path: &str,
+    document: &Document,
+    svg: bool,
+    gzip_html: bool,
+    theme: Theme,
+    no_tooltips: bool,
+) -> Result<(), PgrError> {
+    let out_file = BufWriter::new(File::create(Path::new(path)).map_err(PgrError::io_context(
+        format!("can't create the output file {}", path),
+    ))?);
+    let mut out_file: Box<dyn Write> = if gzip_html && !svg {
+        Box::new(GzEncoder::new(out_file, Compression::default()))
+    } else {
+        Box::new(out_file)
+    };
+    let mut svg_elment = Vec::<u8>::new();
+    svg::write(&mut svg_elment, document)?;
+    if !svg {
+        writeln!(out_file, r#"<html><body>"#)?;
+        writeln!(
+            out_file,
+            r#"<script>document.body.style.background = "{}";</script>"#,
+            theme.bg_color()
+        )?;
+        writeln!(out_file, "{}", ZOOM_JSCRIPT)?;
+        if !no_tooltips {
+            writeln!(
+                out_file,
+                "<style>#aln_tooltip {{ position: absolute; display: none; pointer-events: none; padding: 4px 6px; \
+                 font: 12px monospace; border-radius: 3px; opacity: 0.9; background: {}; color: {}; }}</style>",
+                theme.fg_color(),
+                theme.bg_color()
+            )?;
+            writeln!(out_file, "{}", TOOLTIP_JSCRIPT)?;
+        };
+        writeln!(out_file, r#"<div style="overflow:scroll;">"#)?;
+    };
+
+    writeln!(out_file, "{}", String::from_utf8_lossy(&svg_elment))?;
+
+    if !svg {
+        writeln!(out_file, "</div></body></html>")?;
+    };
+    out_file.flush()?;
+    Ok(())
+}
+
+/// the `max_contigs` query contigs with the most aligned bases for the legend, in the order of `q_names`
+pub fn get_legend_contigs(
+    q_names: &[String],
+    q_aligned_bases: &FxHashMap<String, u64>,
+    max_contigs: usize,
+) -> Vec<String> {
+    let aligned_bases = |q_name: &String| *q_aligned_bases.get(q_name).unwrap_or(&0);
+    let mut ranked = q_names.iter().enumerate().collect::<Vec<_>>();
+    // the sort is stable, the contigs with the same aligned bases stay in the order of `q_names`
+    ranked.sort_by_key(|(_, q_name)| std::cmp::Reverse(aligned_bases(q_name)));
+    ranked.truncate(max_contigs);
+    ranked.sort_by_key(|(idx, _)| *idx);
+    ranked
+        .into_iter()
+        .map(|(_, q_name)| q_name.clone())
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn get_legend_group(
+    q_names: &[String],
+    color_assigner: &ColorAssigner,
+    theme: Theme,
+    font_family: &str,
+    font_size: f64,
+    opacity: Opacity,
+    x: f64,
+    y: f64,
+) -> element::Group {
+    let mut group = element::Group::new().set("id", "legend");
+    // the color boxes and the row spacing follow the font size
+    let row_height = font_size * 1.4;
+    q_names.iter().enumerate().for_each(|(i, q_name)| {
+        let y = y + i as f64 * row_height;
+        let color = color_assigner.get_color(q_name);
+        let rect = element::Rectangle::new()
+            .set("x", x)
+            .set("y", y)
+            .set("width", font_size)
+            .set("height", font_size)
+            .set("fill", color)
+            .set("opacity", opacity.ribbon);
+        group.append(rect);
+        let text = element::Text::new(q_name.clone())
+            .set("x", x + row_height)
+            .set("y", y + font_size * 0.9)
+            .set("font-size", format!("{font_size}px"))
+            .set("font-family", font_family)
+            .set("fill", theme.fg_color());
+        group.append(text);
+    });
+    group
+}
+
+/// keep the `max_records` longest (in the target span) alignment records
+pub fn cap_records<R: Borrow<CtgMapRec> + Clone>(records: &[R], max_records: usize) -> Vec<R> {
+    let mut records = records.to_vec();
+    records.sort_by_key(|r| {
+        let r: &CtgMapRec = r.borrow();
+        std::cmp::Reverse(r.te.saturating_sub(r.ts))
+    });
+    records.truncate(max_records);
+    records
+}
+
+/// the records of an assembly by the targets they are drawn on, see `bucket_records`
+#[derive(Default)]
+pub struct RecordBuckets<'a> {
+    /// the records of the query contigs to their assigned targets
+    pub tgt_to_records: FxHashMap<String, Vec<Cow<'a, CtgMapRec>>>,
+    /// the records of each query contig to the targets other than its assigned one
+    pub qry_to_alt_tgt_records: FxHashMap<String, Vec<Cow<'a, CtgMapRec>>>,
+    /// the records to each target from the query contigs assigned to the other targets
+    pub tgt_to_alt_qry_records: FxHashMap<String, Vec<Cow<'a, CtgMapRec>>>,
+    /// the query contigs not assigned to any target, in the order of their first records
+    pub unassigned_ctgs: Vec<String>,
+    /// the q_dup records that are not drawn
+    pub q_dup_dropped: Vec<&'a CtgMapRec>,
+}
+
+/// put the records into the buckets by the targets assigned to the query contigs in `ctg2tgt`,
+/// the q_dup records are dropped unless `keep_q_dup` and they are to the assigned targets
+pub fn bucket_records<'a>(
+    records: &'a [CtgMapRec],
+    ctg2tgt: &FxHashMap<String, String>,
+    keep_q_dup: bool,
+) -> RecordBuckets<'a> {
+    let mut buckets = RecordBuckets::default();
+    let mut unassigned_ctgs = FxHashSet::<&str>::default();
+    records.iter().for_each(|r| {
+        if r.q_dup && !keep_q_dup {
+            buckets.q_dup_dropped.push(r);
+            return;
+        };
+        let tgt = match ctg2tgt.get(&r.q_name) {
+            Some(tgt) => tgt,
+            None => {
+                if !r.q_dup && unassigned_ctgs.insert(r.q_name.as_str()) {
+                    buckets.unassigned_ctgs.push(r.q_name.clone());
+                };
+                return;
+            }
+        };
+        if *tgt != r.t_name {
+            if r.q_dup {
+                buckets.q_dup_dropped.push(r);
+                return;
+            };
+            buckets
+                .qry_to_alt_tgt_records
+                .entry(r.q_name.clone())
+                .or_default()
+                .push(Cow::Borrowed(r));
+            buckets
+                .tgt_to_alt_qry_records
+                .entry(r.t_name.clone())
+                .or_default()
+                .push(Cow::Borrowed(r));
+            return;
+        };
+        buckets
+            .tgt_to_records
+            .entry(r.t_name.clone())
+            .or_default()
+            .push(Cow::Borrowed(r));
+    });
+    buckets
+}
+
+/// the options of `render_ctgmap`, the defaults are the ones of pgr-generate-chr-aln-plot
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    pub panel_width: f64,
+    /// if given, the plot is scaled to fit this many bases to the panel width instead of the plotted targets
+    pub total_target_bases: Option<f64>,
+    /// an error instead of a warning when `total_target_bases` is smaller than the plotted targets
+    pub strict: bool,
+    /// the gap (in bases) between the targets when they are fitted to the panel width
+    pub target_padding: f64,
+    /// the per-chromosome panels are drawn at this multiple of the scale fitting all targets to the panel width
+    pub chr_scaling_multiplier: f64,
+    /// scale each per-chromosome panel to fill 90% of the panel width
+    pub auto_scale_chr: bool,
+    /// if it is not empty, only draw the per-chromosome panels of these targets without the overview panel
+    pub ctg: Vec<String>,
+    /// only draw the overview panel
+    pub summary_only: bool,
+    /// the (name, start, end) of a region of the target in `ctg` to fill the panel with
+    pub region: Option<(String, u32, u32)>,
+    pub sort_chromosomes: ChromosomeSort,
+    pub length_transform: LengthTransform,
+    pub theme: Theme,
+    pub palette: &'static [&'static str],
+    pub font_family: String,
+    pub label_font_size: f64,
+    pub track_labels: LabelPosition,
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    /// the CSS rules added as a <style> element to the documents
+    pub external_css: Option<String>,
+    /// the height of the document instead of fitting it to the panels
+    pub box_height: Option<f64>,
+    pub legend: bool,
+    pub legend_max_contigs: usize,
+    pub opacity: Opacity,
+    pub track_layout: TrackLayout,
+    /// the contigs in it go first on the query tracks, see `sort_query_blocks`
+    pub query_order: FxHashMap<String, usize>,
+    pub query_sort: QuerySort,
+    pub cytobands: Option<CytoBands>,
+    pub ref_annotation_tracks: Vec<RefAnnotationTrack>,
+    pub telomeres: Option<FxHashMap<String, Vec<(u32, u32)>>>,
+    pub genes: Option<FxHashMap<String, Vec<GeneFeature>>>,
+    /// the regions marked under the query tracks by the query contig names
+    pub query_annotations: Option<FxHashMap<String, Vec<(u32, u32)>>>,
+    /// the (set, label) of the assemblies drawn as extra tracks under the per-chromosome panels
+    pub additional_assemblies: Vec<(CtgMapSet, String)>,
+    pub track_spacing: f64,
+    /// only keep the longest alignment records of each target in its per-chromosome panel
+    pub max_records_per_contig: Option<usize>,
+    pub scale_bar: bool,
+    pub tick_interval: Option<f64>,
+    pub ruler: bool,
+    pub grid: bool,
+    /// if given, mark the regions of the targets without the alignment records and at least this many bases long,
+    /// they are not marked by default
+    pub gap_min_length: Option<u32>,
+    pub coverage_bins: Option<usize>,
+    /// draw the alignments of the query contigs to the targets other than the assigned ones
+    pub alt_hits: bool,
+    pub show_dups: bool,
+    pub highlight_inversions: bool,
+    pub highlight_duplications: bool,
+    pub flip_query: bool,
+    pub inversions_only: bool,
+    pub shade_reverse_strand: bool,
+    pub highlight_query: Option<String>,
+    /// draw the overview panel over the per-chromosome panels of all targets
+    pub overview: bool,
+    /// return the per-chromosome panels as standalone documents instead of stacking them in the document
+    pub split_chr_panels: bool,
+    /// show the progress of the per-chromosome panels on stderr
+    pub show_progress: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            panel_width: 1400.0,
+            total_target_bases: None,
+            strict: false,
+            target_padding: 1.5e6,
+            chr_scaling_multiplier: 12.0,
+            auto_scale_chr: false,
+            ctg: vec![],
+            summary_only: false,
+            region: None,
+            sort_chromosomes: ChromosomeSort::default(),
+            length_transform: LengthTransform::default(),
+            theme: Theme::default(),
+            palette: &CMAP[..],
+            font_family: "monospace".to_string(),
+            label_font_size: 20.0,
+            track_labels: LabelPosition::default(),
+            title: None,
+            subtitle: None,
+            external_css: None,
+            box_height: None,
+            legend: false,
+            legend_max_contigs: 30,
+            opacity: Opacity::default(),
+            track_layout: TrackLayout::default(),
+            query_order: FxHashMap::default(),
+            query_sort: QuerySort::default(),
+            cytobands: None,
+            ref_annotation_tracks: vec![],
+            telomeres: None,
+            genes: None,
+            query_annotations: None,
+            additional_assemblies: vec![],
+            track_spacing: 110.0,
+            max_records_per_contig: None,
+            scale_bar: true,
+            tick_interval: None,
+            ruler: true,
+            grid: false,
+            gap_min_length: None,
+            coverage_bins: None,
+            alt_hits: true,
+            show_dups: false,
+            highlight_inversions: false,
+            highlight_duplications: false,
+            flip_query: false,
+            inversions_only: false,
+            shade_reverse_strand: false,
+            highlight_query: None,
+            overview: true,
+            split_chr_panels: false,
+            show_progress: false,
+        }
+    }
+}
+
+/// the bases of a target in the plot with the records of the query contigs assigned to it,
+/// the query contigs are laid out one after another under the target
+pub fn get_target_span<R: Borrow<CtgMapRec>>(
+    t_len: u32,
+    records: &[R],
+    query_length: &FxHashMap<String, u32>,
+) -> f64 {
+    let mut q_set = FxHashSet::<&str>::default();
+    let q_len_sum = records
+        .iter()
+        .map(|r| r.borrow())
+        .filter(|r| q_set.insert(r.q_name.as_str()))
+        .map(|r| *query_length.get(&r.q_name).unwrap_or(&r.ctg_len) as f64)
+        .sum::<f64>();
+    (t_len as f64).max(q_len_sum)
+}
+
+/// the scaling factor of the per-chromosome panel of a target of `t_len` bases in `render_ctgmap`,
+/// `scaling_factor` fits the spans of all plotted targets with the padding to the panel width
+pub fn get_chr_scaling_factor(scaling_factor: f64, t_len: u32, opts: &RenderOptions) -> f64 {
+    if opts.auto_scale_chr {
+        opts.panel_width * 0.9 / t_len.max(1) as f64
+    } else if let Some((_, r_bgn, r_end)) = opts.region.as_ref() {
+        opts.panel_width / (r_end - r_bgn) as f64
+    } else if !opts.ctg.is_empty() {
+        scaling_factor
+    } else {
+        scaling_factor * opts.chr_scaling_multiplier
+    }
+}
+
+/// the writer of the per-chromosome documents of `render_ctgmap_with_writer` called with the target names
+pub type ChrDocumentWriter<'a> = dyn Fn(&str, Document) -> Result<(), PgrError> + Sync + 'a;
+
+/// the plot made by `render_ctgmap`
+pub struct CtgMapPlot {
+    pub document: Document,
+    /// the standalone documents of the per-chromosome panels in the order of the targets with `split_chr_panels`,
+    /// it is empty if they are passed to the writer of `render_ctgmap_with_writer`
+    pub chr_documents: Vec<(String, Document)>,
+    pub layout: PlotLayout,
+    /// the warnings and the notes in the order they come up
+    pub messages: Vec<String>,
+}
+
+/// the plot of pgr-generate-chr-aln-plot: the overview panel of the targets over the per-chromosome panels,
+/// each query contig is drawn under the target it has the most aligned bases to;
+/// it fails only with `strict` when `total_target_bases` is smaller than the plotted targets
+pub fn render_ctgmap(set: &CtgMapSet, opts: &RenderOptions) -> Result<CtgMapPlot, PgrError> {
+    render_ctgmap_inner(set, opts, None)
+}
+
+/// the same as `render_ctgmap`, but with `split_chr_panels` each per-chromosome document is passed to
+/// `write_chr_document` with the target name as soon as it is drawn and dropped afterward instead of kept in
+/// `chr_documents`, the documents of different targets are written in parallel; it fails with the first error
+/// of `write_chr_document`
+pub fn render_ctgmap_with_writer(
+    set: &CtgMapSet,
+    opts: &RenderOptions,
+    write_chr_document: &ChrDocumentWriter,
+) -> Result<CtgMapPlot, PgrError> {
+    render_ctgmap_inner(set, opts, Some(write_chr_document))
+}
+
+fn render_ctgmap_inner(
+    set: &CtgMapSet,
+    opts: &RenderOptions,
+    write_chr_document: Option<&ChrDocumentWriter>,
+) -> Result<CtgMapPlot, PgrError> {
+    let mut messages = Vec::<String>::new();
+    opts.ctg.iter().for_each(|ctg| {
+        if !set.target_length.iter().any(|(_, t_name, _)| t_name == ctg) {
+            messages.push(format!(
+                "WARNING: --ctg {} is not found in the target_length",
+                ctg
+            ));
+        };
+    });
+
+    let mut target_length = set.target_length.clone();
+    sort_targets(&mut target_length, opts.sort_chromosomes);
+    let mut query_length = set
+        .query_length
+        .iter()
+        .map(|v| (v.1.clone(), v.2))
+        .collect::<FxHashMap<_, _>>();
+
+    let ctg2tgt = assign_contigs_to_targets(&set.records);
+    if let Some(highlight_query) = opts.highlight_query.as_ref() {
+        if !query_length.contains_key(highlight_query) {
+            messages.push(format!(
+                "WARNING: --highlight-query {} is not found in the query_length of the ctgmap.json file",
+                highlight_query
+            ));
+        };
+    };
+
+    let RecordBuckets {
+        mut tgt_to_records,
+        mut qry_to_alt_tgt_records,
+        mut tgt_to_alt_qry_records,
+        unassigned_ctgs,
+        ..
+    } = bucket_records(
+        &set.records,
+        &ctg2tgt,
+        opts.show_dups || opts.highlight_duplications,
+    );
+    unassigned_ctgs.iter().for_each(|q_name| {
+        messages.push(format!(
+            "WARNING: {} is not assigned to any target, skip its alignment records",
+            q_name
+        ));
+    });
+
+    let mut clipped_query_annotations = None;
+    if let Some((r_name, r_bgn, r_end)) = opts.region.as_ref() {
+        let records = tgt_to_records
+            .remove(r_name)
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|r| clip_record(r, *r_bgn, *r_end, true))
+            .collect::<Vec<_>>();
+
+        // the part of each query contig aligned to the region, the query coordinates are rebased to it
+        let mut q_windows = FxHashMap::<String, (u32, u32)>::default();
+        records.iter().for_each(|r| {
+            let (qs, qe) = (r.qs.min(r.qe), r.qs.max(r.qe));
+            let e = q_windows.entry(r.q_name.clone()).or_insert((qs, qe));
+            *e = (e.0.min(qs), e.1.max(qe));
+        });
+        let rebase = |r: CtgMapRec| {
+            let (q_bgn, q_end) = *q_windows.get(&r.q_name).unwrap();
+            Cow::Owned(CtgMapRec {
+                qs: r.qs - q_bgn,
+                qe: r.qe - q_bgn,
+                ctg_len: q_end - q_bgn,
+                ..r
+            })
+        };
+
+        if !records.is_empty() {
+            tgt_to_records.insert(
+                r_name.clone(),
+                records.into_iter().map(rebase).collect::<Vec<_>>(),
+            );
+        };
+        if let Some(records) = tgt_to_alt_qry_records.get_mut(r_name) {
+            *records = records
+                .iter()
+                .flat_map(|r| clip_record(r, *r_bgn, *r_end, true))
+                .map(Cow::Owned)
+                .collect::<Vec<_>>();
+        };
+        qry_to_alt_tgt_records.retain(|q_name, _| q_windows.contains_key(q_name));
+        qry_to_alt_tgt_records
+            .iter_mut()
+            .for_each(|(q_name, records)| {
+                let (q_bgn, q_end) = *q_windows.get(q_name).unwrap();
+                *records = records
+                    .iter()
+                    .flat_map(|r| clip_record(r, q_bgn, q_end, false))
+                    .map(rebase)
+                    .collect::<Vec<_>>();
+            });
+        q_windows.iter().for_each(|(q_name, (q_bgn, q_end))| {
+            query_length.insert(q_name.clone(), q_end - q_bgn);
+        });
+        // the contigs not in the region are left as they are and counted as not plotted below
+        clipped_query_annotations = opts.query_annotations.as_ref().map(|query_annotations| {
+            let mut query_annotations = query_annotations.clone();
+            query_annotations.iter_mut().for_each(|(q_name, regions)| {
+                if let Some((q_bgn, q_end)) = q_windows.get(q_name) {
+                    *regions = regions
+                        .iter()
+                        .filter(|(bgn, end)| bgn < q_end && end > q_bgn)
+                        .map(|(bgn, end)| (bgn.max(q_bgn) - q_bgn, end.min(q_end) - q_bgn))
+                        .collect::<Vec<_>>();
+                };
+            });
+            query_annotations
+        });
+    };
+    let query_annotations = clipped_query_annotations
+        .as_ref()
+        .or(opts.query_annotations.as_ref());
+
+    let target_padding = opts.target_padding;
+    let length_transform = opts.length_transform;
+    let max_t_len = target_length.iter().map(|v| v.2).max().unwrap_or(0);
+    let mut offset = 0_f64;
+    // the offsets of the targets in the overview panel with the transformed lengths
+    let mut overview_offset = 0_f64;
+    let mut overview_t_offsets = Vec::<f64>::new();
+    let target_aln_blocks = target_length
+        .iter()
+        .flat_map(|(id, t_name, t_len)| {
+            if !opts.ctg.is_empty() && !opts.summary_only && !opts.ctg.contains(t_name) {
+                return None;
+            };
+            let mut q_len_sum_transformed = 0.0;
+            let mut q_set = FxHashSet::<String>::default();
+            tgt_to_records
+                .get(t_name)
+                .unwrap_or(&vec![])
+                .iter()
+                .for_each(|record| {
+                    let q_len = query_length.get(&record.q_name).unwrap_or(&record.ctg_len);
+                    if !q_set.contains(&record.q_name) {
+                        q_set.insert(record.q_name.clone());
+                        q_len_sum_transformed += length_transform.apply(*q_len, max_t_len);
+                    };
+                });
+
+            if let Some(records) = tgt_to_records.get(t_name) {
+                let out = (*id, t_name.clone(), *t_len, offset, records);
+                offset += get_target_span(*t_len, records, &query_length) + target_padding;
+                overview_t_offsets.push(overview_offset);
+                overview_offset += length_transform
+                    .apply(*t_len, max_t_len)
+                    .max(q_len_sum_transformed)
+                    + target_padding;
+                Some(out)
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // order the query contigs as they appear along the targets for the color assignment and the legend
+    let mut q_names = Vec::<String>::new();
+    let mut q_name_set = FxHashSet::<String>::default();
+    let mut q_aligned_bases = FxHashMap::<String, u64>::default();
+    target_aln_blocks
+        .iter()
+        .for_each(|target_aln_block_record| {
+            let mut records = target_aln_block_record.4.iter().collect::<Vec<_>>();
+            records.sort_by_key(|&v| v.ts);
+            records.into_iter().for_each(|record| {
+                if !q_name_set.contains(&record.q_name) {
+                    q_name_set.insert(record.q_name.clone());
+                    q_names.push(record.q_name.clone());
+                }
+                *q_aligned_bases.entry(record.q_name.clone()).or_default() +=
+                    record.te.saturating_sub(record.ts) as u64;
+            });
+        });
+    if let Some(query_annotations) = query_annotations {
+        // the query contigs are only drawn in the per-chromosome panels
+        let skipped = query_annotations
+            .iter()
+            .filter(|(q_name, _)| opts.summary_only || !q_name_set.contains(*q_name))
+            .map(|(_, regions)| regions.len())
+            .sum::<usize>();
+        if skipped > 0 {
+            messages.push(format!(
+                "{} records in the query annotation bed file are skipped, their query contigs are not plotted",
+                skipped
+            ));
+        };
+    };
+    let multi_assembly_plot = MultiAssemblyPlot::new(
+        &opts.additional_assemblies,
+        opts.palette,
+        opts.track_spacing,
+        opts.show_dups,
+    );
+    let color_assigner = ColorAssigner::new(
+        &q_names,
+        palette_section(opts.palette, 0, multi_assembly_plot.len() + 1),
+    );
+    let theme = opts.theme;
+    let font_family = opts.font_family.as_str();
+    let opacity = opts.opacity;
+    let track_layout = opts.track_layout;
+    let chr_panel_height = track_layout.panel_height() + multi_assembly_plot.extra_height();
+    let svg_box_height = opts.box_height.unwrap_or_else(|| {
+        get_svg_box_height(
+            target_aln_blocks.len(),
+            chr_panel_height,
+            opts.split_chr_panels || opts.summary_only,
+            opts.ctg.is_empty(),
+        )
+    });
+    // the title and the subtitle are put above the panels, extend the view box upward for them
+    let (title_group, title_height) =
+        get_title_group(&opts.title, &opts.subtitle, -50.0, theme, font_family);
+    let view_box_y = -50.0 - title_height;
+    let svg_box_height = svg_box_height + title_height;
+
+    // start to construct the SVG element
+    let mut document = get_plot_document(
+        (
+            -opts.panel_width * 0.05,
+            view_box_y,
+            opts.panel_width * 0.95 * 2.0,
+            svg_box_height,
+        ),
+        opts.panel_width * 2.0,
+        svg_box_height,
+        theme,
+    )
+    .set("preserveAspectRatio", "none")
+    .set("id", "WholeGenomeViwer")
+    .set("overflow", "visible");
+    if let Some(external_css) = opts.external_css.as_ref() {
+        document.append(element::Style::new(external_css.as_str()));
+    };
+    if opts.gap_min_length.is_some() {
+        document.append(create_gap_pattern());
+    };
+    if opts.highlight_inversions {
+        document.append(create_inversion_pattern());
+    };
+    if let Some(title_group) = title_group {
+        document.append(title_group);
+    };
+
+    if target_aln_blocks.is_empty() {
+        messages.push(
+            "WARNING: no alignment record to plot, the output will be an empty canvas".to_string(),
+        );
+    };
+    let (scaling_factor, clipped) =
+        get_scaling_factor(opts.panel_width, opts.total_target_bases, offset);
+    if clipped {
+        let msg = format!(
+            "--total-target-bases {} is smaller than the {} bases of the plotted targets (with the padding), \
+             the ribbons beyond the panel width are clipped",
+            opts.total_target_bases.unwrap(),
+            offset
+        );
+        if opts.strict {
+            return Err(PgrError::InvalidArgument(msg));
+        };
+        messages.push(format!("WARNING: {}", msg));
+    };
+
+    let scale_bar_len = if !opts.scale_bar {
+        None
+    } else if let Some((_, r_bgn, r_end)) = opts.region.as_ref() {
+        Some(get_scale_bar_length(r_end - r_bgn))
+    } else {
+        let max_t_len = target_aln_blocks.iter().map(|v| v.2).max().unwrap_or(0);
+        Some(get_scale_bar_length(max_t_len))
+    };
+    let panel_options = PanelOptions {
+        scaling_factor,
+        theme,
+        font_family,
+        opacity,
+        track_layout,
+        query_order: Some(&opts.query_order),
+        query_sort: opts.query_sort,
+        cytobands: opts.cytobands.as_ref(),
+        ref_annotation_tracks: &opts.ref_annotation_tracks,
+        telomeres: opts.telomeres.as_ref(),
+        genes: opts.genes.as_ref(),
+        query_annotations,
+        scale_bar_len,
+        tick_interval: opts.tick_interval,
+        ruler: opts.ruler,
+        region: opts
+            .region
+            .as_ref()
+            .map(|(_, r_bgn, r_end)| (*r_bgn, *r_end)),
+        show_dups: opts.show_dups,
+        highlight_inversions: opts.highlight_inversions,
+        highlight_duplications: opts.highlight_duplications,
+        flip_query: opts.flip_query,
+        inversions_only: opts.inversions_only,
+        shade_reverse_strand: opts.shade_reverse_strand,
+        highlight_query: opts.highlight_query.as_deref(),
+        coverage_bins: opts.coverage_bins,
+        gap_min_length: opts.gap_min_length,
+        grid: opts.grid,
+    };
+
+    // the per-chromosome panels stay in the linear scale with the transformed overview
+    let overview_scaling_factor = if length_transform == LengthTransform::Linear {
+        scaling_factor
+    } else {
+        get_scaling_factor(opts.panel_width, None, overview_offset).0
+    };
+    let mut overview_ribbons = FxHashMap::<String, Vec<RibbonLayout>>::default();
+    let mut plot_overview = || {
+        let scaling_factor = overview_scaling_factor;
+        target_aln_blocks
+            .iter()
+            .zip(overview_t_offsets.iter())
+            .for_each(|(target_aln_block_records, &t_offset)| {
+                let t_name = target_aln_block_records.1.clone();
+                let t_factor =
+                    length_transform.position_factor(target_aln_block_records.2, max_t_len);
+                let mut group = element::Group::new().set("id", format!("overview_{}", t_name));
+                let b = t_offset * scaling_factor;
+                let e = (t_offset + length_transform.apply(target_aln_block_records.2, max_t_len))
+                    * scaling_factor;
+                let w = 4.0 + ((target_aln_block_records.0 + 1) % 2) as f64 * 1.5;
+                let path_str = format!("M {b:0.4} 6 L {e:0.4} 6");
+                let path = element::Path::new()
+                    .set("class", "ref_track")
+                    .set("stroke", theme.fg_color())
+                    .set("stroke-width", format!("{w}"))
+                    .set("opacity", opacity.track)
+                    .set("stroke-opacity", opacity.track)
+                    .set("d", path_str);
+                group.append(path);
+
+                let text = element::Text::new(target_aln_block_records.1.clone())
+                    .set("x", b)
+                    .set("y", 0)
+                    .set("font-size", format!("{}px", opts.label_font_size * 0.3))
+                    .set("font-family", font_family)
+                    .set("fill", theme.fg_color());
+                group.append(text);
+
+                opts.ref_annotation_tracks
+                    .iter()
+                    .enumerate()
+                    .for_each(|(idx, track)| {
+                        if let Some(regions) = track.regions.get(&t_name) {
+                            let y = 3.0 + get_ref_annotation_dy(idx);
+                            regions.iter().for_each(|(bgn, end, color)| {
+                                let b = (t_offset + *bgn as f64 * t_factor) * scaling_factor;
+                                let e = (t_offset + *end as f64 * t_factor) * scaling_factor;
+                                let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
+                                let path = element::Path::new()
+                                    .set("stroke", *color)
+                                    .set("stroke-width", 6)
+                                    .set("opacity", opacity.track)
+                                    .set("stroke-opacity", opacity.track)
+                                    .set("d", path_str);
+                                group.append(path);
+                            });
+                        }
+                    });
+
+                let mut best_query_block = FxHashMap::<String, &CtgMapRec>::default();
+                target_aln_block_records.4.iter().for_each(|record| {
+                    let e = best_query_block
+                        .entry(record.q_name.clone())
+                        .or_insert(record);
+                    if (e.qs as i32 - e.qe as i32).abs()
+                        < (record.qs as i32 - record.qe as i32).abs()
+                    {
+                        *e = record;
+                    }
+                });
+
+                let mut best_query_block = best_query_block.into_values().collect::<Vec<_>>();
+                sort_query_blocks(
+                    &mut best_query_block,
+                    target_aln_block_records.4,
+                    &opts.query_order,
+                    opts.query_sort,
+                    &query_length,
+                );
+                let mut q_offset = 0.0;
+                let mut q_offset_map = FxHashMap::<String, QueryLane>::default();
+                best_query_block.into_iter().for_each(|record| {
+                    let q_len = query_length.get(&record.q_name).unwrap_or(&record.ctg_len);
+                    if !q_offset_map.contains_key(&record.q_name) {
+                        q_offset_map.insert(
+                            record.q_name.clone(),
+                            QueryLane {
+                                offset: q_offset,
+                                q_len: *q_len,
+                                orientation: get_display_orientation(record, opts.flip_query),
+                            },
+                        );
+
+                        let q_len_transformed = length_transform.apply(*q_len, max_t_len);
+                        let b = (t_offset + q_offset) * scaling_factor;
+                        let e = (t_offset + q_offset + q_len_transformed) * scaling_factor;
+                        let y = 95.0;
+                        let path_str = format!("M {b:0.4} {y:0.4} L {e:0.4} {y:0.4}");
+                        let color = color_assigner.get_color(&record.q_name);
+                        let path = element::Path::new()
+                            .set("class", "query_track")
+                            .set("stroke", color)
+                            .set("stroke-width", "5")
+                            .set("opacity", opacity.track)
+                            .set("stroke-opacity", opacity.track)
+                            .set("d", path_str);
+                        group.append(path);
+
+                        q_offset += q_len_transformed;
+                    };
+                });
+
+                target_aln_block_records.4.iter().for_each(|record| {
+                    if !is_ribbon_drawn(record, &panel_options) {
+                        return;
+                    };
+
+                    let ts = record.ts as f64 * t_factor + t_offset;
+                    let te = record.te as f64 * t_factor + t_offset;
+
+                    let lane = q_offset_map.get(&record.q_name).unwrap();
+                    let q_factor = length_transform.position_factor(lane.q_len, max_t_len);
+                    let (qs, qe) = lane.get_ribbon_query_span(record);
+                    let qs = qs as f64 * q_factor + t_offset + lane.offset;
+                    let qe = qe as f64 * q_factor + t_offset + lane.offset;
+                    let ts = ts * scaling_factor;
+                    let te = te * scaling_factor;
+                    let qs = qs * scaling_factor;
+                    let qe = qe * scaling_factor;
+                    if is_double_dup(record, &panel_options) {
+                        group.append(get_double_dup_line(ts, te, qs, qe, 10.0, 90.0));
+                        return;
+                    };
+
+                    let color = color_assigner.get_color(&record.q_name);
+                    overview_ribbons
+                        .entry(t_name.clone())
+                        .or_default()
+                        .push(RibbonLayout {
+                            q_name: record.q_name.clone(),
+                            ts,
+                            te,
+                            qs,
+                            qe,
+                        });
+                    let path_str =
+                        format!("M {ts:0.4} 10 L {te:0.4} 10 L {qe:0.4} 90 L {qs:0.4} 90 Z");
+                    group.append(get_ribbon_path(
+                        record,
+                        color,
+                        path_str,
+                        &panel_options,
+                        0.5,
+                    ));
+                });
+                document.append(group);
+            });
+        // the bases are not in a linear scale with the transformed lengths
+        let scale_bar_len = scale_bar_len.filter(|_| length_transform == LengthTransform::Linear);
+        if let Some(scale_bar_len) = scale_bar_len {
+            document.append(get_scale_bar_group(
+                scale_bar_len,
+                scaling_factor,
+                0.0,
+                110.0,
+                6.0,
+                theme,
+                font_family,
+            ));
+        };
+    };
+
+    if (opts.ctg.is_empty() || opts.summary_only) && opts.overview {
+        plot_overview();
+    };
+
+    // per chromosome plot
+
+    let mut y_offset = if opts.ctg.is_empty() { 200.0 } else { 0.0 };
+    // the region is shifted to the left of the panel and the parts outside are hidden
+    let (view_box_x, overflow) = if let Some((_, r_bgn, r_end)) = opts.region.as_ref() {
+        (
+            *r_bgn as f64 * opts.panel_width / (r_end - r_bgn) as f64,
+            "hidden",
+        )
+    } else {
+        (0.0, "visible")
+    };
+
+    let no_alt_records = FxHashMap::<String, Vec<Cow<CtgMapRec>>>::default();
+    let (tgt_to_alt_qry_records, qry_to_alt_tgt_records) = if opts.alt_hits {
+        (&tgt_to_alt_qry_records, &qry_to_alt_tgt_records)
+    } else {
+        (&no_alt_records, &no_alt_records)
+    };
+
+    let progress_bar = if !opts.show_progress || opts.summary_only {
+        ProgressBar::hidden()
+    } else {
+        let total_records = target_aln_blocks.iter().map(|v| v.4.len() as u64).sum();
+        ProgressBar::new(total_records).with_style(
+            ProgressStyle::with_template(
+                "[{elapsed_precise}] {bar:40} {pos}/{len} records {msg} (eta {eta})",
+            )
+            .expect("invalid progress bar template"),
+        )
+    };
+    let mut chr_ribbons = FxHashMap::<String, (f64, f64, Vec<RibbonLayout>)>::default();
+    let mut chr_documents = Vec::<(String, Document)>::new();
+    // a per-chromosome panel with its label at `panel_y`
+    let get_sub_svg =
+        |t_name: &str, group: element::Group, tracks: Option<element::Group>, panel_y: f64| {
+            let chr_view_box = (view_box_x, -25, opts.panel_width, chr_panel_height);
+            let mut sub_svg = Document::new()
+                .set("viewBox", chr_view_box)
+                // the viewer script restores the initial view box on double-click
+                .set("data-initial-viewbox", chr_view_box)
+                .set("width", opts.panel_width)
+                .set("height", chr_panel_height)
+                .set("preserveAspectRatio", "none")
+                .set("y", panel_y)
+                .set("id", t_name)
+                .set("class", "chr_view")
+                .set("overflow", overflow);
+            sub_svg.append(group);
+            if let Some(tracks) = tracks {
+                sub_svg.append(tracks);
+            };
+            // the view box of the panel starts 25 units above the reference track layout
+            let label_y = panel_y + 25.0 + track_layout.ref_y;
+            let text = place_label(opts.track_labels, t_name, label_y, opts.panel_width)
+                .set("font-size", format!("{}px", opts.label_font_size))
+                .set("font-family", font_family)
+                .set("fill", theme.fg_color());
+            (sub_svg, text)
+        };
+
+    // the per-chromosome panels are generated in parallel and laid out in the order of the targets afterward
+    let chr_panels = if opts.summary_only {
+        vec![]
+    } else {
+        target_aln_blocks
+            .par_iter()
+            .map(|target_aln_block_record| {
+                let t_name = target_aln_block_record.1.clone();
+                let records = target_aln_block_record.4;
+                progress_bar.set_message(t_name.clone());
+                progress_bar.inc(records.len() as u64);
+                let max_records = opts
+                    .max_records_per_contig
+                    .filter(|&max_records| records.len() > max_records);
+                let warning = max_records.map(|max_records| {
+                    format!(
+                        "WARNING: {} drops {} alignment records (--max-records-per-contig {})",
+                        t_name,
+                        records.len() - max_records,
+                        max_records
+                    )
+                });
+                let capped_records =
+                    max_records.map(|max_records| cap_records(records, max_records));
+                let target_aln_block_record = &(
+                    target_aln_block_record.0,
+                    t_name.clone(),
+                    target_aln_block_record.2,
+                    target_aln_block_record.3,
+                    capped_records.as_ref().unwrap_or(records),
+                );
+                let t_len = target_aln_block_record.2;
+                let scaling_factor = get_chr_scaling_factor(scaling_factor, t_len, opts);
+                let scale_bar_len = if opts.auto_scale_chr {
+                    scale_bar_len.map(|_| get_scale_bar_length(t_len.max(1)))
+                } else {
+                    scale_bar_len
+                };
+                let panel_options = PanelOptions {
+                    scaling_factor,
+                    scale_bar_len,
+                    ..panel_options
+                };
+                let (group, ribbons) = match get_chr_svg_group(
+                    target_aln_block_record,
+                    &AssemblyRecords {
+                        tgt_to_alt_qry_records,
+                        qry_to_alt_tgt_records,
+                        ctg2tgt: &ctg2tgt,
+                        query_length: &query_length,
+                        color_assigner: &color_assigner,
+                    },
+                    &panel_options,
+                ) {
+                    Some(value) => value,
+                    None => return Ok((warning, None)),
+                };
+                let tracks = if multi_assembly_plot.len() > 0 {
+                    Some(multi_assembly_plot.render_tracks(
+                        target_aln_block_record.0,
+                        &t_name,
+                        target_aln_block_record.2,
+                        &panel_options,
+                        opts.alt_hits,
+                    ))
+                } else {
+                    None
+                };
+                if !opts.split_chr_panels {
+                    return Ok((
+                        warning,
+                        Some((t_name, scaling_factor, ribbons, Some((group, tracks)), None)),
+                    ));
+                };
+                let chr_width = if opts.region.is_some() {
+                    opts.panel_width
+                } else {
+                    get_chr_panel_width(target_aln_block_record, &query_length, scaling_factor)
+                        .max(opts.panel_width)
+                };
+                let (sub_svg, text) = get_sub_svg(&t_name, group, tracks, 0.0);
+                // keep the left margin for the label as in the stacked panels
+                let label_margin = match opts.track_labels {
+                    LabelPosition::Left => opts.panel_width * 0.05,
+                    _ => 0.0,
+                };
+                let chr_document = get_chr_document(
+                    sub_svg,
+                    text,
+                    chr_width,
+                    chr_panel_height,
+                    label_margin,
+                    theme,
+                    opts.highlight_inversions,
+                    opts.gap_min_length.is_some(),
+                    opts.external_css.as_deref(),
+                );
+                // the written documents are not kept, only one document per thread is in the memory
+                let chr_document = match write_chr_document {
+                    Some(write_chr_document) => {
+                        write_chr_document(&t_name, chr_document)?;
+                        None
+                    }
+                    None => Some(chr_document),
+                };
+                Ok((
+                    warning,
+                    Some((t_name, scaling_factor, ribbons, None, chr_document)),
+                ))
+            })
+            .collect::<Result<Vec<_>, PgrError>>()?
+    };
+    chr_panels.into_iter().for_each(|(warning, chr_panel)| {
+        messages.extend(warning);
+        if let Some((t_name, scaling_factor, ribbons, chr_panel, chr_document)) = chr_panel {
+            // the panel is at the top of its own document with `split_chr_panels`
+            let panel_y = if opts.split_chr_panels { 0.0 } else { y_offset };
+            chr_ribbons.insert(t_name.clone(), (panel_y, scaling_factor, ribbons));
+            if let Some((group, tracks)) = chr_panel {
+                let (sub_svg, text) = get_sub_svg(&t_name, group, tracks, panel_y);
+                document.append(text);
+                document.append(sub_svg);
+                y_offset += chr_panel_height;
+            };
+            if let Some(chr_document) = chr_document {
+                chr_documents.push((t_name, chr_document));
+            };
+        };
+    });
+
+    progress_bar.finish_and_clear();
+
+    if opts.legend {
+        let legend_q_names =
+            get_legend_contigs(&q_names, &q_aligned_bases, opts.legend_max_contigs);
+        if legend_q_names.len() < q_names.len() {
+            messages.push(format!(
+                "the legend lists {} of the {} query contigs by the aligned bases, see --legend-max-contigs",
+                legend_q_names.len(),
+                q_names.len()
+            ));
+        };
+        let legend_x = opts.panel_width * 1.85 + 10.0;
+        let legend_width = 200.0;
+        let legend_font_size = opts.label_font_size * 0.5;
+        let legend_height = legend_q_names.len() as f64 * legend_font_size * 1.4 + 10.0;
+        document.append(get_legend_group(
+            &legend_q_names,
+            &color_assigner,
+            theme,
+            font_family,
+            legend_font_size,
+            opacity,
+            legend_x,
+            -40.0,
+        ));
+
+        // expand the document to fit the legend, keep the original horizontal scale
+        let view_box_width = opts.panel_width * 0.95 * 2.0 + legend_width;
+        let view_box_height = svg_box_height.max(legend_height + 50.0);
+        document.assign(
+            "viewBox",
+            (
+                -opts.panel_width * 0.05,
+                view_box_y,
+                view_box_width,
+                view_box_height,
+            ),
+        );
+        document.assign("width", view_box_width / 0.95);
+        document.assign("height", view_box_height);
+    };
+
+    let targets = target_aln_blocks
+        .iter()
+        .zip(overview_t_offsets.iter())
+        .map(|((_, t_name, t_len, _, _), offset)| {
+            let (chr_panel_y, chr_scaling_factor, chr_ribbons) = match chr_ribbons.remove(t_name) {
+                Some((y, scaling_factor, ribbons)) => (Some(y), Some(scaling_factor), ribbons),
+                None => (None, None, vec![]),
+            };
+            TargetLayout {
+                t_name: t_name.clone(),
+                t_len: *t_len,
+                offset: *offset,
+                overview_ribbons: overview_ribbons.remove(t_name).unwrap_or_default(),
+                chr_panel_y,
+                chr_scaling_factor,
+                chr_ribbons,
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(CtgMapPlot {
+        document,
+        chr_documents,
+        layout: PlotLayout {
+            overview_scaling_factor,
+            targets,
+        },
+        messages,
+    })
+}
+
+/// the records drawn as the ribbons of the per-chromosome panels (the overview panel with `summary_only`) of
+/// `render_ctgmap` by the plotted targets in their order, they are bucketed, clipped to the region and capped
+/// by `max_records_per_contig` as in `render_ctgmap` without rebasing the query coordinates to the region
+pub fn get_drawn_records<'a>(
+    set: &'a CtgMapSet,
+    opts: &RenderOptions,
+) -> Vec<(String, Vec<Cow<'a, CtgMapRec>>)> {
+    let mut target_length = set.target_length.clone();
+    sort_targets(&mut target_length, opts.sort_chromosomes);
+    let ctg2tgt = assign_contigs_to_targets(&set.records);
+    let mut tgt_to_records = bucket_records(
+        &set.records,
+        &ctg2tgt,
+        opts.show_dups || opts.highlight_duplications,
+    )
+    .tgt_to_records;
+    if let Some((r_name, r_bgn, r_end)) = opts.region.as_ref() {
+        if let Some(records) = tgt_to_records.get_mut(r_name) {
+            *records = records
+                .iter()
+                .flat_map(|r| clip_record(r, *r_bgn, *r_end, true))
+                .map(Cow::Owned)
+                .collect::<Vec<_>>();
+        };
+    };
+    let panel_options = PanelOptions {
+        show_dups: opts.show_dups,
+        highlight_duplications: opts.highlight_duplications,
+        inversions_only: opts.inversions_only,
+        ..PanelOptions::default()
+    };
+    target_length
+        .iter()
+        .filter(|(_, t_name, _)| {
+            opts.ctg.is_empty() || opts.summary_only || opts.ctg.contains(t_name)
+        })
+        .flat_map(|(_, t_name, _)| {
+            let records = tgt_to_records.remove(t_name).filter(|v| !v.is_empty())?;
+            let records = match opts.max_records_per_contig {
+                Some(max_records) if !opts.summary_only && records.len() > max_records => {
+                    cap_records(&records, max_records)
+                }
+                _ => records,
+            };
+            let records = records
+                .into_iter()
+                .filter(|r| is_ribbon_drawn(r, &panel_options) && !is_double_dup(r, &panel_options))
+                .collect::<Vec<_>>();
+            Some((t_name.clone(), records))
+        })
+        .collect::<Vec<_>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_record(t_name: &str, ts: u32, te: u32, q_name: &str, qs: u32, qe: u32) -> CtgMapRec {
+        CtgMapRec {
+            t_name: t_name.to_string(),
+            ts,
+            te,
+            q_name: q_name.to_string(),
+            qs,
+            qe,
+            ctg_len: 1_000_000,
+            orientation: 0,
+            ctg_orientation: 0,
+            t_dup: false,
+            t_ovlp: false,
+            q_dup: false,
+            q_ovlp: false,
+            identity: None,
+        }
+    }
+
+    /// draw the panel of chr1 with the records, the query contigs are colored in the order of `query_length`
+    fn get_test_panel(
+        records: &[CtgMapRec],
+        t_len: u32,
+        query_length: &[(&str, u32)],
+        opts: PanelOptions,
+    ) -> (String, Vec<RibbonLayout>) {
+        let q_names = query_length
+            .iter()
+            .map(|(q_name, _)| q_name.to_string())
+            .collect::<Vec<_>>();
+        let query_length = query_length
+            .iter()
+            .map(|(q_name, q_len)| (q_name.to_string(), *q_len))
+            .collect::<FxHashMap<_, _>>();
+        let (group, ribbons) = get_chr_svg_group(
+            &(0, "chr1".to_string(), t_len, 0.0, &records.to_vec()),
+            &AssemblyRecords {
+                tgt_to_alt_qry_records: &FxHashMap::default(),
+                qry_to_alt_tgt_records: &FxHashMap::default(),
+                ctg2tgt: &FxHashMap::default(),
+                query_length: &query_length,
+                color_assigner: &ColorAssigner::new(&q_names, &CMAP),
+            },
+            &opts,
+        )
+        .unwrap();
+        (group.to_string(), ribbons)
+    }
+
+    #[test]
+    fn test_natural_sort_key() {
+        let mut t_names = vec!["chrM", "chr10", "chrY", "chr2", "chrUn_1", "chrX", "chr1"];
+        t_names.sort_by_key(|t_name| natural_sort_key(t_name));
+        assert_eq!(
+            t_names,
+            vec!["chr1", "chr2", "chr10", "chrM", "chrUn_1", "chrX", "chrY"]
+        );
+
+        let mut t_names = vec![
+            "scaffold_10",
+            "scaffold_2",
+            "contig_1_10",
+            "contig_1_9",
+            "HG002#2#chr1",
+            "HG002#1#chr10",
+            "HG002#1#chr2",
+            "scaffold",
+        ];
+        t_names.sort_by_key(|t_name| natural_sort_key(t_name));
+        assert_eq!(
+            t_names,
+            vec![
+                "HG002#1#chr2",
+                "HG002#1#chr10",
+                "HG002#2#chr1",
+                "contig_1_9",
+                "contig_1_10",
+                "scaffold",
+                "scaffold_2",
+                "scaffold_10"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_color_assigner() {
+        let q_names = (0..97).map(|i| format!("ctg{i}")).collect::<Vec<_>>();
+        let color_assigner = ColorAssigner::new(&q_names, &CMAP);
+        q_names.windows(2).for_each(|w| {
+            assert_ne!(
+                color_assigner.get_color(&w[0]),
+                color_assigner.get_color(&w[1])
+            );
+        });
+        let color_assigner2 = ColorAssigner::new(&q_names, &CMAP);
+        q_names.iter().for_each(|q_name| {
+            assert_eq!(
+                color_assigner.get_color(q_name),
+                color_assigner2.get_color(q_name)
+            );
+        });
+    }
+
+    #[test]
+    fn test_calculate_hash() {
+        // the FNV-1a values are fixed, so the colors are the same in every process and on every platform
+        assert_eq!(calculate_hash(""), 0xcbf29ce484222325);
+        assert_eq!(calculate_hash("chr1"), 17496414806553445919);
+        assert_ne!(calculate_hash("chr1"), calculate_hash("chr2"));
+    }
+
+    #[test]
+    fn test_tick_group() {
+        let group =
+            get_tick_group(25_000_000, 1e7, 1e-5, 2.0, Theme::Light, "monospace").to_string();
+        assert_eq!(group.matches("<text").count(), 2);
+        assert!(group.contains("\n10\n</text>"));
+        assert!(group.contains("\n20\n</text>"));
+
+        let group =
+            get_tick_group(1_000_000, 3e5, 1e-5, 2.0, Theme::Light, "monospace").to_string();
+        assert!(group.contains("\n0.3\n</text>"));
+        assert!(group.contains("\n0.9\n</text>"));
+    }
+
+    #[test]
+    fn test_show_dups() {
+        let records = vec![
+            test_record("chr1", 0, 1000, "ctg1", 0, 1000),
+            CtgMapRec {
+                t_dup: true,
+                q_dup: true,
+                ..test_record("chr1", 200, 800, "ctg1", 2000, 2600)
+            },
+        ];
+        let get_ribbons = |show_dups| {
+            let (svg_string, ribbons) = get_test_panel(
+                &records,
+                1_000_000,
+                &[("ctg1", 1_000_000)],
+                PanelOptions {
+                    scaling_factor: 0.001,
+                    show_dups,
+                    ..PanelOptions::default()
+                },
+            );
+            (svg_string, ribbons.len())
+        };
+        let (svg_string, ribbon_count) = get_ribbons(false);
+        assert_eq!(ribbon_count, 1);
+        assert!(!svg_string.contains("stroke-dasharray"));
+        let (svg_string, ribbon_count) = get_ribbons(true);
+        assert_eq!(ribbon_count, 2);
+        assert_eq!(svg_string.matches("stroke-dasharray").count(), 1);
+    }
+
+    #[test]
+    fn test_highlight_duplications() {
+        let records = vec![
+            CtgMapRec {
+                t_dup: true,
+                ..test_record("chr1", 0, 1000, "ctg1", 0, 1000)
+            },
+            CtgMapRec {
+                q_dup: true,
+                ..test_record("chr1", 1000, 2000, "ctg1", 1000, 2000)
+            },
+            CtgMapRec {
+                t_dup: true,
+                q_dup: true,
+                ..test_record("chr1", 200, 800, "ctg1", 2000, 2600)
+            },
+        ];
+        let get_ribbons = |highlight_duplications| {
+            let (svg_string, ribbons) = get_test_panel(
+                &records,
+                1_000_000,
+                &[("ctg1", 1_000_000)],
+                PanelOptions {
+                    scaling_factor: 0.001,
+                    highlight_duplications,
+                    ..PanelOptions::default()
+                },
+            );
+            (svg_string, ribbons.len())
+        };
+        let (svg_string, ribbon_count) = get_ribbons(false);
+        assert_eq!(ribbon_count, 2);
+        assert!(!svg_string.contains(DUP_TARGET_COLOR));
+        assert!(!svg_string.contains("double_dup"));
+        let (svg_string, ribbon_count) = get_ribbons(true);
+        // the double-dup block is a line, not a ribbon
+        assert_eq!(ribbon_count, 2);
+        assert!(svg_string.contains(&format!("fill=\"{}\"", DUP_TARGET_COLOR)));
+        assert!(svg_string.contains(&format!("fill=\"{}\"", DUP_QUERY_COLOR)));
+        assert!(svg_string.contains("M 0.5000 14.0000 L 2.3000 88.0000"));
+    }
+
+    #[test]
+    fn test_highlight_inversions() {
+        let records = vec![
+            test_record("chr1", 0, 1000, "ctg1", 0, 1000),
+            CtgMapRec {
+                orientation: 1,
+                ..test_record("chr1", 2000, 2100, "ctg1", 2000, 2100)
+            },
+        ];
+        let get_svg_string = |highlight_inversions| {
+            get_test_panel(
+                &records,
+                1_000_000,
+                &[("ctg1", 1_000_000)],
+                PanelOptions {
+                    scaling_factor: 0.001,
+                    highlight_inversions,
+                    ..PanelOptions::default()
+                },
+            )
+            .0
+        };
+        assert!(!get_svg_string(false).contains("inversion_hatch"));
+        let svg_string = get_svg_string(true);
+        // only the inverted ribbon is hatched
+        assert_eq!(svg_string.matches("url(#inversion_hatch)").count(), 1);
+        assert_eq!(
+            svg_string
+                .matches(&format!("stroke=\"{}\"", INVERSION_COLOR))
+                .count(),
+            1
+        );
+
+        let defs = create_inversion_pattern().to_string();
+        assert!(defs.contains("<pattern"));
+        assert!(defs.contains("id=\"inversion_hatch\""));
+        assert!(defs.contains("patternUnits=\"objectBoundingBox\""));
+    }
+
+    #[test]
+    fn test_shade_reverse_strand() {
+        assert_eq!(desaturate_color("#ff0000", 1.0), "#ff0000");
+        assert_eq!(desaturate_color("#ff0000", 0.0), "#4c4c4c");
+        assert_eq!(desaturate_color("#ff0000", 0.5), "#a62626");
+        assert_eq!(desaturate_color("#808080", 0.3), "#808080");
+        assert_eq!(desaturate_color("red", 0.3), "red");
+
+        let forward = test_record("chr1", 0, 1000, "ctg1", 0, 1000);
+        let reverse = CtgMapRec {
+            orientation: 1,
+            ..test_record("chr1", 2000, 2100, "ctg1", 2000, 2100)
+        };
+        assert_eq!(get_ribbon_fill("#ff0000", &forward, true), "#ff0000");
+        assert_eq!(get_ribbon_fill("#ff0000", &reverse, false), "#ff0000");
+        assert_eq!(
+            get_ribbon_fill("#ff0000", &reverse, true),
+            desaturate_color("#ff0000", 0.3)
+        );
+    }
+
+    #[test]
+    fn test_identity_shade() {
+        assert_eq!(lighten_color("#ff0000", 0.0), "#ff0000");
+        assert_eq!(lighten_color("#ff0000", 0.5), "#ff8080");
+        assert_eq!(lighten_color("red", 0.5), "red");
+        assert_eq!(get_identity_shade(1.0), 0.0);
+        assert_eq!(get_identity_shade(0.5), 0.7);
+        assert!((get_identity_shade(0.95) - 0.35).abs() < 1e-6);
+
+        let record = test_record("chr1", 0, 1000, "ctg1", 0, 1000);
+        assert_eq!(get_ribbon_fill("#ff0000", &record, false), "#ff0000");
+        // darker for the higher identity
+        let fill = |identity| {
+            let record = CtgMapRec {
+                identity: Some(identity),
+                ..record.clone()
+            };
+            get_ribbon_fill("#ff0000", &record, false)
+        };
+        assert_eq!(fill(1.0), "#ff0000");
+        assert_eq!(fill(0.8), lighten_color("#ff0000", 0.7));
+        assert!(fill(0.99) < fill(0.95));
+    }
+
+    #[test]
+    fn test_dark_theme_cytoband() {
+        let records = vec![test_record("chr1", 0, 1000, "ctg1", 0, 1000)];
+        let cytobands = Some(CytoBands {
+            cytobands: [(
+                "chr1".to_string(),
+                vec![
+                    (0, 500_000, "p11".to_string(), "gpos100".to_string()),
+                    (500_000, 1_000_000, "q11".to_string(), "gneg".to_string()),
+                ],
+            )]
+            .into_iter()
+            .collect(),
+        });
+        let get_svg_string = |theme| {
+            get_test_panel(
+                &records,
+                1_000_000,
+                &[("ctg1", 1_000_000)],
+                PanelOptions {
+                    scaling_factor: 0.001,
+                    theme,
+                    cytobands: cytobands.as_ref(),
+                    ..PanelOptions::default()
+                },
+            )
+            .0
+        };
+        let svg_string = get_svg_string(Theme::Light);
+        assert!(svg_string.contains("stroke=\"#000\""));
+        let svg_string = get_svg_string(Theme::Dark);
+        assert!(svg_string.contains("stroke=\"#DDD\""));
+        assert!(svg_string.contains("stroke=\"#555\""));
+        assert!(!svg_string.contains("stroke=\"#000\""));
+    }
+
+    #[test]
+    fn test_cytoband_outline() {
+        assert_eq!(
+            get_cytoband_outline(10.0, 20.0, 6.0, "p11.1", "acen", false, false).unwrap(),
+            "M 10.0000 2.0000 L 20.0000 5.0000 L 20.0000 7.0000 L 10.0000 10.0000 Z"
+        );
+        assert_eq!(
+            get_cytoband_outline(20.0, 30.0, 6.0, "q11", "acen", false, false).unwrap(),
+            "M 20.0000 5.0000 L 30.0000 2.0000 L 30.0000 10.0000 L 20.0000 7.0000 Z"
+        );
+        assert!(
+            get_cytoband_outline(0.0, 10.0, 6.0, "", "acen", false, false)
+                .unwrap()
+                .contains("L 5.0000 5.0000")
+        );
+        assert_eq!(
+            get_cytoband_outline(10.0, 20.0, 6.0, "p21", "gneg", false, false),
+            None
+        );
+        // the telomere caps
+        assert_eq!(
+            get_cytoband_outline(0.0, 10.0, 6.0, "p36", "gneg", true, false).unwrap(),
+            "M 4.0000 2.0000 L 10.0000 2.0000 L 10.0000 10.0000 \
+             L 4.0000 10.0000 A 4.0000 4 0 0 1 4.0000 2.0000 Z"
+        );
+        assert_eq!(
+            get_cytoband_outline(90.0, 92.0, 6.0, "q44", "gneg", false, true).unwrap(),
+            "M 90.0000 2.0000 L 90.0000 2.0000 A 2.0000 4 0 0 1 90.0000 10.0000 \
+             L 90.0000 10.0000 Z"
+        );
+        assert!(get_cytoband_outline(0.0, 6.0, 6.0, "", "gvar", true, true)
+            .unwrap()
+            .contains("A 3.0000 4 0 0 1 3.0000 10.0000"));
+    }
+
+    #[test]
+    fn test_cytoband_txt() {
+        let cytoband_txt = "\
+chr1\t0\t2300000\tp36.33\tgneg
+chr1\t2300000\t5300000\tp36.32\tgpos25
+chr1\t121700000\t125100000\tp11.1\tacen
+chr2\t0\t4400000\tp25.3\tgneg
+chrM\t0\t16569\t\tgvar
+";
+        let cytobands = CytoBands::from_ucsc_txt(io::Cursor::new(cytoband_txt)).unwrap();
+        assert_eq!(cytobands.cytobands.len(), 3);
+        assert_eq!(
+            cytobands.cytobands["chr1"][1],
+            (2300000, 5300000, "p36.32".to_string(), "gpos25".to_string())
+        );
+        assert_eq!(cytobands.cytobands["chrM"][0].2, "");
+        assert!(CytoBands::from_ucsc_txt(io::Cursor::new("chr1\t0\t100\n")).is_err());
+        assert!(CytoBands::from_ucsc_txt(io::Cursor::new("chr1\t0\tx\tp1\tgneg\n")).is_err());
+
+        assert_eq!(Theme::Light.cytoband_color("gpos100"), "#000");
+        assert_eq!(Theme::Light.cytoband_color("gpos50"), "#555");
+        assert_eq!(Theme::Dark.cytoband_color("gpos75"), "#BBB");
+        assert_eq!(Theme::Light.cytoband_color("gneg"), "#AAA");
+        assert_eq!(Theme::Dark.cytoband_color("acen"), "#FF0");
+        assert_eq!(Theme::Light.cytoband_color("stalk"), "#69C");
+    }
+
+    #[test]
+    fn test_query_annotation() {
+        let records = vec![
+            CtgMapRec {
+                orientation: 1,
+                ctg_orientation: 1,
+                ..test_record("chr1", 0, 1000, "ctg1", 0, 1000)
+            },
+            test_record("chr1", 2000, 3000, "ctg2", 0, 1000),
+        ];
+        let qry_highlight = Some(
+            [
+                ("ctg1".to_string(), vec![(0, 100)]),
+                ("ctg2".to_string(), vec![(0, 100)]),
+            ]
+            .into_iter()
+            .collect::<FxHashMap<_, _>>(),
+        );
+        let (svg_string, _) = get_test_panel(
+            &records,
+            1_000_000,
+            &[("ctg1", 1000), ("ctg2", 1000)],
+            PanelOptions {
+                scaling_factor: 0.001,
+                query_annotations: qry_highlight.as_ref(),
+                ..PanelOptions::default()
+            },
+        );
+        assert_eq!(svg_string.matches("stroke=\"#00F\"").count(), 2);
+        // ctg1 is reversed, its first 100 bases are at the end of its track
+        assert!(svg_string.contains("M 0.9000 105.0000 L 1.0000 105.0000"));
+        // ctg2 follows ctg1 on the query track
+        assert!(svg_string.contains("M 1.0000 105.0000 L 1.1000 105.0000"));
+    }
+
+    #[test]
+    fn test_track_layout() {
+        assert_eq!(TrackLayout::new(8.0, 74.0), TrackLayout::default());
+        assert_eq!(TrackLayout::default().panel_height(), 130.0);
+        let track_layout = TrackLayout::new(16.0, 150.0);
+        assert_eq!(track_layout.alt_tgt_y, 22.0);
+        assert_eq!(track_layout.trapezoid_bottom_y, 172.0);
+        assert_eq!(track_layout.query_y, 183.0);
+        assert_eq!(track_layout.alt_qry_y, 201.0);
+
+        let records = vec![test_record("chr1", 0, 1000, "ctg1", 0, 1000)];
+        let (svg_string, _) = get_test_panel(
+            &records,
+            1000,
+            &[("ctg1", 1000)],
+            PanelOptions {
+                scaling_factor: 0.1,
+                track_layout,
+                ..PanelOptions::default()
+            },
+        );
+        assert!(svg_string.contains("M 0.0000 183.0000 L 100.0000 183.0000"));
+        assert!(svg_string.contains(
+            "M 0.0000 22.0000 L 100.0000 22.0000 L 100.0000 172.0000 L 0.0000 172.0000 Z"
+        ));
+    }
+
+    #[test]
+    fn test_query_order() {
+        let records = [
+            test_record("chr1", 0, 1000, "ctg1", 0, 1000),
+            test_record("chr1", 2000, 3000, "ctg2", 0, 1000),
+            test_record("chr1", 4000, 5000, "ctg3", 0, 1000),
+        ];
+        let mut best_query_block = records.iter().collect::<Vec<_>>();
+        sort_query_blocks(
+            &mut best_query_block,
+            &records,
+            &FxHashMap::default(),
+            QuerySort::Position,
+            &FxHashMap::default(),
+        );
+        let q_names = best_query_block
+            .iter()
+            .map(|r| r.q_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(q_names, vec!["ctg1", "ctg2", "ctg3"]);
+
+        // ctg1 is not listed and it is put after the listed ones
+        let query_order = [("ctg3".to_string(), 0), ("ctg2".to_string(), 1)]
+            .into_iter()
+            .collect::<FxHashMap<_, _>>();
+        sort_query_blocks(
+            &mut best_query_block,
+            &records,
+            &query_order,
+            QuerySort::Position,
+            &FxHashMap::default(),
+        );
+        let q_names = best_query_block
+            .iter()
+            .map(|r| r.q_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(q_names, vec!["ctg3", "ctg2", "ctg1"]);
+
+        // ctg3 is the longest and ctg1 / ctg2 are the same length, the tie is broken by the position
+        let query_length = [
+            ("ctg1".to_string(), 1000),
+            ("ctg2".to_string(), 1000),
+            ("ctg3".to_string(), 5000),
+        ]
+        .into_iter()
+        .collect::<FxHashMap<_, _>>();
+        let get_q_names = |query_order: &FxHashMap<String, usize>, query_sort| {
+            let mut best_query_block = records.iter().rev().collect::<Vec<_>>();
+            sort_query_blocks(
+                &mut best_query_block,
+                &records,
+                query_order,
+                query_sort,
+                &query_length,
+            );
+            best_query_block
+                .iter()
+                .map(|r| r.q_name.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            get_q_names(&FxHashMap::default(), QuerySort::Length),
+            vec!["ctg3", "ctg1", "ctg2"]
+        );
+        assert_eq!(
+            get_q_names(&FxHashMap::default(), QuerySort::Name),
+            vec!["ctg1", "ctg2", "ctg3"]
+        );
+        // the listed contigs still go first
+        let query_order = [("ctg2".to_string(), 0)]
+            .into_iter()
+            .collect::<FxHashMap<_, _>>();
+        assert_eq!(
+            get_q_names(&query_order, QuerySort::Length),
+            vec!["ctg2", "ctg3", "ctg1"]
+        );
+
+        // ctg1 has its longest block first but most of its blocks after ctg2
+        let records = [
+            test_record("chr1", 0, 3000, "ctg1", 0, 3000),
+            test_record("chr1", 6000, 6100, "ctg1", 3000, 3100),
+            test_record("chr1", 7000, 7100, "ctg1", 3100, 3200),
+            test_record("chr1", 4000, 5000, "ctg2", 0, 1000),
+        ];
+        let mut best_query_block = vec![&records[3], &records[0]];
+        sort_query_blocks(
+            &mut best_query_block,
+            &records,
+            &FxHashMap::default(),
+            QuerySort::MedianPosition,
+            &query_length,
+        );
+        assert_eq!(
+            best_query_block
+                .iter()
+                .map(|r| r.q_name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["ctg2", "ctg1"]
+        );
+        assert_eq!(get_median_target_positions(&records)["ctg1"], 6050);
+    }
+
+    #[test]
+    fn test_flip_query() {
+        let records = vec![test_record("chr1", 0, 500, "ctg1", 0, 500)];
+        let get_svg_string = |flip_query| {
+            get_test_panel(
+                &records,
+                1000,
+                &[("ctg1", 1000)],
+                PanelOptions {
+                    scaling_factor: 0.1,
+                    flip_query,
+                    ..PanelOptions::default()
+                },
+            )
+            .0
+        };
+        let svg_string = get_svg_string(false);
+        assert!(svg_string
+            .contains("M 0.0000 14.0000 L 50.0000 14.0000 L 50.0000 88.0000 L 0.0000 88.0000 Z"));
+        // the first half of the contig is drawn at the right end of the query track, twisted
+        let svg_string = get_svg_string(true);
+        assert!(svg_string
+            .contains("M 0.0000 14.0000 L 50.0000 14.0000 L 50.0000 88.0000 L 100.0000 88.0000 Z"));
+        assert!(svg_string.contains("M 0.0000 95.0000 L 100.0000 95.0000"));
+    }
+
+    #[test]
+    fn test_query_lanes() {
+        // ctg1 spans two regions of chr1 with ctg2 aligned between them
+        let records = vec![
+            test_record("chr1", 0, 1000, "ctg1", 0, 1000),
+            test_record("chr1", 1000, 2000, "ctg2", 0, 1000),
+            test_record("chr1", 3000, 3500, "ctg1", 1000, 1500),
+        ];
+        let get_ribbons = |records: &Vec<CtgMapRec>, flip_query| {
+            let (_, ribbons) = get_test_panel(
+                records,
+                4000,
+                &[("ctg1", 1500), ("ctg2", 1000)],
+                PanelOptions {
+                    flip_query,
+                    ..PanelOptions::default()
+                },
+            );
+            ribbons
+                .into_iter()
+                .map(|r| (r.q_name, r.qs, r.qe))
+                .collect::<Vec<_>>()
+        };
+        // the lanes: ctg1 at 0-1500 and ctg2 at 1500-2500
+        assert_eq!(
+            get_ribbons(&records, false),
+            vec![
+                ("ctg1".to_string(), 0.0, 1000.0),
+                ("ctg2".to_string(), 1500.0, 2500.0),
+                ("ctg1".to_string(), 1000.0, 1500.0),
+            ]
+        );
+        let flipped = vec![
+            ("ctg1".to_string(), 1500.0, 500.0),
+            ("ctg2".to_string(), 2500.0, 1500.0),
+            ("ctg1".to_string(), 500.0, 0.0),
+        ];
+        assert_eq!(get_ribbons(&records, true), flipped);
+
+        // a block with another ctg_orientation is still mirrored with the lane it goes to
+        let mut records = records;
+        records[2].ctg_orientation = 1;
+        assert_eq!(get_ribbons(&records, true), flipped);
+    }
+
+    #[test]
+    fn test_gap_markers() {
+        let mut group = element::Group::new();
+        draw_gap_markers(
+            &mut group,
+            100_000,
+            &[(0, 20_000), (25_000, 60_000)],
+            0.01,
+            50.0,
+            10_000,
+        );
+        let group = group.to_string();
+        // the 5000 bases gap is too short
+        assert_eq!(group.matches("<rect").count(), 1);
+        assert!(group.contains("x=\"600.0000\""));
+        assert!(group.contains("width=\"400.0000\""));
+        assert!(group.contains("y=\"40.0000\""));
+        assert!(group.contains("no alignment 60000-100000, 40.0 kbp"));
+
+        let mut group = element::Group::new();
+        draw_gap_markers(&mut group, 1000, &[(0, 1000)], 0.1, 0.0, 0);
+        assert!(!group.to_string().contains("<rect"));
+    }
+
+    #[test]
+    fn test_grid_lines() {
+        let mut group = element::Group::new();
+        draw_grid_lines(&mut group, 800.0, &[6.0, 95.0], Theme::Light);
+        let group = group.to_string();
+        assert_eq!(group.matches("class=\"grid\"").count(), 2);
+        assert!(group.contains("M 0 6.0000 L 800.0000 6.0000"));
+        assert!(group.contains("M 0 95.0000 L 800.0000 95.0000"));
+    }
+
+    #[test]
+    fn test_coverage_bins() {
+        assert_eq!(
+            get_coverage_bins(&[(0, 100), (50, 150), (300, 400)], 400, 4),
+            vec![
+                (0, 100, 1.5),
+                (100, 200, 0.5),
+                (200, 300, 0.0),
+                (300, 400, 1.0)
+            ]
+        );
+        // the last bin is shorter, the intervals past the target are clipped
+        assert_eq!(
+            get_coverage_bins(&[(501, 1200)], 1000, 3),
+            vec![(0, 334, 0.0), (334, 668, 0.5), (668, 1000, 1.0)]
+        );
+        assert!(get_coverage_bins(&[(0, 10)], 0, 3).is_empty());
+
+        let records = vec![
+            test_record("chr1", 0, 500, "ctg1", 0, 500),
+            test_record("chr1", 0, 250, "ctg2", 0, 250),
+        ];
+        let get_svg_string = |coverage_bins| {
+            get_test_panel(
+                &records,
+                1000,
+                &[("ctg1", 500), ("ctg2", 250)],
+                PanelOptions {
+                    scaling_factor: 0.1,
+                    coverage_bins,
+                    ..PanelOptions::default()
+                },
+            )
+            .0
+        };
+        assert!(!get_svg_string(None).contains("coverage"));
+        let svg_string = get_svg_string(Some(4));
+        // the empty bins are not drawn
+        assert_eq!(svg_string.matches("<rect").count(), 2);
+        assert!(svg_string.contains("coverage chr1:0-250: 2.00"));
+        assert!(svg_string.contains("opacity=\"0.5000\""));
+    }
+
+    #[test]
+    fn test_ref_annotation_tracks() {
+        let records = vec![test_record("chr1", 0, 1000, "ctg1", 0, 1000)];
+        let get_track = |label: &str, bgn, end| RefAnnotationTrack {
+            label: label.to_string(),
+            regions: [("chr1".to_string(), vec![(bgn, end, "#F00")])]
+                .into_iter()
+                .collect(),
+        };
+        let ref_highlight = vec![get_track("genes", 0, 100), get_track("repeats", 200, 300)];
+        let (svg_string, _) = get_test_panel(
+            &records,
+            1000,
+            &[("ctg1", 1000)],
+            PanelOptions {
+                scaling_factor: 0.1,
+                ref_annotation_tracks: &ref_highlight,
+                ..PanelOptions::default()
+            },
+        );
+        // the second lane is stacked above the first one
+        assert!(svg_string.contains("M 0.0000 -2.0000 L 10.0000 -2.0000"));
+        assert!(svg_string.contains("M 20.0000 -9.0000 L 30.0000 -9.0000"));
+        assert!(svg_string.contains("<title>genes: 0-100</title>"));
+        assert!(svg_string.contains("<title>repeats: 200-300</title>"));
+    }
+
+    #[test]
+    fn test_telomeres() {
+        let records = vec![test_record("chr1", 0, 1000, "ctg1", 0, 1000)];
+        let telomeres = Some(
+            [
+                ("chr1".to_string(), vec![(0, 100), (900, 1000), (900, 2000)]),
+                ("chr2".to_string(), vec![(0, 100)]),
+            ]
+            .into_iter()
+            .collect::<FxHashMap<_, _>>(),
+        );
+        let (svg_string, _) = get_test_panel(
+            &records,
+            1000,
+            &[("ctg1", 1000)],
+            PanelOptions {
+                scaling_factor: 0.1,
+                telomeres: telomeres.as_ref(),
+                ..PanelOptions::default()
+            },
+        );
+        // the chr2 telomere and the one beyond the end of chr1 are not drawn
+        assert_eq!(svg_string.matches("<polygon").count(), 2);
+        assert!(svg_string.contains("points=\"1.0000,-6.0000 9.0000,-6.0000 5.0000,2.0000\""));
+        assert!(svg_string.contains("<title>telomere: 900-1000</title>"));
+    }
+
+    #[test]
+    fn test_inversions_only() {
+        let mut inverted_record = test_record("chr1", 500, 1000, "ctg1", 500, 1000);
+        inverted_record.orientation = 1;
+        let records = vec![test_record("chr1", 0, 500, "ctg1", 0, 500), inverted_record];
+        let get_ribbons = |inversions_only| {
+            let (svg_string, ribbons) = get_test_panel(
+                &records,
+                1000,
+                &[("ctg1", 1000)],
+                PanelOptions {
+                    scaling_factor: 0.1,
+                    inversions_only,
+                    ..PanelOptions::default()
+                },
+            );
+            (svg_string, ribbons)
+        };
+        let (_, ribbons) = get_ribbons(false);
+        assert_eq!(ribbons.len(), 2);
+        let (svg_string, ribbons) = get_ribbons(true);
+        assert_eq!(ribbons.len(), 1);
+        assert_eq!(ribbons[0].ts, 50.0);
+        // the query track is kept
+        assert!(svg_string.contains("M 0.0000 95.0000 L 100.0000 95.0000"));
+    }
+
+    #[test]
+    fn test_render_ctgmap() {
+        let set = CtgMapSet {
+            records: vec![
+                test_record("chr10", 0, 1000, "ctg2", 0, 1000),
+                test_record("chr2", 0, 1000, "ctg1", 0, 1000),
+                test_record("chr2", 2000, 2500, "ctg1", 2000, 2500),
+                // an alternative hit of ctg1, drawn on the alternative hit tracks
+                test_record("chr10", 5000, 5400, "ctg1", 5000, 5400),
+            ],
+            target_length: vec![
+                (0, "chr10".to_string(), 10_000),
+                (1, "chr2".to_string(), 10_000),
+                (2, "chr3".to_string(), 10_000),
+            ],
+            query_length: vec![(0, "ctg1".to_string(), 6000), (1, "ctg2".to_string(), 1000)],
+        };
+        let opts = RenderOptions {
+            overview: false,
+            ..RenderOptions::default()
+        };
+        let svg_string = render_ctgmap(&set, &opts).unwrap().document.to_string();
+        // chr3 has no record and no panel, chr2 comes before chr10
+        assert_eq!(svg_string.matches("class=\"chr_view\"").count(), 2);
+        assert!(svg_string.find("id=\"chr2\"").unwrap() < svg_string.find("id=\"chr10\"").unwrap());
+        assert_eq!(svg_string.matches("class=\"ribbon\"").count(), 3);
+        assert!(svg_string.contains("chr10 to chr2 with ctg1:5000-5400"));
+
+        let plot = render_ctgmap(&set, &RenderOptions::default()).unwrap();
+        // the overview panel draws the ribbons again
+        assert_eq!(
+            plot.document
+                .to_string()
+                .matches("class=\"ribbon\"")
+                .count(),
+            6
+        );
+        assert!(plot.messages.is_empty());
+        let t_names = plot
+            .layout
+            .targets
+            .iter()
+            .map(|t| t.t_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(t_names, vec!["chr2", "chr10"]);
+        // the 10 kbp of chr2 and the padding
+        assert_eq!(plot.layout.targets[1].offset, 1_510_000.0);
+        assert_eq!(plot.layout.targets[0].overview_ribbons.len(), 2);
+        assert_eq!(plot.layout.targets[0].chr_panel_y, Some(200.0));
+        assert_eq!(plot.layout.targets[1].chr_panel_y, Some(330.0));
+
+        let opts = RenderOptions {
+            split_chr_panels: true,
+            ctg: vec!["chr10".to_string(), "chr4".to_string()],
+            ..RenderOptions::default()
+        };
+        let plot = render_ctgmap(&set, &opts).unwrap();
+        assert!(!plot.document.to_string().contains("chr_view"));
+        assert_eq!(plot.chr_documents.len(), 1);
+        assert_eq!(plot.chr_documents[0].0, "chr10");
+        assert_eq!(
+            plot.messages,
+            vec!["WARNING: --ctg chr4 is not found in the target_length"]
+        );
+
+        // the documents go to the writer instead of the plot
+        let opts = RenderOptions {
+            split_chr_panels: true,
+            ..RenderOptions::default()
+        };
+        let written = std::sync::Mutex::new(Vec::<String>::new());
+        let plot = render_ctgmap_with_writer(&set, &opts, &|t_name, chr_document| {
+            assert!(chr_document.to_string().contains("chr_view"));
+            written.lock().unwrap().push(t_name.to_string());
+            Ok(())
+        })
+        .unwrap();
+        assert!(plot.chr_documents.is_empty());
+        let mut written = written.into_inner().unwrap();
+        written.sort();
+        assert_eq!(written, vec!["chr10", "chr2"]);
+        assert!(matches!(
+            render_ctgmap_with_writer(&set, &opts, &|_, _| Err(PgrError::InvalidArgument(
+                "fail to write".to_string()
+            ))),
+            Err(PgrError::InvalidArgument(_))
+        ));
+
+        let opts = RenderOptions {
+            total_target_bases: Some(1000.0),
+            strict: true,
+            ..RenderOptions::default()
+        };
+        assert!(matches!(
+            render_ctgmap(&set, &opts),
+            Err(PgrError::InvalidArgument(_))
+        ));
+
+        // the gap markers are opt-in
+        let svg_string = render_ctgmap(&set, &RenderOptions::default())
+            .unwrap()
+            .document
+            .to_string();
+        assert!(!svg_string.contains("class=\"gap\""));
+        assert!(!svg_string.contains("gap_hatch"));
+        let opts = RenderOptions {
+            ruler: false,
+            gap_min_length: Some(1000),
+            ..RenderOptions::default()
+        };
+        let svg_string = render_ctgmap(&set, &opts).unwrap().document.to_string();
+        assert!(svg_string.contains("class=\"gap\""));
+        let empty_set = CtgMapSet {
+            records: vec![],
+            target_length: vec![],
+            query_length: vec![],
+        };
+        assert!(!render_ctgmap(&empty_set, &opts)
+            .unwrap()
+            .document
+            .to_string()
+            .contains("chr_view"));
+    }
+
+    #[test]
+    fn test_bucket_records() {
+        let records = vec![
+            test_record("chr1", 0, 1000, "ctg1", 0, 1000),
+            test_record("chr2", 0, 300, "ctg1", 1000, 1300),
+            CtgMapRec {
+                q_dup: true,
+                ..test_record("chr2", 500, 800, "ctg1", 1500, 1800)
+            },
+            test_record("chr2", 1000, 2000, "ctg2", 0, 1000),
+        ];
+        let ctg2tgt = assign_contigs_to_targets(&records);
+        let buckets = bucket_records(&records, &ctg2tgt, false);
+        assert_eq!(buckets.tgt_to_records["chr1"].len(), 1);
+        assert_eq!(buckets.tgt_to_records["chr2"].len(), 1);
+        assert_eq!(buckets.qry_to_alt_tgt_records["ctg1"].len(), 1);
+        assert_eq!(buckets.tgt_to_alt_qry_records["chr2"].len(), 1);
+        assert_eq!(buckets.q_dup_dropped.len(), 1);
+        assert!(buckets.unassigned_ctgs.is_empty());
+        // the q_dup record on the other target is not an alternative hit
+        let buckets = bucket_records(&records, &ctg2tgt, true);
+        assert_eq!(buckets.qry_to_alt_tgt_records["ctg1"].len(), 1);
+        assert_eq!(buckets.q_dup_dropped.len(), 1);
+    }
+
+    #[test]
+    fn test_highlight_query() {
+        let records = vec![
+            test_record("chr1", 0, 500, "ctg1", 0, 500),
+            test_record("chr1", 500, 1000, "ctg2", 0, 500),
+        ];
+        let (svg_string, _) = get_test_panel(
+            &records,
+            1000,
+            &[("ctg1", 500), ("ctg2", 500)],
+            PanelOptions {
+                scaling_factor: 0.1,
+                highlight_query: Some("ctg2"),
+                ..PanelOptions::default()
+            },
+        );
+        assert_eq!(svg_string.matches(" opacity=\"0.2\"").count(), 1);
+        assert_eq!(svg_string.matches(" opacity=\"1\"").count(), 1);
+        assert!(svg_string.contains("stroke-width=\"2\""));
+    }
+
+    #[test]
+    fn test_sort_targets() {
+        let target_length = [("chr2", 200), ("chr10", 100), ("chrX", 300), ("chr1", 200)]
+            .into_iter()
+            .enumerate()
+            .map(|(id, (t_name, t_len))| (id as u32, t_name.to_string(), t_len))
+            .collect::<Vec<_>>();
+        let get_t_names = |chromosome_sort| {
+            let mut target_length = target_length.clone();
+            sort_targets(&mut target_length, chromosome_sort);
+            target_length.into_iter().map(|v| v.1).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            get_t_names(ChromosomeSort::Natural),
+            vec!["chr1", "chr2", "chr10", "chrX"]
+        );
+        assert_eq!(
+            get_t_names(ChromosomeSort::Lexicographic),
+            vec!["chr1", "chr10", "chr2", "chrX"]
+        );
+        // chr2 and chr1 are the same length and chr2 is first in the input
+        assert_eq!(
+            get_t_names(ChromosomeSort::ByLengthDesc),
+            vec!["chrX", "chr2", "chr1", "chr10"]
+        );
+        assert_eq!(
+            get_t_names(ChromosomeSort::AsInput),
+            vec!["chr2", "chr10", "chrX", "chr1"]
+        );
+    }
+
+    #[test]
+    fn test_length_transform() {
+        assert_eq!(LengthTransform::Linear.apply(1000, 4_000_000), 1000.0);
+        assert_eq!(
+            LengthTransform::Linear.position_factor(1000, 4_000_000),
+            1.0
+        );
+        // the longest target keeps its length and the short ones are longer
+        assert_eq!(
+            LengthTransform::Sqrt.apply(4_000_000, 4_000_000),
+            4_000_000.0
+        );
+        assert_eq!(LengthTransform::Sqrt.apply(40_000, 4_000_000), 400_000.0);
+        assert_eq!(
+            LengthTransform::Sqrt.position_factor(40_000, 4_000_000),
+            10.0
+        );
+        let len = LengthTransform::Log.apply(1000, 4_000_000);
+        assert!((len - 4_000_000.0 * 1001_f64.ln() / 4_000_001_f64.ln()).abs() < 1e-6);
+        assert_eq!(LengthTransform::Log.apply(0, 4_000_000), 0.0);
+        assert_eq!(LengthTransform::Log.position_factor(0, 4_000_000), 1.0);
+        assert_eq!(LengthTransform::Sqrt.apply(100, 0), 100.0);
+    }
+
+    #[test]
+    fn test_scale_bar_length() {
+        assert_eq!(get_scale_bar_length(248_956_422), 10_000_000);
+        assert_eq!(get_scale_bar_length(60_000_000), 5_000_000);
+        assert_eq!(get_scale_bar_length(4_600_000), 100_000);
+        assert_eq!(get_scale_bar_length(50_000), 10_000);
+        assert_eq!(format_bp(5_000_000), "5 Mbp");
+        assert_eq!(format_bp(500_000), "500 kbp");
+        assert_eq!(format_span(120_345), "120.3 kbp");
+        assert_eq!(format_span(2_500_000), "2.5 Mbp");
+        assert_eq!(format_span(500), "500 bp");
+    }
+
+    #[test]
+    fn test_palette_section() {
+        // the palette is split evenly for the assemblies
+        assert_eq!(palette_section(&CMAP, 0, 3), &CMAP[0..32]);
+        assert_eq!(palette_section(&CMAP, 2, 3), &CMAP[64..96]);
+        assert_eq!(palette_section(&OKABE_ITO_CMAP, 9, 10).len(), 1);
+    }
+
+    #[test]
+    fn test_max_records_per_contig() {
+        let mut records = (0..20)
+            .map(|i| {
+                let ts = i * 10_000;
+                test_record("chr1", ts, ts + 1000 + i * 100, "ctg1", ts, ts + 1000)
+            })
+            .collect::<Vec<_>>();
+        let capped = cap_records(&records, 5);
+        assert_eq!(capped.len(), 5);
+        assert!(capped.iter().all(|r| r.te - r.ts >= 1000 + 15 * 100));
+        // a record with the end before the start is put last instead of panicking
+        let reversed = test_record("chr1", 500_000, 400_000, "ctg1", 0, 1000);
+        assert_eq!(cap_records(&[reversed, records[0].clone()], 1)[0].ts, 0);
+
+        records
+            .extend((0..3).map(|i| test_record("chr2", i * 1000, i * 1000 + 500, "ctg2", 0, 500)));
+        let set = CtgMapSet {
+            records,
+            target_length: vec![
+                (0, "chr1".to_string(), 1_000_000),
+                (1, "chr2".to_string(), 10_000),
+            ],
+            query_length: vec![
+                (0, "ctg1".to_string(), 1_000_000),
+                (1, "ctg2".to_string(), 500),
+            ],
+        };
+        let opts = RenderOptions {
+            overview: false,
+            max_records_per_contig: Some(5),
+            ..RenderOptions::default()
+        };
+        let plot = render_ctgmap(&set, &opts).unwrap();
+        assert_eq!(
+            plot.messages,
+            vec!["WARNING: chr1 drops 15 alignment records (--max-records-per-contig 5)"]
+        );
+        // the panels are in the order of the targets, chr2 has fewer records than the cap
+        let svg_string = plot.document.to_string();
+        let ribbon_counts = svg_string
+            .split("class=\"chr_view\"")
+            .skip(1)
+            .map(|panel| panel.matches("class=\"ribbon\"").count())
+            .collect::<Vec<_>>();
+        assert_eq!(ribbon_counts, vec![5, 3]);
+        assert_eq!(plot.layout.targets[0].chr_ribbons.len(), 5);
+    }
+
+    #[test]
+    fn test_drawn_records() {
+        let mut q_dup_record = test_record("chr1", 30_000, 35_000, "ctg1", 30_000, 35_000);
+        q_dup_record.q_dup = true;
+        let set = CtgMapSet {
+            records: vec![
+                test_record("chr1", 0, 1000, "ctg1", 0, 1000),
+                test_record("chr1", 10_000, 12_000, "ctg1", 10_000, 12_000),
+                test_record("chr1", 20_000, 23_000, "ctg1", 20_000, 23_000),
+                q_dup_record,
+                test_record("chr1", 40_000, 60_000, "ctg1", 40_000, 60_000),
+                test_record("chr1", 70_000, 80_000, "ctg1", 70_000, 80_000),
+                test_record("chr2", 0, 1000, "ctg2", 0, 1000),
+            ],
+            target_length: vec![
+                (0, "chr1".to_string(), 100_000),
+                (1, "chr2".to_string(), 10_000),
+            ],
+            query_length: vec![
+                (0, "ctg1".to_string(), 100_000),
+                (1, "ctg2".to_string(), 1000),
+            ],
+        };
+        let opts = RenderOptions {
+            ctg: vec!["chr1".to_string()],
+            region: Some(("chr1".to_string(), 0, 50_000)),
+            max_records_per_contig: Some(2),
+            ..RenderOptions::default()
+        };
+        let drawn_records = get_drawn_records(&set, &opts);
+        assert_eq!(drawn_records.len(), 1);
+        let (t_name, records) = &drawn_records[0];
+        assert_eq!(t_name, "chr1");
+        // the record over the region end is clipped before the cap, the query coordinates are not rebased
+        let mut spans = records
+            .iter()
+            .map(|r| (r.ts, r.te, r.qs, r.qe))
+            .collect::<Vec<_>>();
+        spans.sort();
+        assert_eq!(
+            spans,
+            vec![
+                (20_000, 23_000, 20_000, 23_000),
+                (40_000, 50_000, 40_000, 50_000)
+            ]
+        );
+
+        // the same records are drawn as the ribbons of the panel
+        let plot = render_ctgmap(&set, &opts).unwrap();
+        let target = &plot.layout.targets[0];
+        let chr_scaling_factor = target.chr_scaling_factor.unwrap();
+        let mut ribbon_spans = target
+            .chr_ribbons
+            .iter()
+            .map(|v| {
+                (
+                    (v.ts / chr_scaling_factor).round() as u32,
+                    (v.te / chr_scaling_factor).round() as u32,
+                )
+            })
+            .collect::<Vec<_>>();
+        ribbon_spans.sort();
+        assert_eq!(ribbon_spans, vec![(20_000, 23_000), (40_000, 50_000)]);
+    }
+
+    #[test]
+    fn test_opacity() {
+        let records = vec![test_record("chr1", 0, 1000, "ctg1", 0, 1000)];
+        let query_length = [("ctg1".to_string(), 1_000_000)]
+            .into_iter()
+            .collect::<FxHashMap<_, _>>();
+        let (group, _) = get_chr_svg_group(
+            &(0, "chr1".to_string(), 1_000_000, 0.0, &records),
+            &AssemblyRecords {
+                tgt_to_alt_qry_records: &FxHashMap::default(),
+                qry_to_alt_tgt_records: &FxHashMap::default(),
+                ctg2tgt: &FxHashMap::default(),
+                query_length: &query_length,
+                color_assigner: &ColorAssigner::new(&["ctg1".to_string()], &CMAP),
+            },
+            &PanelOptions {
+                scaling_factor: 0.001,
+                opacity: Opacity {
+                    ribbon: 0.3,
+                    track: 1.0,
+                },
+                ..PanelOptions::default()
+            },
+        )
+        .unwrap();
+        let svg_string = group.to_string();
+        // the reference track, the query track and the ribbon
+        assert_eq!(svg_string.matches(" opacity=\"1\"").count(), 2);
+        assert_eq!(svg_string.matches(" opacity=\"0.3\"").count(), 1);
+        assert!(!svg_string.contains("opacity=\"0.7\""));
+        // the classes for the rules of --external-css
+        for class in ["ref_track", "query_track", "ribbon"] {
+            assert_eq!(svg_string.matches(&format!("class=\"{class}\"")).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_title_group() {
+        let (group, height) = get_title_group(&None, &None, -50.0, Theme::Light, "monospace");
+        assert!(group.is_none());
+        assert_eq!(height, 0.0);
+
+        let title = Some("HG002".to_string());
+        let subtitle = Some("hap1 vs. hg38".to_string());
+        let (group, height) = get_title_group(&title, &None, -50.0, Theme::Light, "monospace");
+        assert_eq!(height, 30.0);
+        assert!(group.unwrap().to_string().contains("\nHG002\n</text>"));
+        let (group, height) = get_title_group(&title, &subtitle, -50.0, Theme::Light, "monospace");
+        assert_eq!(height, 48.0);
+        let group = group.unwrap().to_string();
+        assert_eq!(group.matches("<text").count(), 2);
+        // both are drawn above `y`
+        assert!(group.contains("y=\"-76\""));
+        assert!(group.contains("y=\"-55\""));
+    }
+
+    #[test]
+    fn test_legend_contigs() {
+        let q_names = ["ctg1", "ctg2", "ctg3", "ctg4"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        let q_aligned_bases = [("ctg1", 100), ("ctg2", 5000), ("ctg3", 100), ("ctg4", 2000)]
+            .into_iter()
+            .map(|(q_name, len)| (q_name.to_string(), len))
+            .collect::<FxHashMap<_, _>>();
+        assert_eq!(
+            get_legend_contigs(&q_names, &q_aligned_bases, 3),
+            vec!["ctg1", "ctg2", "ctg4"]
+        );
+        assert_eq!(get_legend_contigs(&q_names, &q_aligned_bases, 30), q_names);
+        assert!(get_legend_contigs(&q_names, &q_aligned_bases, 0).is_empty());
+    }
+
+    #[test]
+    fn test_font_options() {
+        let q_names = vec!["ctg1".to_string(), "ctg2".to_string()];
+        let color_assigner = ColorAssigner::new(&q_names, &CMAP);
+        let group = get_legend_group(
+            &q_names,
+            &color_assigner,
+            Theme::Light,
+            "Helvetica",
+            20.0,
+            Opacity::default(),
+            0.0,
+            0.0,
+        )
+        .to_string();
+        assert_eq!(group.matches("font-family=\"Helvetica\"").count(), 2);
+        assert!(group.contains("font-size=\"20px\""));
+        // the second row is shifted by the row height
+        assert!(group.contains("y=\"28\""));
+
+        let group = get_tick_group(25_000_000, 1e7, 1e-5, 2.0, Theme::Light, "serif").to_string();
+        assert!(group.contains("font-family=\"serif\""));
+        assert!(!group.contains("monospace"));
+    }
+
+    #[test]
+    fn test_chr_document() {
+        let records = vec![
+            test_record("chr1", 0, 1000, "ctg1", 0, 1000),
+            test_record("chr1", 2000, 3000, "ctg1", 1000, 2000),
+            test_record("chr1", 5000, 6000, "ctg2", 0, 1000),
+        ];
+        let query_length = [("ctg1".to_string(), 4000), ("ctg2".to_string(), 3000)]
+            .into_iter()
+            .collect::<FxHashMap<_, _>>();
+        // the query track (7000 bases) is longer than the target
+        let width = get_chr_panel_width(
+            &(0, "chr1".to_string(), 6000, 0.0, &records),
+            &query_length,
+            0.1,
+        );
+        assert_eq!(width, 720.0);
+        let width = get_chr_panel_width(
+            &(0, "chr1".to_string(), 10000, 0.0, &records),
+            &query_length,
+            0.1,
+        );
+        assert_eq!(width, 1020.0);
+
+        let chr_document = get_chr_document(
+            Document::new().set("class", "chr_view"),
+            element::Text::new("chr1"),
+            width,
+            130.0,
+            0.0,
+            Theme::Dark,
+            true,
+            true,
+            None,
+        )
+        .to_string();
+        assert!(chr_document.contains("viewBox=\"0 0 1020 130\""));
+        assert!(chr_document.contains("<rect"));
+        assert!(chr_document.contains("inversion_hatch"));
+        assert!(chr_document.contains("gap_hatch"));
+        assert!(chr_document.contains("class=\"chr_view\""));
+        assert!(!chr_document.contains("<style>"));
+        let chr_document = get_chr_document(
+            Document::new(),
+            element::Text::new("chr1"),
+            width,
+            130.0,
+            70.0,
+            Theme::Light,
+            false,
+            false,
+            Some(".ribbon > title { display: none; }"),
+        )
+        .to_string();
+        assert!(chr_document.contains("viewBox=\"-70 0 1090 130\""));
+        assert!(chr_document.contains("width=\"1090\""));
+        assert!(chr_document.contains("<style>"));
+        assert!(chr_document.contains(".ribbon &gt; title { display: none; }"));
+        assert!(!chr_document.contains("gap_hatch"));
+    }
+
+    #[test]
+    fn test_place_label() {
+        let label = place_label(LabelPosition::Left, "chr1", 31.0, 1000.0).to_string();
+        assert!(label.contains("x=\"-40\""));
+        assert!(label.contains("y=\"31\""));
+        assert!(label.contains("text-anchor=\"start\""));
+        assert!(label.contains("dominant-baseline=\"central\""));
+        assert!(label.contains("chr1"));
+        let label = place_label(LabelPosition::Right, "chr1", 31.0, 1000.0).to_string();
+        assert!(label.contains("x=\"850\""));
+        let label = place_label(LabelPosition::Inside, "chr1", 31.0, 1000.0).to_string();
+        assert!(label.contains("x=\"400\""));
+        assert!(label.contains("text-anchor=\"middle\""));
+    }
+
+    #[test]
+    fn test_svg_box_height() {
+        // 40 targets don't fit in the old fixed 3500 and 2 targets don't need it
+        assert_eq!(get_svg_box_height(40, 130.0, false, true), 5450.0);
+        assert_eq!(get_svg_box_height(2, 130.0, false, true), 510.0);
+        assert_eq!(get_svg_box_height(40, 130.0, true, true), 180.0);
+        assert_eq!(get_svg_box_height(3, 130.0, false, false), 440.0);
+        assert_eq!(get_svg_box_height(1, 100.0, false, false), 180.0);
+    }
+
+    #[test]
+    fn test_gene_arrow() {
+        let genes = [(
+            "chr1".to_string(),
+            vec![
+                GeneFeature {
+                    bgn: 100,
+                    end: 300,
+                    strand: '+',
+                    name: "ABC1".to_string(),
+                },
+                GeneFeature {
+                    bgn: 500,
+                    end: 520,
+                    strand: '-',
+                    name: "mrna2".to_string(),
+                },
+            ],
+        )]
+        .into_iter()
+        .collect::<FxHashMap<_, _>>();
+        assert_eq!(
+            get_gene_arrow_points(10.0, 20.0, 0.0, '+'),
+            "10.0000,-1.5000 18.0000,-1.5000 20.0000,0.0000 18.0000,1.5000 10.0000,1.5000"
+        );
+        assert_eq!(
+            get_gene_arrow_points(10.0, 11.0, 0.0, '-'),
+            "10.0000,0.0000 11.0000,-1.5000 11.0000,-1.5000 11.0000,1.5000 11.0000,1.5000"
+        );
+
+        let records = vec![test_record("chr1", 0, 1000, "ctg1", 0, 1000)];
+        let query_length = [("ctg1".to_string(), 1000)]
+            .into_iter()
+            .collect::<FxHashMap<_, _>>();
+        let (group, _) = get_chr_svg_group(
+            &(0, "chr1".to_string(), 1000, 0.0, &records),
+            &AssemblyRecords {
+                tgt_to_alt_qry_records: &FxHashMap::default(),
+                qry_to_alt_tgt_records: &FxHashMap::default(),
+                ctg2tgt: &FxHashMap::default(),
+                query_length: &query_length,
+                color_assigner: &ColorAssigner::new(&["ctg1".to_string()], &CMAP),
+            },
+            &PanelOptions {
+                scaling_factor: 0.1,
+                genes: Some(&genes),
+                ..PanelOptions::default()
+            },
+        )
+        .unwrap();
+        let svg_string = group.to_string();
+        assert_eq!(svg_string.matches("<polygon").count(), 2);
+        assert!(svg_string.contains("<title>ABC1: 100-300 +</title>"));
+        // ABC1 is 20 pixels wide and labeled, mrna2 is too narrow for a label
+        assert!(svg_string.contains("\nABC1\n</text>"));
+        assert!(!svg_string.contains("\nmrna2\n</text>"));
+    }
+
+    #[test]
+    fn test_plot_document() {
+        let svg_string =
+            get_plot_document((-10.0, -20.0, 100.0, 50.0), 100.0, 50.0, Theme::Dark).to_string();
+        assert!(svg_string.contains("background-color: #1a1a1a"));
+        // the background rectangle starts at the view box origin
+        assert!(svg_string.contains("x=\"-10\""));
+        assert!(svg_string.contains("y=\"-20\""));
+        let svg_string =
+            get_plot_document((0.0, 0.0, 100.0, 50.0), 100.0, 50.0, Theme::Light).to_string();
+        assert!(!svg_string.contains("<rect"));
+    }
+
+    #[test]
+    fn test_write_gzip_html() {
+        use flate2::read::MultiGzDecoder;
+        use std::io::Read;
+        let dir = std::env::temp_dir().join(format!("pgr_render_gzip_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let document = Document::new().add(element::Rectangle::new().set("width", 10));
+
+        let html_path = dir.join("plot.html").to_string_lossy().to_string();
+        write_plot_file(&html_path, &document, false, false, Theme::Light, false).unwrap();
+        let gz_path = dir
+            .join(format!("plot.{}", get_output_extension(false, true)))
+            .to_string_lossy()
+            .to_string();
+        assert!(gz_path.ends_with("plot.html.gz"));
+        write_plot_file(&gz_path, &document, false, true, Theme::Light, false).unwrap();
+
+        let mut html = String::new();
+        MultiGzDecoder::new(File::open(&gz_path).unwrap())
+            .read_to_string(&mut html)
+            .unwrap();
+        assert_eq!(html, std::fs::read_to_string(&html_path).unwrap());
+        assert!(html.starts_with("<html><body>"));
+        assert_eq!(get_output_extension(true, true), "svg");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+/// get (N, M) from the "merged:N:M" annotation of a merged region header line of pgr-merge-svcnd-bed,
+/// N is the number of the labels (haplotypes) and M is the number of the intervals in the region
+pub fn parse_merged_annotation(annotation: &str) -> Option<(usize, u32)> {
+    let mut fields = annotation.strip_prefix("merged:")?.split(':');
+    let number_haplotypes = fields.next()?.parse::<usize>().ok()?;
+    let number_intervals = fields.next()?.parse::<u32>().ok()?;
+    Some((number_haplotypes, number_intervals))
+}
+
+/// get the label and the original svcnd annotation from the "label:annotation:bgn-end:N:count" annotation
+/// of a sub-record of a merged region
+pub fn parse_sub_record_annotation(annotation: &str) -> Option<(&str, &str)> {
+    // the last three fields are fixed, the annotation in the middle may contain ':'
+    let label_annotation = annotation.rsplitn(4, ':').nth(3)?;
+    Some(
+        label_annotation
+            .split_once(':')
+            .unwrap_or((label_annotation, "")),
+    )
+}
+
+/// the single-linkage clusters of the intervals on the same sequence, two intervals are linked if the gap
+/// between them is at most `max_gap` (the overlapping ones have no gap), the intervals should be sorted by
+/// the sequence names and then the start positions, return the cluster ID (from 0) of each interval
+pub fn cluster_intervals(sorted_intervals: &[(u32, u32, String)], max_gap: u32) -> Vec<usize> {
+    let mut cluster_ids = Vec::<usize>::with_capacity(sorted_intervals.len());
+    // the sequence and the end of the current cluster
+    let mut current: Option<(&str, u32)> = None;
+    let mut cluster_id = 0_usize;
+    sorted_intervals.iter().for_each(|(bgn, end, name)| {
+        current = match current {
+            Some((c_name, c_end))
+                if c_name == name.as_str() && *bgn <= c_end.saturating_add(max_gap) =>
+            {
+                Some((c_name, c_end.max(*end)))
+            }
+            Some(_) => {
+                cluster_id += 1;
+                Some((name.as_str(), *end))
+            }
+            None => Some((name.as_str(), *end)),
+        };
+        cluster_ids.push(cluster_id);
+    });
+    cluster_ids
+}
+
+/// the (bgn, end) of an interval and its payload, e.g. the label and the annotation of a bed record
+pub type Interval<T> = ((u32, u32), T);
+
+/// merge the overlapping intervals, or the intervals with a gap of at most `merge_gap` bases in between,
+/// return the (bgn, end) of each merged region and the intervals in it sorted by the start positions
+pub fn merge_overlapping_intervals<T: Clone + Ord>(
+    intervals: &mut [Interval<T>],
+    merge_gap: u32,
+) -> Vec<(u32, u32, Vec<Interval<T>>)> {
+    let mut interval_groups = Vec::<(u32, u32, Vec<Interval<T>>)>::new();
+    if intervals.is_empty() {
+        return interval_groups;
+    }
+
+    intervals.sort();
+    let (mut current_bgn, mut current_end) = intervals.first().unwrap().0;
+
+    let mut current_groups = Vec::<Interval<T>>::new();
+    intervals.iter().for_each(|(interval, payload)| {
+        if current_end.saturating_add(merge_gap) < interval.0 {
+            interval_groups.push((current_bgn, current_end, current_groups.clone()));
+            current_groups.clear();
+            current_groups.push((*interval, payload.clone()));
+            (current_bgn, current_end) = *interval;
+        } else {
+            current_groups.push((*interval, payload.clone()));
+            if current_end < interval.1 {
+                current_end = interval.1;
+            }
+        }
+    });
+    if !current_groups.is_empty() {
+        interval_groups.push((current_bgn, current_end, current_groups.clone()));
+    }
+    interval_groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_annotation() {
+        assert_eq!(parse_merged_annotation("merged:2:3"), Some((2, 3)));
+        assert_eq!(parse_merged_annotation("merged:2"), None);
+        assert_eq!(parse_merged_annotation("hap1:SV:100-300:2:1"), None);
+        assert_eq!(
+            parse_sub_record_annotation("hap1:SV:100-300:2:1"),
+            Some(("hap1", "SV"))
+        );
+        assert_eq!(
+            parse_sub_record_annotation("hap2:DEL:a:b:1000-1100:1:1"),
+            Some(("hap2", "DEL:a:b"))
+        );
+        assert_eq!(
+            parse_sub_record_annotation("hap3:100-300:2:1"),
+            Some(("hap3", ""))
+        );
+        assert_eq!(parse_sub_record_annotation("100-300:2:1"), None);
+    }
+
+    #[test]
+    fn test_cluster_intervals() {
+        let intervals = [
+            (100, 200, "chr1"),
+            (150, 160, "chr1"),
+            (250, 300, "chr1"),
+            (400, 500, "chr1"),
+            (520, 600, "chr2"),
+        ]
+        .into_iter()
+        .map(|(bgn, end, name)| (bgn, end, name.to_string()))
+        .collect::<Vec<_>>();
+        assert_eq!(cluster_intervals(&intervals, 50), vec![0, 0, 0, 1, 2]);
+        assert_eq!(cluster_intervals(&intervals, 100), vec![0, 0, 0, 0, 1]);
+        // the contained interval doesn't shorten the cluster
+        assert_eq!(cluster_intervals(&intervals, 0), vec![0, 0, 1, 2, 3]);
+        assert!(cluster_intervals(&[], 0).is_empty());
+    }
+
+    #[test]
+    fn test_merge_overlapping_intervals() {
+        let intervals = vec![
+            ((100, 200), "hap1"),
+            ((200, 300), "hap2"),
+            ((500, 600), "hap1"),
+            ((1100, 1200), "hap2"),
+        ];
+        let get_bounds = |merge_gap| {
+            merge_overlapping_intervals(&mut intervals.clone(), merge_gap)
+                .into_iter()
+                .map(|(bgn, end, intervals)| (bgn, end, intervals.len()))
+                .collect::<Vec<_>>()
+        };
+        // the abutting intervals are merged without a gap
+        assert_eq!(
+            get_bounds(0),
+            vec![(100, 300, 2), (500, 600, 1), (1100, 1200, 1)]
+        );
+        assert_eq!(
+            get_bounds(199),
+            vec![(100, 300, 2), (500, 600, 1), (1100, 1200, 1)]
+        );
+        assert_eq!(get_bounds(200), vec![(100, 600, 3), (1100, 1200, 1)]);
+        assert_eq!(get_bounds(500), vec![(100, 1200, 4)]);
+        assert_eq!(get_bounds(u32::MAX), vec![(100, 1200, 4)]);
+        assert!(merge_overlapping_intervals::<&str>(&mut [], 0).is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+use pgr_bin::render::calculate_hash;
+use std::process::Command;
+
+const GENERATE_CHR_ALN_PLOT: &str = env!("CARGO_BIN_EXE_pgr-generate-chr-aln-plot");
+
+fn test_hash(name: &str) -> u64 {
+    let output = Command::new(GENERATE_CHR_ALN_PLOT)
+        .args(["--test-hash", name])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .trim()
+        .parse::<u64>()
+        .unwrap()
+}
+
+#[test]
+fn test_hash_across_processes() {
+    // each call is a new process, the hash (and so the color of a contig) doesn't depend on the process
+    assert_eq!(test_hash("chr1"), calculate_hash("chr1"));
+    assert_eq!(test_hash("chr1"), test_hash("chr1"));
+    assert_eq!(test_hash("chr1"), 17496414806553445919);
+    assert_ne!(test_hash("chr1"), test_hash("chr2"));
+}
+
+#[test]
+fn test_hash_is_exclusive() {
+    let output = Command::new(GENERATE_CHR_ALN_PLOT)
+        .args(["--test-hash", "chr1", "a.ctgmap.json", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    // the positional arguments are still required without --test-hash
+    let output = Command::new(GENERATE_CHR_ALN_PLOT).output().unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,134 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const MERGE_SVCND_BED: &str = env!("CARGO_BIN_EXE_pgr-merge-svcnd-bed");
+
+// hap1 and hap2 overlap at chr1:150-200, the INS of hap2 is a region of its own
+const HAP1_BED: &str = "chr1\t100\t200\tSV\n";
+const HAP2_BED: &str = "chr1\t150\t300\tDEL\nchr1\t1000\t1100\tINS\n";
+const MERGED_BED: &str = "chr1\t100\t300\tmerged:2:2\n\
+                          chr1\t100\t200\thap1:SV:100-300:2:1\n\
+                          chr1\t150\t300\thap2:DEL:100-300:2:1\n\
+                          chr1\t1000\t1100\tmerged:1:1\n\
+                          chr1\t1000\t1100\thap2:INS:1000-1100:1:1\n";
+
+/// a directory with hap1.bed and hap2.bed, named by the test and the process so the parallel runs don't share it
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "pgr_merge_svcnd_bed_{}_{}",
+        name,
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("hap1.bed"), HAP1_BED).unwrap();
+    std::fs::write(dir.join("hap2.bed"), HAP2_BED).unwrap();
+    dir
+}
+
+fn manifest(dir: &Path) -> String {
+    ["hap1", "hap2"]
+        .iter()
+        .map(|label| {
+            format!(
+                "{}\t{}\n",
+                label,
+                dir.join(format!("{}.bed", label)).display()
+            )
+        })
+        .collect()
+}
+
+fn run(args: &[&str], stdin: Option<&str>) -> Output {
+    let mut child = Command::new(MERGE_SVCND_BED)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // the tool may exit on an argument error before reading stdin, so a broken pipe is not a test failure
+    let _ = child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.unwrap_or("").as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_manifest_file() {
+    let dir = test_dir("manifest");
+    let manifest_path = dir.join("manifest.tsv");
+    std::fs::write(&manifest_path, manifest(&dir)).unwrap();
+    let output_path = dir.join("merged.bed");
+    let output = run(
+        &[
+            manifest_path.to_str().unwrap(),
+            output_path.to_str().unwrap(),
+        ],
+        None,
+    );
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(&output_path).unwrap(), MERGED_BED);
+
+    let output = run(
+        &[
+            dir.join("missing.tsv").to_str().unwrap(),
+            output_path.to_str().unwrap(),
+        ],
+        None,
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("can't open the input manifest"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_glob() {
+    let dir = test_dir("glob");
+    let output_path = dir.join("merged.out");
+    let output = run(
+        &[
+            dir.join("*.bed").to_str().unwrap(),
+            output_path.to_str().unwrap(),
+            "--glob",
+        ],
+        None,
+    );
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(&output_path).unwrap(), MERGED_BED);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_stdin() {
+    let dir = test_dir("stdin");
+    let output_path = dir.join("merged.bed");
+    let output = run(
+        &["-", output_path.to_str().unwrap(), "--stdin"],
+        Some(&manifest(&dir)),
+    );
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(&output_path).unwrap(), MERGED_BED);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_glob_conflicts_with_stdin() {
+    let dir = test_dir("conflict");
+    let output_path = dir.join("merged.bed");
+    let output = run(
+        &[
+            dir.join("*.bed").to_str().unwrap(),
+            output_path.to_str().unwrap(),
+            "--glob",
+            "--stdin",
+        ],
+        Some(&manifest(&dir)),
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+    assert!(!output_path.exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+const VALIDATE_CTGMAP: &str = env!("CARGO_BIN_EXE_pgr-validate-ctgmap");
+
+/// a ctgmap.json file with `content`, named by the test and the process so the parallel runs don't share it
+fn test_file(name: &str, content: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "pgr_validate_ctgmap_{}_{}",
+        name,
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("test.ctgmap.json");
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_invalid_json() {
+    let path = test_file("invalid_json", "{\"records\": [");
+    let output = Command::new(VALIDATE_CTGMAP).arg(&path).output().unwrap();
+    // an error instead of a panic, which exits with 101
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("JSON error"));
+    assert!(!stderr.contains("panicked"));
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_missing_file() {
+    let path = test_file("missing_file", "").with_file_name("missing.ctgmap.json");
+    let output = Command::new(VALIDATE_CTGMAP).arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("can't open the ctgmap.json file"));
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}